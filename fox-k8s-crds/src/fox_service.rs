@@ -1,116 +1,1630 @@
-use crate::kubernetes_crd::{
-    KubernetesCRD, Metadata, Names, ObjectSchema, OpenAPISchema, Properties, Spec, Version,
+use crate::schema::{self, ValidationRule};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceConversion, CustomResourceDefinition, ServiceReference, WebhookClientConfig,
+    WebhookConversion,
 };
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::CustomResource;
-use schemars::gen::{SchemaGenerator, SchemaSettings};
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub mod builder;
+mod defaults;
+pub mod v1beta1;
+pub mod validation;
+
+/// Struct corresponding to the Status (`status`) part of the `FoxService` resource. Written by the
+/// operator at the end of each reconciliation pass.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct FoxServiceStatus {
+    /// Number of pods created by the workload of this service
     pub replicas: i32,
+    /// Number of pods of the workload of this service that are ready
+    #[serde(default)]
+    pub ready_replicas: i32,
+    /// Number of pods of the workload of this service that are available, i.e., ready for at
+    /// least `minReadySeconds`
+    #[serde(default)]
+    pub available_replicas: i32,
+    /// Label selector of the pods of this service in its serialized form, read through the
+    /// `/scale` subresource by autoscalers targeting the `FoxService`
+    pub selector: Option<String>,
+    /// High-level summary of where the service is in its lifecycle
+    #[serde(default)]
+    pub phase: FoxServicePhase,
+    /// Detailed observations of the service's state
+    #[serde(default)]
+    pub conditions: Vec<FoxServiceCondition>,
+    /// The `metadata.generation` of the resource last successfully applied to its subresources
+    pub observed_generation: Option<i64>,
+    /// Cluster IP address of the Service exposing the HTTP ingress points, once assigned
+    pub service_cluster_ip: Option<String>,
+    /// IP addresses and hostnames assigned to the Service of type `LoadBalancer` by the cloud
+    /// provider, once provisioned
+    #[serde(default)]
+    pub load_balancer_addresses: Vec<String>,
+    /// IP addresses and hostnames the Ingress is reachable at, once assigned by the ingress
+    /// controller
+    #[serde(default)]
+    pub ingress_addresses: Vec<String>,
+}
+
+impl FoxServiceStatus {
+    /// Sets a condition, replacing an existing condition of the same type. The last transition
+    /// time of the existing condition is kept if its status did not change.
+    pub fn set_condition(&mut self, mut condition: FoxServiceCondition) {
+        match self
+            .conditions
+            .iter_mut()
+            .find(|existing| existing.type_ == condition.type_)
+        {
+            Some(existing) => {
+                if existing.status == condition.status {
+                    condition.last_transition_time = existing.last_transition_time.take();
+                }
+                *existing = condition;
+            }
+            None => self.conditions.push(condition),
+        }
+    }
+}
+
+/// Lifecycle phase of a `FoxService`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default, JsonSchema)]
+pub enum FoxServicePhase {
+    /// The service has not been reconciled yet
+    #[default]
+    Pending,
+    /// Subresources are created, but not all pods are ready yet
+    Deploying,
+    /// All pods of the service are up-to-date and ready
+    Ready,
+    /// The last reconciliation failed, see the `Failed` condition for details
+    Failed,
+    /// The workload is scaled down to zero pods, as requested by `suspend`
+    Suspended,
+}
+
+/// A single observation of a `FoxService` state, modelled after the conditions of built-in resources
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceCondition {
+    /// Type of the condition, e.g., `Ready` or `Failed`
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Status of the condition, one of `True`, `False` or `Unknown`
+    pub status: String,
+    /// Machine-readable reason for the condition's last transition
+    pub reason: Option<String>,
+    /// Human-readable details about the condition
+    pub message: Option<String>,
+    /// RFC 3339 timestamp of the last change of `status`
+    pub last_transition_time: Option<String>,
+}
+
+impl FoxServiceCondition {
+    /// All pods of the service are up-to-date and ready
+    pub const READY: &'static str = "Ready";
+    /// The last reconciliation failed, the message contains the error
+    pub const FAILED: &'static str = "Failed";
+    /// The rollout of the deployment makes progress, mirrored from the deployment. `False` once
+    /// `progressDeadlineSeconds` is exceeded
+    pub const PROGRESSING: &'static str = "Progressing";
+    /// A Secret or ConfigMap referenced by the containers doesn't exist, or the specification is
+    /// invalid. The workload is left untouched until the problem is fixed, the message names the
+    /// missing object or key, or the invalid fields
+    pub const DEGRADED: &'static str = "Degraded";
+    /// The certificate requested through `ingress.issuerRef` was issued, mirrored from the
+    /// cert-manager Certificate
+    pub const CERTIFICATE_READY: &'static str = "CertificateReady";
+    /// The workload is scaled down to zero pods and the CronJob is suspended, as requested by
+    /// `suspend`
+    pub const SUSPENDED: &'static str = "Suspended";
+    /// Reconciliation is paused by the `fox.cbopt.com/paused` annotation, subresources are left
+    /// untouched. Removed along with the annotation
+    pub const PAUSED: &'static str = "Paused";
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct FoxServiceContainer {
     /// This is the name the container will be created with
+    #[schemars(schema_with = "crate::schema::dns_label")]
     pub name: String,
     /// Container image reference (including tag)
+    #[schemars(schema_with = "crate::schema::non_empty_string")]
     pub image: String,
+    /// When to pull the container image. Defaults to `IfNotPresent`. If set to `null`, it is
+    /// `Always` for images tagged `latest` (or not tagged at all) and `IfNotPresent` for images with
+    /// a pinned tag or digest
+    #[serde(default = "default_image_pull_policy")]
+    pub image_pull_policy: Option<ImagePullPolicy>,
+    /// Entrypoint of the container, replacing the one of the image. The image entrypoint is used if
+    /// omitted
+    pub command: Option<Vec<String>>,
     /// Command line arguments for running the container
     pub args: Option<Vec<String>>,
-    /// Key value pairs (string, string) for environment variables
-    pub env: Option<HashMap<String, String>>,
-    /// Key value pairs (int, int) -> (actual, exposed) for ports for this container
-    /// All ports are exposed over TCP protocol
-    pub ports: Option<HashMap<i32, i32>>,
+    /// Environment variables, each either set to a literal value or read from a Secret, a
+    /// ConfigMap or a field of the pod. Passed to the container in the given order, so variables
+    /// can refer to the ones before them, e.g., `$(HOST):8080`. Resources stored with the former
+    /// shape, a map of names to values, are still understood by the operator
+    #[serde(
+        default,
+        deserialize_with = "deserialize_env",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub env: Option<Vec<EnvEntry>>,
+    /// ConfigMaps and Secrets whose keys are all imported as environment variables
+    pub env_from: Option<Vec<FoxServiceEnvFrom>>,
+    /// Ports the container listens on. Resources stored with the former shape, a map of host port
+    /// to container port, are still understood by the operator
+    #[serde(
+        default,
+        deserialize_with = "deserialize_ports",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ports: Option<Vec<FoxServicePort>>,
+    /// Periodic check restarting the container when it fails
+    pub liveness_probe: Option<FoxServiceProbe>,
+    /// Periodic check removing the container from service endpoints when it fails
+    pub readiness_probe: Option<FoxServiceProbe>,
+    /// Check delaying the other probes until it succeeds once, meant for slow-booting containers
+    pub startup_probe: Option<FoxServiceProbe>,
+    /// Volumes (declared in `volumes` of the service) mounted into the container
+    pub volume_mounts: Option<Vec<FoxServiceVolumeMount>>,
+    /// Privileges and access control settings of the container
+    pub security_context: Option<FoxServiceSecurityContext>,
+    /// Actions run right after the container is started and right before it is stopped
+    pub lifecycle: Option<FoxServiceLifecycle>,
+    /// Whether the container runs to completion on the schedule of `cron`, instead of in the
+    /// long-running pods of the workload. Defaults to false
+    pub batch: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Image pull policy of containers it is omitted for, applied by the API server as well.
+fn default_image_pull_policy() -> Option<ImagePullPolicy> {
+    Some(ImagePullPolicy::IfNotPresent)
+}
+
+impl FoxServiceContainer {
+    /// The image pull policy to use for this container, either as given or defaulted based on the
+    /// tag of the image.
+    pub fn effective_image_pull_policy(&self) -> ImagePullPolicy {
+        self.image_pull_policy.unwrap_or_else(|| {
+            if has_pinned_tag(&self.image) {
+                ImagePullPolicy::IfNotPresent
+            } else {
+                ImagePullPolicy::Always
+            }
+        })
+    }
+}
+
+/// Deserializes the ports of a container either as a list of ports or in the former shape, a map
+/// of host port to container port. The host ports of the former shape are dropped.
+fn deserialize_ports<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<FoxServicePort>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Ports {
+        List(Vec<FoxServicePort>),
+        Map(BTreeMap<i32, i32>),
+    }
+
+    let ports = Option::<Ports>::deserialize(deserializer)?;
+    Ok(ports.map(|ports| match ports {
+        Ports::List(ports) => ports,
+        // Host ports used to be forced onto every port, but are opt-in now.
+        Ports::Map(ports) => ports
+            .into_values()
+            .map(|container_port| FoxServicePort {
+                name: None,
+                container_port,
+                host_port: None,
+                protocol: None,
+                unknown_fields: BTreeMap::new(),
+            })
+            .collect(),
+    }))
+}
+
+/// Deserializes the environment variables of a container either as a list of entries or in the
+/// former shape, a map of names to values ordered by name. Values of the former shape are either
+/// literal strings, `{secretKeyRef: {name: db, key: password}}` or `{fieldRef: spec.nodeName}`.
+fn deserialize_env<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<EnvEntry>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Env {
+        List(Vec<EnvEntry>),
+        Map(BTreeMap<String, EnvValue>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum EnvValue {
+        Literal(String),
+        #[serde(rename_all = "camelCase")]
+        SecretKeyRef {
+            secret_key_ref: SecretKeyRef,
+        },
+        #[serde(rename_all = "camelCase")]
+        FieldRef {
+            field_ref: String,
+        },
+    }
+
+    let env = Option::<Env>::deserialize(deserializer)?;
+    Ok(env.map(|env| match env {
+        Env::List(env) => env,
+        Env::Map(env) => env
+            .into_iter()
+            .map(|(name, value)| match value {
+                EnvValue::Literal(value) => EnvEntry::literal(name, value),
+                EnvValue::SecretKeyRef { secret_key_ref } => EnvEntry {
+                    name,
+                    value: None,
+                    value_from: Some(EnvValueSource {
+                        secret_key_ref: Some(secret_key_ref),
+                        ..EnvValueSource::default()
+                    }),
+                    unknown_fields: BTreeMap::new(),
+                },
+                EnvValue::FieldRef { field_ref } => EnvEntry {
+                    name,
+                    value: None,
+                    value_from: Some(EnvValueSource {
+                        field_ref: Some(FieldRef {
+                            field_path: field_ref,
+                            unknown_fields: BTreeMap::new(),
+                        }),
+                        ..EnvValueSource::default()
+                    }),
+                    unknown_fields: BTreeMap::new(),
+                },
+            })
+            .collect(),
+    }))
+}
+
+/// Checks whether an image reference points to a specific version of the image, i.e., it has a
+/// digest or a tag other than `latest`.
+fn has_pinned_tag(image: &str) -> bool {
+    if image.contains('@') {
+        return true;
+    }
+    // The registry host may contain a port, the tag can only follow the last path segment.
+    let last_segment = image.rsplit('/').next().unwrap_or(image);
+    match last_segment.split_once(':') {
+        Some((_, tag)) => tag != "latest",
+        None => false,
+    }
+}
+
+/// Environment variable of a container, in the shape of the Kubernetes `EnvVar`. Exactly one of
+/// `value` and `valueFrom` must be set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvEntry {
+    /// Name of the variable
+    pub name: String,
+    /// Literal value of the variable. It may refer to variables defined before, e.g., `$(HOST)`
+    pub value: Option<String>,
+    /// Source the value is read from when the container starts
+    pub value_from: Option<EnvValueSource>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl EnvEntry {
+    /// Constructs a variable with a literal value.
+    pub fn literal(name: impl Into<String>, value: impl Into<String>) -> Self {
+        EnvEntry {
+            name: name.into(),
+            value: Some(value.into()),
+            value_from: None,
+            unknown_fields: BTreeMap::new(),
+        }
+    }
+}
+
+/// Source of the value of an environment variable. Exactly one of `secretKeyRef`,
+/// `configMapKeyRef` and `fieldRef` must be set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvValueSource {
+    /// Key of a Secret the value is read from
+    pub secret_key_ref: Option<SecretKeyRef>,
+    /// Key of a ConfigMap the value is read from
+    pub config_map_key_ref: Option<ConfigMapKeyRef>,
+    /// Field of the pod exposed by the Downward API, e.g., the name of the pod
+    pub field_ref: Option<FieldRef>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reference to a single key of a Secret in the namespace of the service.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct SecretKeyRef {
+    /// Name of the Secret
+    pub name: String,
+    /// Key within the Secret
+    pub key: String,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reference to a single key of a ConfigMap in the namespace of the service.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct ConfigMapKeyRef {
+    /// Name of the ConfigMap
+    pub name: String,
+    /// Key within the ConfigMap
+    pub key: String,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reference to a field of the pod.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldRef {
+    /// Path of the field, e.g., `metadata.name` or `status.podIP`
+    pub field_path: String,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Port a container listens on.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServicePort {
+    /// Name of the port, unique within the service. The Service targets named ports by their name
+    pub name: Option<String>,
+    /// Number of the port in the container
+    pub container_port: i32,
+    /// Number of the port on the node the port is exposed at. Rarely needed, as only one pod per
+    /// node can bind a host port
+    pub host_port: Option<i32>,
+    /// Protocol of the port. Defaults to `TCP`
+    pub protocol: Option<PortProtocol>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Network protocol of a port
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, JsonSchema)]
+pub enum PortProtocol {
+    /// Transmission Control Protocol
+    #[serde(rename = "TCP")]
+    Tcp,
+    /// User Datagram Protocol
+    #[serde(rename = "UDP")]
+    Udp,
+    /// Stream Control Transmission Protocol
+    #[serde(rename = "SCTP")]
+    Sctp,
+}
+
+impl PortProtocol {
+    /// Name of the protocol as used by Kubernetes.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PortProtocol::Tcp => "TCP",
+            PortProtocol::Udp => "UDP",
+            PortProtocol::Sctp => "SCTP",
+        }
+    }
+}
+
+/// Import of all keys of a ConfigMap or a Secret as environment variables. Exactly one of
+/// `configMap`, `secret` and `configFiles` must be set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceEnvFrom {
+    /// Name of the ConfigMap to import, in the namespace of the service
+    pub config_map: Option<String>,
+    /// Name of the Secret to import, in the namespace of the service
+    pub secret: Option<String>,
+    /// Import the `configFiles` of the service, each file becoming a variable
+    pub config_files: Option<bool>,
+    /// Prefix prepended to the name of each imported variable
+    pub prefix: Option<String>,
+    /// Whether the container may start if the ConfigMap or Secret doesn't exist
+    pub optional: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// ServiceAccount the pods of a service run as, either an existing one or one created by the
+/// operator.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct FoxServiceAccount {
+    /// Name of the ServiceAccount
+    pub name: String,
+    /// Create the ServiceAccount along with the service and delete it with the service, instead of
+    /// using an existing one
+    pub create: Option<bool>,
+    /// Annotations of the created ServiceAccount, e.g., `eks.amazonaws.com/role-arn`. Requires
+    /// `create`
+    pub annotations: Option<BTreeMap<String, String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Security settings of a container.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceSecurityContext {
+    /// Whether a process can gain more privileges than its parent process
+    pub allow_privilege_escalation: Option<bool>,
+    /// Whether the root filesystem of the container is mounted read-only
+    pub read_only_root_filesystem: Option<bool>,
+    /// Linux capabilities added to and dropped from the defaults of the container runtime
+    pub capabilities: Option<FoxServiceCapabilities>,
+    /// Run the container with all privileges of the host. Requires `allowPrivileged` on the service
+    pub privileged: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Linux capabilities of a container, e.g., `NET_BIND_SERVICE`, or `ALL` to drop them all.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct FoxServiceCapabilities {
+    /// Capabilities to add
+    pub add: Option<Vec<String>>,
+    /// Capabilities to drop
+    pub drop: Option<Vec<String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Security settings of the pods of a service.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServicePodSecurityContext {
+    /// User ID the container processes run as
+    pub run_as_user: Option<i64>,
+    /// Group ID the container processes run as
+    pub run_as_group: Option<i64>,
+    /// Group owning the mounted volumes, added to the groups of the container processes
+    pub fs_group: Option<i64>,
+    /// Refuse to start containers that would run as root
+    pub run_as_non_root: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Hooks run by the kubelet on lifecycle changes of a container.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceLifecycle {
+    /// Run right after the container is started
+    pub post_start: Option<LifecycleHandler>,
+    /// Run before the container is stopped, e.g., `sleep 10` to let load balancers stop sending
+    /// requests first. Counts towards `terminationGracePeriodSeconds` of the service
+    pub pre_stop: Option<LifecycleHandler>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Action of a lifecycle hook. Exactly one of `httpGet` and `exec` must be set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleHandler {
+    /// Send an HTTP GET request to the container
+    pub http_get: Option<HttpGetProbe>,
+    /// Run a command in the container
+    pub exec: Option<ExecProbe>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Health check of a container. Exactly one of `httpGet`, `tcpSocket` and `exec` must be set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceProbe {
+    /// Check succeeds if an HTTP GET request returns a status code in the range 200-399
+    pub http_get: Option<HttpGetProbe>,
+    /// Check succeeds if a TCP connection can be opened
+    pub tcp_socket: Option<TcpSocketProbe>,
+    /// Check succeeds if a command run in the container exits with status 0
+    pub exec: Option<ExecProbe>,
+    /// Number of seconds after the container has started before the first check
+    pub initial_delay_seconds: Option<i32>,
+    /// How often (in seconds) to perform the check
+    pub period_seconds: Option<i32>,
+    /// Number of consecutive failed checks before the check is considered failed
+    pub failure_threshold: Option<i32>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpGetProbe {
+    /// Path to request (e.g., `/healthz`)
+    pub path: String,
+    /// Port of the container to send the request to, either its number or its name
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub port: IntOrString,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TcpSocketProbe {
+    /// Port of the container to connect to, either its number or its name
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub port: IntOrString,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecProbe {
+    /// Command to run in the container, it is not run in a shell
+    pub command: Vec<String>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// A volume available to the containers of the service. Exactly one of `emptyDir`, `configMap`,
+/// `secret`, `persistence` and `configFiles` must be set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceVolume {
+    /// Name of the volume, referenced by the volume mounts of the containers
+    pub name: String,
+    /// Empty directory sharing the lifetime of the pod
+    pub empty_dir: Option<EmptyDirVolume>,
+    /// Files created from the keys of a ConfigMap
+    pub config_map: Option<ConfigMapVolume>,
+    /// Files created from the keys of a Secret
+    pub secret: Option<SecretVolume>,
+    /// Persistent storage, the operator creates a PersistentVolumeClaim for the volume
+    pub persistence: Option<PersistenceVolume>,
+    /// Files created from the `configFiles` of the service
+    pub config_files: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EmptyDirVolume {
+    /// Storage medium backing the directory, `Memory` for a tmpfs. Defaults to the node's disk
+    pub medium: Option<String>,
+    /// Maximum size of the directory as a Kubernetes quantity (e.g., `1Gi`)
+    pub size_limit: Option<String>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigMapVolume {
+    /// Name of the ConfigMap in the namespace of the service
+    pub name: String,
+    /// Whether the pod may start if the ConfigMap doesn't exist
+    pub optional: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretVolume {
+    /// Name of the Secret in the namespace of the service
+    pub secret_name: String,
+    /// Whether the pod may start if the Secret doesn't exist
+    pub optional: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistenceVolume {
+    /// Name of the StorageClass to provision the volume with. Defaults to the cluster's default
+    pub storage_class: Option<String>,
+    /// Requested size of the volume as a Kubernetes quantity (e.g., `10Gi`). Can be increased later
+    pub size: String,
+    /// Ways the volume can be mounted. Defaults to `ReadWriteOnce`
+    pub access_modes: Option<Vec<AccessMode>>,
+    /// Keep the PersistentVolumeClaim when the service is deleted
+    pub retain: Option<bool>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Way a persistent volume can be mounted
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum AccessMode {
+    /// Read-write by a single node
+    ReadWriteOnce,
+    /// Read-only by many nodes
+    ReadOnlyMany,
+    /// Read-write by many nodes
+    ReadWriteMany,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceVolumeMount {
+    /// Name of the volume to mount, as declared in `volumes` of the service
+    pub name: String,
+    /// Path in the container to mount the volume at
+    pub mount_path: String,
+    /// Whether the volume is mounted read-only
+    pub read_only: Option<bool>,
+    /// Path within the volume to mount instead of its root
+    pub sub_path: Option<String>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Toleration of a node taint, matching taints by key, value and effect.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceToleration {
+    /// Taint key to match. An empty key with operator `Exists` matches all taints
+    pub key: Option<String>,
+    /// How the key is matched against the value. Defaults to `Equal`
+    pub operator: Option<TolerationOperator>,
+    /// Taint value to match. Must be omitted for operator `Exists`
+    pub value: Option<String>,
+    /// Taint effect to match. All effects are matched if omitted
+    pub effect: Option<TaintEffect>,
+    /// How long pods stay bound to a node after a matching `NoExecute` taint is added
+    pub toleration_seconds: Option<i64>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Node affinity and pod anti-affinity of the pods of a service.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceAffinity {
+    /// Labels of the nodes the pods are scheduled onto
+    pub node_affinity: Option<FoxServiceNodeAffinity>,
+    /// Pods the pods of this service are kept apart from, e.g., replicas of the service itself
+    pub pod_anti_affinity: Option<FoxServicePodAntiAffinity>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Node affinity, as hard requirements, soft preferences or both.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceNodeAffinity {
+    /// Terms of which a node must satisfy at least one for pods to be scheduled onto it
+    pub required: Option<Vec<FoxServiceNodeSelectorTerm>>,
+    /// Terms that make a node preferred, the weights of all terms satisfied by a node are summed up
+    pub preferred: Option<Vec<PreferredNodeSelectorTerm>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Requirements on node labels, all of which must be satisfied.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceNodeSelectorTerm {
+    /// Requirements on node labels
+    pub match_expressions: Vec<FoxServiceNodeSelectorRequirement>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Node selector term with a weight.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct PreferredNodeSelectorTerm {
+    /// Weight of the term between 1 and 100
+    pub weight: i32,
+    /// Requirements a preferred node satisfies
+    pub preference: FoxServiceNodeSelectorTerm,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Requirement on the value of a node label.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct FoxServiceNodeSelectorRequirement {
+    /// Label key
+    pub key: String,
+    /// Relation between the label and the values
+    pub operator: NodeSelectorOperator,
+    /// Values to compare with. Must be empty for `Exists` and `DoesNotExist` and a single integer
+    /// for `Gt` and `Lt`
+    pub values: Option<Vec<String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Relation between a node label and a list of values
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum NodeSelectorOperator {
+    /// The label value is one of the values
+    In,
+    /// The label value is none of the values
+    NotIn,
+    /// The label is present
+    Exists,
+    /// The label is absent
+    DoesNotExist,
+    /// The label value is greater than the value
+    Gt,
+    /// The label value is less than the value
+    Lt,
+}
+
+/// Pod anti-affinity, as hard requirements, soft preferences or both.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServicePodAntiAffinity {
+    /// Terms all of which must be satisfied for pods to be scheduled onto a node
+    pub required: Option<Vec<FoxServicePodAffinityTerm>>,
+    /// Terms that make a node preferred, the weights of all terms satisfied by a node are summed up
+    pub preferred: Option<Vec<PreferredPodAffinityTerm>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Pods that must not run in the same topology domain, e.g., on the same node.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServicePodAffinityTerm {
+    /// Node label defining the topology domain, e.g., `kubernetes.io/hostname`
+    pub topology_key: String,
+    /// Labels of the pods to keep apart from. Defaults to the labels of the pods of this service
+    pub match_labels: Option<BTreeMap<String, String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Pod affinity term with a weight.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct PreferredPodAffinityTerm {
+    /// Weight of the term between 1 and 100
+    pub weight: i32,
+    /// Pods a preferred node doesn't run in the same topology domain
+    pub term: FoxServicePodAffinityTerm,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Constraint on how unevenly pods may be spread across the domains of a topology.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceTopologySpread {
+    /// Maximum difference in the number of matching pods between any two domains, at least 1
+    pub max_skew: i32,
+    /// Node label defining the topology domain, e.g., `topology.kubernetes.io/zone`
+    pub topology_key: String,
+    /// What to do with a pod that can't be scheduled without violating the constraint
+    pub when_unsatisfiable: UnsatisfiableConstraintAction,
+    /// Labels of the pods to spread. Defaults to the labels of the pods of this service
+    pub match_labels: Option<BTreeMap<String, String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Action taken on a pod that would violate a topology spread constraint
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum UnsatisfiableConstraintAction {
+    /// Don't schedule the pod
+    DoNotSchedule,
+    /// Schedule the pod anyway, preferring domains that reduce the skew
+    ScheduleAnyway,
+}
+
+/// Operator of a toleration
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum TolerationOperator {
+    /// The taint has the key and the value of the toleration
+    Equal,
+    /// The taint has the key of the toleration, with any value
+    Exists,
+}
+
+/// Effect of a node taint
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum TaintEffect {
+    /// New pods are not scheduled onto the node
+    NoSchedule,
+    /// New pods are scheduled onto the node only if there is no other choice
+    PreferNoSchedule,
+    /// Pods are evicted from the node and new pods are not scheduled onto it
+    NoExecute,
+}
+
+/// Strategy of replacing old pods with new ones when the service is updated.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceStrategy {
+    /// Kind of the strategy
+    #[serde(rename = "type")]
+    pub type_: StrategyType,
+    /// Pods created above the number of replicas during a rolling update, either a number or a
+    /// percentage of the replicas (e.g., `25%`, rounded up). Defaults to `25%`. Not supported by the
+    /// `daemonset` workload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub max_surge: Option<IntOrString>,
+    /// Pods that may be unavailable during a rolling update, either a number or a percentage of
+    /// the replicas (e.g., `25%`, rounded down). Defaults to `25%`, or to `1` for the `daemonset`
+    /// workload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub max_unavailable: Option<IntOrString>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Kind of a rollout strategy
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum StrategyType {
+    /// Stop all old pods before starting new ones, e.g., for services backed by a single-writer
+    /// database
+    Recreate,
+    /// Replace old pods with new ones gradually
+    RollingUpdate,
+    /// Replace old pods only once they are deleted by other means. Only supported by the
+    /// `daemonset` workload
+    OnDelete,
+}
+
+/// Kind of workload running the pods of a service
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkloadKind {
+    /// Interchangeable pods managed by a Deployment
+    Deployment,
+    /// Pods with stable names managed by a StatefulSet. Each pod gets its own claim for every
+    /// persistent volume, claims of the `deployment` workload are not carried over
+    StatefulSet,
+    /// One pod on every node managed by a DaemonSet, e.g., for node agents. `replicas` is ignored
+    DaemonSet,
+}
+
+/// Policy for pulling a container image
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum ImagePullPolicy {
+    /// Pull the image every time a container is started
+    Always,
+    /// Pull the image only if it is not present on the node yet
+    IfNotPresent,
+    /// Never pull the image, it must be present on the node
+    Never,
+}
+
+/// Settings of the Service exposing the HTTP ingress points of a service.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceExposure {
+    /// How the Service is exposed. Defaults to `ClusterIP`
+    #[serde(rename = "type", default = "default_service_type")]
+    pub type_: Option<ServiceType>,
+    /// Annotations of the Service, e.g., to configure the cloud load balancer. Keys in the
+    /// `cbopt.com` domain are reserved for the operator
+    pub annotations: Option<BTreeMap<String, String>>,
+    /// Whether requests of a client are routed to the same pod. Defaults to `None`
+    pub session_affinity: Option<FoxServiceSessionAffinity>,
+    /// Whether external traffic is routed to pods on other nodes as well. `Local` preserves the
+    /// client source IP. Only valid with `NodePort` and `LoadBalancer`. Defaults to `Cluster`
+    pub external_traffic_policy: Option<ExternalTrafficPolicy>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Routing of external traffic reaching a node
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum ExternalTrafficPolicy {
+    /// Traffic is routed to pods on any node, hiding the client source IP
+    Cluster,
+    /// Traffic is routed only to pods on the node it reached
+    Local,
+}
+
+/// Routing of the requests of a client to pods
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceSessionAffinity {
+    /// Kind of the session affinity
+    #[serde(rename = "type")]
+    pub type_: SessionAffinityType,
+    /// Seconds a client sticks to a pod since its last request, between 1 and 86400. Only valid
+    /// with `ClientIP`. Defaults to 10800
+    pub timeout_seconds: Option<i32>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Kind of a session affinity
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum SessionAffinityType {
+    /// Requests are routed to any pod
+    None,
+    /// Requests from the same client IP address are routed to the same pod
+    ClientIP,
+}
+
+/// Settings of the Ingress routing requests for the endpoints of the HTTP ingress points to the
+/// Service.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceIngress {
+    /// Name of the IngressClass implementing the Ingress. Defaults to the class configured for the
+    /// operator, if any, or else the default class of the cluster
+    pub class_name: Option<String>,
+    /// TLS certificates for endpoints of the HTTP ingress points, terminated by the ingress
+    /// controller
+    pub tls: Option<Vec<FoxServiceIngressTls>>,
+    /// Name of a cert-manager ClusterIssuer issuing the certificates into the TLS Secrets. Without
+    /// one, the Secrets have to exist already
+    pub cluster_issuer: Option<String>,
+    /// cert-manager issuer of a certificate for all endpoints of the HTTP ingress points, requested
+    /// by the operator through a Certificate. The certificate is stored in the Secret
+    /// `<name>-tls`. Replaces `tls` and `clusterIssuer`
+    pub issuer_ref: Option<FoxServiceIssuerRef>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// DNS record of a service published by external-dns
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceDns {
+    /// Fully qualified name of the record, e.g., `api.example.com`
+    pub hostname: String,
+    /// Time to live of the record in seconds. Defaults to the one configured for external-dns
+    pub ttl: Option<i32>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reference to a cert-manager issuer
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceIssuerRef {
+    /// Name of the issuer
+    pub name: String,
+    /// Kind of the issuer. Defaults to `Issuer`, which resides in the namespace of the service
+    pub kind: Option<IssuerKind>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Kind of a cert-manager issuer
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum IssuerKind {
+    /// Issuer in the namespace of the service
+    Issuer,
+    /// Issuer available to all namespaces
+    ClusterIssuer,
+}
+
+/// Bounds and targets of the horizontal autoscaling of a service. At least one target must be set
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceAutoscaling {
+    /// Lower bound of the number of pods. Defaults to 1
+    pub min_replicas: Option<i32>,
+    /// Upper bound of the number of pods
+    pub max_replicas: i32,
+    /// Average CPU utilization of the pods to maintain, as a percentage of their CPU requests
+    pub target_cpu_utilization: Option<i32>,
+    /// Average memory utilization of the pods to maintain, as a percentage of their memory
+    /// requests
+    pub target_memory_utilization: Option<i32>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Settings of the vertical autoscaling of a service
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceVerticalAutoscaling {
+    /// How the recommended resource requests are applied. Defaults to `Auto`
+    pub update_mode: Option<VerticalUpdateMode>,
+    /// Bounds of the recommended resource requests per container
+    pub container_policies: Option<Vec<FoxServiceContainerPolicy>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Way the recommendations of a VerticalPodAutoscaler are applied
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum VerticalUpdateMode {
+    /// Recommendations are only reported in the status of the VerticalPodAutoscaler
+    Off,
+    /// Recommendations are applied to new pods only
+    Initial,
+    /// Recommendations are applied by evicting pods whose requests are off
+    Auto,
+}
+
+/// Bounds of the resource requests recommended for a container
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceContainerPolicy {
+    /// Name of the container the bounds apply to, `*` for all containers without bounds of their own
+    pub container_name: String,
+    /// Lowest resource requests to recommend
+    pub min_allowed: Option<FoxServiceResourceBounds>,
+    /// Highest resource requests to recommend
+    pub max_allowed: Option<FoxServiceResourceBounds>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Amounts of compute resources as Kubernetes quantities
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceResourceBounds {
+    /// Amount of CPU, e.g., `250m`
+    pub cpu: Option<String>,
+    /// Amount of memory, e.g., `512Mi`
+    pub memory: Option<String>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Schedule of the containers of a service marked as `batch`, run by a CronJob
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceCron {
+    /// When to run the containers, in cron format (e.g., `*/15 * * * *`) or as a macro like
+    /// `@hourly`. Times are in the time zone of the controller manager
+    pub schedule: String,
+    /// What to do if the previous run hasn't finished yet when the next one is due. Defaults to
+    /// `Allow`
+    pub concurrency_policy: Option<ConcurrencyPolicy>,
+    /// Skips subsequent runs, runs already started are not affected. Defaults to false
+    pub suspend: Option<bool>,
+    /// Number of successfully finished runs kept for inspection. Defaults to 3
+    pub successful_jobs_history_limit: Option<i32>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Containers run to completion around changes of the workload of a service
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceHooks {
+    /// Containers run one after another as Jobs whenever the specification changes, before the
+    /// workload is created or updated. A failing hook stops the rollout
+    pub pre_deploy: Option<Vec<FoxServiceContainer>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Handling of runs overlapping with the previous one
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum ConcurrencyPolicy {
+    /// Start the next run regardless
+    Allow,
+    /// Skip the next run
+    Forbid,
+    /// Stop the previous run and start the next one
+    Replace,
+}
+
+/// Labels and annotations of the `FoxService` object copied onto its subresources and pods. Those
+/// set by the operator take precedence, and keys in the `kubectl.kubernetes.io` and `cbopt.com`
+/// domains are never copied. Keys removed from the `FoxService` are left on the subresources
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServicePropagation {
+    /// Whether the labels are copied. Defaults to true
+    pub labels: Option<bool>,
+    /// Whether the annotations are copied. Defaults to true
+    pub annotations: Option<bool>,
+    /// Prefixes of the keys copied, e.g., `team` or `example.com/`. All keys are copied if omitted
+    pub prefixes: Option<Vec<String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Pods of a service that have to remain available during voluntary disruptions. Exactly one of
+/// `minAvailable` and `maxUnavailable` must be set
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceDisruptionBudget {
+    /// Pods that have to remain available, either a number or a percentage of the replicas (e.g.,
+    /// `50%`, rounded up)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub min_available: Option<IntOrString>,
+    /// Pods that may be unavailable, either a number or a percentage of the replicas (e.g.,
+    /// `25%`, rounded up)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub max_unavailable: Option<IntOrString>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Metrics endpoint of a service
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceMetrics {
+    /// Name of the port of the Service serving the metrics, see `httpIngress`
+    pub port: String,
+    /// HTTP path of the metrics. Defaults to `/metrics`
+    pub path: Option<String>,
+    /// How often the metrics are scraped, e.g., `30s`. Defaults to the interval of Prometheus
+    pub interval: Option<String>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// NetworkPolicy allowing traffic to the ports of the containers of a service only
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceNetworkPolicy {
+    /// Sources allowed to reach the ports. Defaults to all sources
+    pub from: Option<Vec<FoxServiceNetworkPolicyPeer>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
+/// Pods allowed to reach the ports of a service. At least one of `namespaceLabels` and `podLabels`
+/// must be set
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceNetworkPolicyPeer {
+    /// Labels of the namespaces of the pods, all namespaces if empty. Defaults to the namespace of
+    /// the service
+    pub namespace_labels: Option<BTreeMap<String, String>>,
+    /// Labels of the pods. Defaults to all pods of the selected namespaces
+    pub pod_labels: Option<BTreeMap<String, String>>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reference to a Gateway of the Gateway API an HTTPRoute is attached to
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceGateway {
+    /// Name of the Gateway
+    pub name: String,
+    /// Namespace of the Gateway. Defaults to the namespace of the service
+    pub namespace: Option<String>,
+    /// Name of the listener of the Gateway to attach to. Defaults to all listeners
+    pub section_name: Option<String>,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// TLS certificate of an endpoint
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceIngressTls {
+    /// Endpoint of an HTTP ingress point the certificate is for
+    pub host: String,
+    /// Name of the `kubernetes.io/tls` Secret holding the certificate and its key
+    pub secret_name: String,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Way of exposing a Service it is omitted for, applied by the API server as well.
+fn default_service_type() -> Option<ServiceType> {
+    Some(ServiceType::ClusterIP)
+}
+
+/// Way a Service is exposed
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, JsonSchema)]
+pub enum ServiceType {
+    /// Reachable only from within the cluster
+    ClusterIP,
+    /// Reachable on a port of every node as well
+    NodePort,
+    /// Reachable through a load balancer provisioned by the cloud provider as well
+    LoadBalancer,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct HttpIngress {
     /// Name of the container from which this ingress be created
     pub container: String,
-    /// Exposed port of the container that will be targeted for this ingress
+    /// Name of the Service port, required by Kubernetes if there are multiple ingress points.
+    /// Defaults to the name of the targeted container port
+    pub name: Option<String>,
+    /// Port of the Service, targeting the container port with the same number unless `targetPort`
+    /// is set
     pub port: i32,
-    /// HTTP endpoint (domain, e.g., `something.example.com` or `example.com`)
+    /// Port of the container targeted for this ingress, either a number or the name of a port
+    /// declared by the container. Defaults to `port`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::int_or_string")]
+    pub target_port: Option<IntOrString>,
+    /// Protocol of the Service port. Defaults to the protocol of the targeted container port, `TCP`
+    /// if the container doesn't declare the port
+    pub protocol: Option<PortProtocol>,
+    /// HTTP endpoint (domain, e.g., `something.example.com` or `example.com`), the host the
+    /// Ingress routes to this port. Ignored for ports not using TCP
     pub endpoint: String,
-    /// Path on the defined endpoint (e.g., `/my-path`
+    /// Path on the defined endpoint (e.g., `/my-path`), matched as a prefix by the Ingress
     pub path: String,
+    /// Fields not recognized, see `FoxServiceSpec::unknown_fields`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Struct corresponding to the Specification (`spec`) part of the `FoxService` resource, directly
 /// reflects context of the `foxservices.cbopt.com` CRD.
 /// The `FoxService` struct will be generated by the `CustomResource` derive macro.
-#[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, Default, JsonSchema)]
 #[kube(
     group = "cbopt.com",
     version = "v1",
     kind = "FoxService",
     singular = "foxservice",
     plural = "foxservices",
+    shortname = "fox",
+    shortname = "fsvc",
     derive = "PartialEq",
     status = "FoxServiceStatus",
+    scale = r#"{"specReplicasPath": ".spec.replicas", "statusReplicasPath": ".status.replicas", "labelSelectorPath": ".status.selector"}"#,
+    printcolumn = r#"{"name": "Desired", "type": "integer", "jsonPath": ".spec.replicas", "description": "Number of pods requested"}"#,
+    printcolumn = r#"{"name": "Ready", "type": "integer", "jsonPath": ".status.readyReplicas", "description": "Number of pods that are ready"}"#,
+    printcolumn = r#"{"name": "Phase", "type": "string", "jsonPath": ".status.phase", "description": "Lifecycle phase of the service"}"#,
+    printcolumn = r#"{"name": "Age", "type": "date", "jsonPath": ".metadata.creationTimestamp"}"#,
     namespaced
 )]
 #[serde(rename_all = "camelCase")]
 pub struct FoxServiceSpec {
     /// Name of the service
+    #[schemars(schema_with = "crate::schema::dns_label")]
     pub name: String,
-    /// Docker image (including the tag)
-    pub replicas: i32,
+    /// Number of pods, ignored while `autoscaling` is set. Defaults to 1. If set to `null`, the
+    /// operator leaves the number of pods of the workload alone, e.g., to an autoscaler managed by
+    /// others. A new workload starts with a single pod then
+    #[serde(default = "default_replicas")]
+    #[schemars(schema_with = "crate::schema::replicas")]
+    pub replicas: Option<i32>,
+    /// Scales the workload down to zero pods and suspends the CronJob, e.g., to park an
+    /// environment overnight. The Service, the configuration and all other subresources are kept.
+    /// Defaults to false
+    pub suspend: Option<bool>,
+    /// Kind of workload running the pods. Defaults to `deployment`
+    pub workload: Option<WorkloadKind>,
     /// A list of containers that will be run in the same network in this service
+    #[schemars(schema_with = "crate::schema::non_empty_list::<FoxServiceContainer>")]
     pub containers: Vec<FoxServiceContainer>,
     /// A list of HTTP ingress points
     pub http_ingress: Option<Vec<HttpIngress>>,
+    /// Settings of the Service exposing the HTTP ingress points
+    pub service: Option<FoxServiceExposure>,
+    /// Settings of the Ingress routing external HTTP traffic to the HTTP ingress points
+    pub ingress: Option<FoxServiceIngress>,
+    /// Gateway of the Gateway API to attach an HTTPRoute for the HTTP ingress points to, instead
+    /// of creating an Ingress
+    pub gateway: Option<FoxServiceGateway>,
+    /// DNS record published by external-dns for the Ingress, or for the Service if there is none
+    pub dns: Option<FoxServiceDns>,
+    /// Restricts the traffic reaching the pods of this service to the declared ports, for
+    /// namespaces denying all traffic by default
+    pub network_policy: Option<FoxServiceNetworkPolicy>,
+    /// Metrics endpoint scraped by Prometheus through a ServiceMonitor of the Prometheus operator
+    pub metrics: Option<FoxServiceMetrics>,
+    /// Scales the number of pods with their resource utilization through a HorizontalPodAutoscaler,
+    /// in place of `replicas`
+    pub autoscaling: Option<FoxServiceAutoscaling>,
+    /// Lets a VerticalPodAutoscaler set the resource requests of the containers from their usage.
+    /// Not supported together with CPU or memory targets of `autoscaling`
+    pub vertical_autoscaling: Option<FoxServiceVerticalAutoscaling>,
+    /// Limits the number of pods evicted at once, e.g., while draining nodes, through a
+    /// PodDisruptionBudget
+    pub disruption_budget: Option<FoxServiceDisruptionBudget>,
+    /// Runs the containers marked as `batch` on a schedule through a CronJob
+    pub cron: Option<FoxServiceCron>,
+    /// Containers run to completion around changes of the workload, e.g., database migrations
+    pub hooks: Option<FoxServiceHooks>,
+    /// A list of volumes the containers can mount
+    pub volumes: Option<Vec<FoxServiceVolume>>,
+    /// Small configuration files by file name, stored in a ConfigMap managed by the operator.
+    /// Containers mount them through a `configFiles` volume or import them through `envFrom`.
+    /// Changing a file rolls the pods
+    pub config_files: Option<BTreeMap<String, String>>,
+    /// Names of `kubernetes.io/dockerconfigjson` Secrets used to pull the images of the containers
+    /// from private registries
+    pub image_pull_secrets: Option<Vec<String>>,
+    /// Labels a node must carry for the pods of this service to be scheduled onto it, e.g.,
+    /// `disktype: ssd`
+    pub node_selector: Option<BTreeMap<String, String>>,
+    /// Taints of nodes the pods of this service tolerate, e.g., to schedule them onto GPU or spot
+    /// node pools
+    pub tolerations: Option<Vec<FoxServiceToleration>>,
+    /// Constraints on the nodes the pods of this service are scheduled onto, relative to node
+    /// labels and to other pods
+    pub affinity: Option<FoxServiceAffinity>,
+    /// Constraints on how evenly the pods of this service are spread across topology domains,
+    /// e.g., availability zones
+    pub topology_spread: Option<Vec<FoxServiceTopologySpread>>,
+    /// Containers run to completion one after another before the main containers are started,
+    /// e.g., to migrate a database schema. They must not declare ports or probes
+    pub init_containers: Option<Vec<FoxServiceContainer>>,
+    /// User, group and other security settings applied to all containers of the pods
+    pub security_context: Option<FoxServicePodSecurityContext>,
+    /// Opt-in required for containers to set `privileged: true` in their security context
+    pub allow_privileged: Option<bool>,
+    /// ServiceAccount the pods run as, e.g., to assume a cloud IAM role. Defaults to the `default`
+    /// ServiceAccount of the namespace
+    pub service_account: Option<FoxServiceAccount>,
+    /// Seconds a pod is given to shut down gracefully before it is killed. Defaults to 30
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::non_negative_i64")]
+    pub termination_grace_period_seconds: Option<i64>,
+    /// PriorityClass of the pods, allowing them to preempt pods of lower priority
+    pub priority_class_name: Option<String>,
+    /// How pods are replaced on updates. Defaults to a rolling update
+    pub strategy: Option<FoxServiceStrategy>,
+    /// Number of old ReplicaSets kept to allow rollbacks. Defaults to 10
+    pub revision_history_limit: Option<i32>,
+    /// Seconds a rollout may take without making progress before it is reported as failed.
+    /// Defaults to 600
+    pub progress_deadline_seconds: Option<i32>,
+    /// Additional labels of the pods, e.g., for service meshes or cost tooling. The labels set by
+    /// the operator take precedence
+    pub pod_labels: Option<BTreeMap<String, String>>,
+    /// Annotations of the pods, e.g., for log shippers. Changing them rolls the pods
+    pub pod_annotations: Option<BTreeMap<String, String>>,
+    /// Copy the labels and annotations of the `FoxService` object onto the subresources and pods,
+    /// e.g., for cost allocation. Changing them rolls the pods
+    pub propagate: Option<FoxServicePropagation>,
+    /// Fields of the specification not recognized by the operator, e.g., misspelled ones. Kept
+    /// rather than rejected, so a single typo doesn't stop the watch of all resources. The API
+    /// server prunes them as well once the CRD is up-to-date, and rejects them on strict field
+    /// validation. A schema with `additionalProperties: false` is not structural, which the API
+    /// server requires of CRDs. The types nested in the specification keep theirs the same way,
+    /// `unknown_field_paths` lists all of them
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
+/// Number of pods of a service it is omitted for, applied by the API server as well.
+fn default_replicas() -> Option<i32> {
+    Some(1)
+}
+
+/// Path of the conversion webhook served by the operator, converting `FoxService` objects between
+/// the served versions.
+pub const CONVERSION_WEBHOOK_PATH: &str = "/convert";
+
+/// Rules across fields of the `v1` specification the API server checks at admission. The operator
+/// ignores `replicas` while `autoscaling` is set, a number other than the default is a mistake.
+/// The pods of the `statefulset` workload are addressed through its headless Service, a load
+/// balancer in front of them is a mistake as well.
+const VALIDATION_RULES: [ValidationRule; 2] = [
+    ValidationRule {
+        rule: "!has(self.autoscaling) || !has(self.replicas) || self.replicas == 1",
+        message: "replicas must be unset or 1 while autoscaling is set, the HorizontalPodAutoscaler manages the number of pods",
+    },
+    ValidationRule {
+        rule: "!has(self.workload) || self.workload != 'statefulset' || !has(self.service) || !has(self.service.type) || self.service.type != 'LoadBalancer'",
+        message: "service.type must not be LoadBalancer for the statefulset workload, its pods are addressed through a headless Service",
+    },
+];
+
 impl FoxServiceSpec {
-    pub fn kubernetes_crd() -> KubernetesCRD {
-        let mut schema_settings = SchemaSettings::openapi3();
-        schema_settings.inline_subschemas = true;
-        let schema_generator = SchemaGenerator::new(schema_settings);
-        let schema = schema_generator
-            .clone()
-            .into_root_schema_for::<FoxServiceSpec>()
-            .schema
-            .into();
-        let status_schema = schema_generator
-            .clone()
-            .into_root_schema_for::<FoxServiceStatus>()
+    /// The `foxservices.cbopt.com` CRD serving all versions of the `FoxService` resource. The
+    /// deprecated `v1beta1` version is served next to `v1`, objects are stored as `v1` only, the
+    /// version the operator watches.
+    pub fn kubernetes_crd() -> CustomResourceDefinition {
+        let mut crd = FoxService::crd();
+        let mut deprecated = v1beta1::FoxService::crd().spec.versions.remove(0);
+        deprecated.storage = false;
+        deprecated.deprecated = Some(true);
+        deprecated.deprecation_warning =
+            Some("cbopt.com/v1beta1 FoxService is deprecated, use cbopt.com/v1".to_string());
+        crd.spec.versions.insert(0, deprecated);
+        // Not supported by the derive, lists the services in `kubectl get all`.
+        crd.spec.names.categories = Some(vec!["all".to_string()]);
+        crd
+    }
+
+    /// The `foxservices.cbopt.com` CRD as applied to clusters: `kubernetes_crd` with the
+    /// validation rules across fields attached to the `v1` specification schema.
+    pub fn kubernetes_crd_manifest() -> serde_json::Value {
+        schema::with_validation_rules(&Self::kubernetes_crd(), "v1", &VALIDATION_RULES)
+    }
+
+    /// The `foxservices.cbopt.com` CRD as applied to clusters, like `kubernetes_crd_manifest`,
+    /// but converting objects between the versions through the conversion webhook of the operator.
+    ///
+    /// Note: The CA bundle of the webhook is left for cert-manager or the deployment pipeline to
+    /// inject.
+    ///
+    /// # Arguments
+    /// - `namespace` - Namespace of the Service in front of the webhook server of the operator.
+    /// - `service` - Name of the Service, its port 443 is expected to reach the webhook server.
+    pub fn kubernetes_crd_manifest_with_conversion(
+        namespace: &str,
+        service: &str,
+    ) -> serde_json::Value {
+        let mut crd = Self::kubernetes_crd();
+        crd.spec.conversion = Some(CustomResourceConversion {
+            strategy: "Webhook".to_string(),
+            webhook: Some(WebhookConversion {
+                client_config: Some(WebhookClientConfig {
+                    service: Some(ServiceReference {
+                        namespace: namespace.to_string(),
+                        name: service.to_string(),
+                        path: Some(CONVERSION_WEBHOOK_PATH.to_string()),
+                        port: None,
+                    }),
+                    ca_bundle: None,
+                    url: None,
+                }),
+                conversion_review_versions: vec!["v1".to_string()],
+            }),
+        });
+        schema::with_validation_rules(&crd, "v1", &VALIDATION_RULES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The CRD manifest as written by `fox-operator crd`, read back by the API server.
+    fn manifest_round_trip() -> CustomResourceDefinition {
+        let yaml = serde_yaml::to_string(&FoxServiceSpec::kubernetes_crd_manifest()).unwrap();
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn kubernetes_crd_manifest_is_a_crd() {
+        let crd = manifest_round_trip();
+        assert_eq!(crd.metadata.name.as_deref(), Some("foxservices.cbopt.com"));
+        assert_eq!(crd.spec.group, "cbopt.com");
+        assert_eq!(crd.spec.names.kind, "FoxService");
+        let versions: Vec<_> = crd.spec.versions.iter().map(|v| &v.name[..]).collect();
+        assert_eq!(versions, ["v1beta1", "v1"]);
+        let storage: Vec<_> = crd
+            .spec
+            .versions
+            .iter()
+            .filter(|v| v.storage)
+            .map(|v| &v.name[..])
+            .collect();
+        assert_eq!(storage, ["v1"]);
+        assert!(crd.spec.versions.iter().all(|v| v.served));
+        assert!(crd.spec.versions.iter().all(|v| v
             .schema
-            .into();
-        KubernetesCRD {
-            api_version: "apiextensions.k8s.io/v1".to_string(),
-            kind: "CustomResourceDefinition".to_string(),
-            metadata: Metadata {
-                name: "foxservices.cbopt.com".to_string(),
-                namespace: "default".to_string(),
-            },
-            spec: Spec {
-                group: "cbopt.com".to_string(),
-                names: Names {
-                    kind: "FoxService".to_string(),
-                    plural: "foxservices".to_string(),
-                    singular: "foxservice".to_string(),
-                    short_names: vec!["fs".to_string()],
-                },
-                scope: "Namespaced".to_string(),
-                versions: vec![Version {
-                    name: "v1".to_string(),
-                    served: true,
-                    storage: true,
-                    schema: OpenAPISchema {
-                        open_apiv3schema: ObjectSchema {
-                            type_: "object".to_string(),
-                            properties: Properties {
-                                spec: schema,
-                                status: Some(status_schema),
-                            },
-                        },
-                    },
-                }],
-            },
-        }
+            .as_ref()
+            .and_then(|s| s.open_api_v3_schema.as_ref())
+            .is_some()));
+    }
+
+    #[test]
+    fn kubernetes_crd_manifest_names_short_names_and_categories() {
+        let yaml = serde_yaml::to_string(&FoxServiceSpec::kubernetes_crd_manifest()).unwrap();
+        let manifest: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        let names = &manifest["spec"]["names"];
+        let short_names: Vec<String> = serde_yaml::from_value(names["shortNames"].clone()).unwrap();
+        assert_eq!(short_names, ["fox", "fsvc"]);
+        let categories: Vec<String> = serde_yaml::from_value(names["categories"].clone()).unwrap();
+        assert_eq!(categories, ["all"]);
     }
 }
@@ -0,0 +1,346 @@
+use super::validation::FieldError;
+use super::{
+    default_image_pull_policy, default_replicas, EnvEntry, EnvValueSource, FoxServiceContainer,
+    FoxServiceExposure, FoxServiceLifecycle, FoxServicePort, FoxServiceProbe, FoxServiceSpec,
+    FoxServiceStrategy, FoxServiceVolume, FoxServiceVolumeMount, HttpIngress, ImagePullPolicy,
+    PortProtocol, StrategyType, WorkloadKind,
+};
+use std::collections::BTreeMap;
+
+/// Builds a `FoxServiceSpec` step by step, starting from the same defaults a specification
+/// deserialized from an empty object gets, e.g.,
+/// `FoxServiceSpec::builder().name("web").container(|c| c.image("nginx").port(80)).build()`.
+#[derive(Debug, Clone)]
+pub struct FoxServiceSpecBuilder {
+    spec: FoxServiceSpec,
+}
+
+impl FoxServiceSpec {
+    /// Starts building a specification, see `FoxServiceSpecBuilder`.
+    pub fn builder() -> FoxServiceSpecBuilder {
+        FoxServiceSpecBuilder {
+            spec: FoxServiceSpec {
+                replicas: default_replicas(),
+                ..FoxServiceSpec::default()
+            },
+        }
+    }
+}
+
+impl FoxServiceSpecBuilder {
+    /// Sets the name of the service.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.spec.name = name.into();
+        self
+    }
+
+    /// Sets the number of pods.
+    pub fn replicas(mut self, replicas: i32) -> Self {
+        self.spec.replicas = Some(replicas);
+        self
+    }
+
+    /// Scales the workload down to zero pods and suspends the batch containers, or resumes them.
+    pub fn suspend(mut self, suspend: bool) -> Self {
+        self.spec.suspend = Some(suspend);
+        self
+    }
+
+    /// Sets the kind of workload the pods are run by.
+    pub fn workload(mut self, workload: WorkloadKind) -> Self {
+        self.spec.workload = Some(workload);
+        self
+    }
+
+    /// Adds a container to the pods.
+    ///
+    /// # Arguments
+    /// - `build` - Configures the container. Unless named, the container is named after the
+    ///   service.
+    pub fn container(
+        mut self,
+        build: impl FnOnce(FoxServiceContainerBuilder) -> FoxServiceContainerBuilder,
+    ) -> Self {
+        self.spec
+            .containers
+            .push(build(FoxServiceContainerBuilder::new()).container);
+        self
+    }
+
+    /// Adds an init container to the pods.
+    ///
+    /// # Arguments
+    /// - `build` - Configures the init container. Unless named, it is named after the service.
+    pub fn init_container(
+        mut self,
+        build: impl FnOnce(FoxServiceContainerBuilder) -> FoxServiceContainerBuilder,
+    ) -> Self {
+        let container = build(FoxServiceContainerBuilder::new()).container;
+        self.spec
+            .init_containers
+            .get_or_insert_with(Vec::new)
+            .push(container);
+        self
+    }
+
+    /// Adds a volume available to the containers.
+    pub fn volume(mut self, volume: FoxServiceVolume) -> Self {
+        self.spec.volumes.get_or_insert_with(Vec::new).push(volume);
+        self
+    }
+
+    /// Sets how the Service exposing the HTTP ingress points is configured.
+    pub fn service(mut self, service: FoxServiceExposure) -> Self {
+        self.spec.service = Some(service);
+        self
+    }
+
+    /// Adds an HTTP ingress point.
+    ///
+    /// # Arguments
+    /// - `container` - Name of the container the traffic is routed to.
+    /// - `port` - Exposed port of the container.
+    /// - `endpoint` - Domain of the ingress point, e.g., `example.com`.
+    /// - `path` - Path on the domain, e.g., `/`.
+    pub fn http_ingress(
+        mut self,
+        container: impl Into<String>,
+        port: i32,
+        endpoint: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Self {
+        let ingress = HttpIngress {
+            container: container.into(),
+            name: None,
+            port,
+            target_port: None,
+            protocol: None,
+            endpoint: endpoint.into(),
+            path: path.into(),
+            unknown_fields: BTreeMap::new(),
+        };
+        self.spec
+            .http_ingress
+            .get_or_insert_with(Vec::new)
+            .push(ingress);
+        self
+    }
+
+    /// Adds a label to the pods.
+    pub fn pod_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spec
+            .pod_labels
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds an annotation to the pods.
+    pub fn pod_annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spec
+            .pod_annotations
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets how old pods are replaced with new ones, with the default surge and unavailability.
+    pub fn strategy(mut self, type_: StrategyType) -> Self {
+        self.spec.strategy = Some(FoxServiceStrategy {
+            type_,
+            max_surge: None,
+            max_unavailable: None,
+            unknown_fields: BTreeMap::new(),
+        });
+        self
+    }
+
+    /// Sets the seconds a pod is given to shut down gracefully before it is killed.
+    pub fn termination_grace_period_seconds(mut self, seconds: i64) -> Self {
+        self.spec.termination_grace_period_seconds = Some(seconds);
+        self
+    }
+
+    /// Finishes the specification, checking it with `FoxServiceSpec::validate`.
+    ///
+    /// Returns the specification, or all problems found in it.
+    pub fn build(mut self) -> Result<FoxServiceSpec, Vec<FieldError>> {
+        let name = &self.spec.name;
+        let containers = self
+            .spec
+            .containers
+            .iter_mut()
+            .chain(self.spec.init_containers.iter_mut().flatten());
+        for container in containers.filter(|container| container.name.is_empty()) {
+            container.name = name.clone();
+        }
+        let errors = self.spec.validate();
+        if errors.is_empty() {
+            Ok(self.spec)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Builds a `FoxServiceContainer`, see `FoxServiceSpecBuilder::container`.
+#[derive(Debug, Clone)]
+pub struct FoxServiceContainerBuilder {
+    container: FoxServiceContainer,
+}
+
+impl FoxServiceContainerBuilder {
+    fn new() -> Self {
+        FoxServiceContainerBuilder {
+            container: FoxServiceContainer {
+                name: String::new(),
+                image: String::new(),
+                image_pull_policy: default_image_pull_policy(),
+                command: None,
+                args: None,
+                env: None,
+                env_from: None,
+                ports: None,
+                liveness_probe: None,
+                readiness_probe: None,
+                startup_probe: None,
+                volume_mounts: None,
+                security_context: None,
+                lifecycle: None,
+                batch: None,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Sets the name of the container.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.container.name = name.into();
+        self
+    }
+
+    /// Sets the image reference of the container, including the tag.
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.container.image = image.into();
+        self
+    }
+
+    /// Sets when to pull the image.
+    pub fn image_pull_policy(mut self, policy: ImagePullPolicy) -> Self {
+        self.container.image_pull_policy = Some(policy);
+        self
+    }
+
+    /// Sets the entrypoint of the container, replacing the one of the image.
+    pub fn command<S: Into<String>>(mut self, command: impl IntoIterator<Item = S>) -> Self {
+        self.container.command = Some(command.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Adds a command line argument.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.container
+            .args
+            .get_or_insert_with(Vec::new)
+            .push(arg.into());
+        self
+    }
+
+    /// Adds an environment variable with a literal value.
+    pub fn env(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env_entry(EnvEntry::literal(name, value))
+    }
+
+    /// Adds an environment variable read from a source, e.g., a key of a Secret.
+    pub fn env_value_from(self, name: impl Into<String>, source: EnvValueSource) -> Self {
+        self.env_entry(EnvEntry {
+            name: name.into(),
+            value: None,
+            value_from: Some(source),
+            unknown_fields: BTreeMap::new(),
+        })
+    }
+
+    /// Adds an environment variable, after the ones added before.
+    pub fn env_entry(mut self, entry: EnvEntry) -> Self {
+        self.container.env.get_or_insert_with(Vec::new).push(entry);
+        self
+    }
+
+    /// Adds a TCP port the container listens on.
+    pub fn port(self, container_port: i32) -> Self {
+        self.port_with(FoxServicePort {
+            name: None,
+            container_port,
+            host_port: None,
+            protocol: None,
+            unknown_fields: BTreeMap::new(),
+        })
+    }
+
+    /// Adds a named port the container listens on.
+    pub fn named_port(
+        self,
+        name: impl Into<String>,
+        container_port: i32,
+        protocol: PortProtocol,
+    ) -> Self {
+        self.port_with(FoxServicePort {
+            name: Some(name.into()),
+            container_port,
+            host_port: None,
+            protocol: Some(protocol),
+            unknown_fields: BTreeMap::new(),
+        })
+    }
+
+    /// Adds a port the container listens on, with all of its settings.
+    pub fn port_with(mut self, port: FoxServicePort) -> Self {
+        self.container.ports.get_or_insert_with(Vec::new).push(port);
+        self
+    }
+
+    /// Sets the check restarting the container when it fails.
+    pub fn liveness_probe(mut self, probe: FoxServiceProbe) -> Self {
+        self.container.liveness_probe = Some(probe);
+        self
+    }
+
+    /// Sets the check removing the container from service endpoints when it fails.
+    pub fn readiness_probe(mut self, probe: FoxServiceProbe) -> Self {
+        self.container.readiness_probe = Some(probe);
+        self
+    }
+
+    /// Sets the hooks run by the kubelet when the container is started or stopped.
+    pub fn lifecycle(mut self, lifecycle: FoxServiceLifecycle) -> Self {
+        self.container.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// Mounts a volume declared on the service into the container.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the volume, as passed to `FoxServiceSpecBuilder::volume`.
+    /// - `mount_path` - Path in the container to mount the volume at.
+    pub fn volume_mount(mut self, name: impl Into<String>, mount_path: impl Into<String>) -> Self {
+        let mount = FoxServiceVolumeMount {
+            name: name.into(),
+            mount_path: mount_path.into(),
+            read_only: None,
+            sub_path: None,
+            unknown_fields: BTreeMap::new(),
+        };
+        self.container
+            .volume_mounts
+            .get_or_insert_with(Vec::new)
+            .push(mount);
+        self
+    }
+
+    /// Runs the container to completion on the schedule of `cron`, rather than in the pods.
+    pub fn batch(mut self) -> Self {
+        self.container.batch = Some(true);
+        self
+    }
+}
@@ -0,0 +1,83 @@
+use super::{FoxServicePort, FoxServiceSpec, HttpIngress, PortProtocol, WorkloadKind};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+
+impl FoxServiceSpec {
+    /// Kind of workload running the pods, a Deployment unless `workload` is set.
+    pub fn workload_kind(&self) -> WorkloadKind {
+        self.workload.unwrap_or(WorkloadKind::Deployment)
+    }
+
+    /// Finds the port of the container an HTTP ingress point targets, if the container declares it.
+    ///
+    /// # Arguments
+    /// - `ingress` - HTTP ingress point targeting a port by number or by name
+    pub fn ingress_container_port(&self, ingress: &HttpIngress) -> Option<&FoxServicePort> {
+        self.containers
+            .iter()
+            .filter(|container| container.name == ingress.container)
+            .flat_map(|container| container.ports.iter().flatten())
+            .find(|port| match &ingress.target_port {
+                None => port.container_port == ingress.port,
+                Some(IntOrString::Int(target_port)) => port.container_port == *target_port,
+                Some(IntOrString::String(name)) => port.name.as_ref() == Some(name),
+            })
+    }
+
+    /// Protocol of the Service port of an HTTP ingress point, the protocol of the targeted
+    /// container port unless set explicitly.
+    ///
+    /// # Arguments
+    /// - `ingress` - HTTP ingress point the Service port is created for
+    pub fn ingress_protocol(&self, ingress: &HttpIngress) -> PortProtocol {
+        ingress
+            .protocol
+            .or_else(|| {
+                self.ingress_container_port(ingress)
+                    .and_then(|port| port.protocol)
+            })
+            .unwrap_or(PortProtocol::Tcp)
+    }
+
+    /// Name of the Service port of an HTTP ingress point, the name of the targeted container port
+    /// unless set explicitly.
+    ///
+    /// # Arguments
+    /// - `ingress` - HTTP ingress point the Service port is created for
+    pub fn ingress_port_name(&self, ingress: &HttpIngress) -> Option<String> {
+        ingress.name.clone().or_else(|| {
+            self.ingress_container_port(ingress)
+                .and_then(|port| port.name.clone())
+        })
+    }
+
+    /// HTTP ingress points routed by the Ingress or the HTTPRoute of the service. Both route HTTP
+    /// traffic only, so ports using other protocols than TCP are left out.
+    pub fn routed_ingress(&self) -> Vec<&HttpIngress> {
+        self.http_ingress
+            .iter()
+            .flatten()
+            .filter(|ingress| self.ingress_protocol(ingress) == PortProtocol::Tcp)
+            .collect()
+    }
+
+    /// Sets the fields the operator defaults when omitted to the values it would use, so the
+    /// stored specification shows the effective settings, e.g., through a mutating admission
+    /// webhook. Doesn't change how the specification is reconciled.
+    pub fn apply_defaults(&mut self) {
+        self.workload = Some(self.workload_kind());
+        let init_containers = self.init_containers.iter_mut().flatten();
+        let hooks = self
+            .hooks
+            .iter_mut()
+            .flat_map(|hooks| hooks.pre_deploy.iter_mut())
+            .flatten();
+        let containers = self
+            .containers
+            .iter_mut()
+            .chain(init_containers)
+            .chain(hooks);
+        for container in containers {
+            container.image_pull_policy = Some(container.effective_image_pull_policy());
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use super::{
+    default_image_pull_policy, EnvEntry, FoxService as FoxServiceV1,
+    FoxServiceContainer as FoxServiceContainerV1, FoxServicePort,
+    FoxServiceSpec as FoxServiceSpecV1, FoxServiceStatus, HttpIngress as HttpIngressV1,
+};
+use kube::{CustomResource, Resource};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct FoxServiceContainer {
+    /// This is the name the container will be created with
+    #[schemars(schema_with = "crate::schema::dns_label")]
+    pub name: String,
+    /// Container image reference (including tag)
+    #[schemars(schema_with = "crate::schema::non_empty_string")]
+    pub image: String,
+    /// Command line arguments for running the container
+    pub args: Option<Vec<String>>,
+    /// Key value pairs (string, string) for environment variables
+    pub env: Option<BTreeMap<String, String>>,
+    /// Key value pairs (int, int) -> (actual, exposed) for ports for this container
+    /// All ports are exposed over TCP protocol
+    pub ports: Option<BTreeMap<i32, i32>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct HttpIngress {
+    /// Name of the container from which this ingress be created
+    pub container: String,
+    /// Exposed port of the container that will be targeted for this ingress
+    pub port: i32,
+    /// HTTP endpoint (domain, e.g., `something.example.com` or `example.com`)
+    pub endpoint: String,
+    /// Path on the defined endpoint (e.g., `/my-path`
+    pub path: String,
+}
+
+/// Struct corresponding to the Specification (`spec`) part of the `v1beta1` version of the
+/// `FoxService` resource. Deprecated in favour of `v1`, which accepts these shapes as well: the API
+/// server converts between the versions, through the conversion webhook of the operator if set up.
+#[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[kube(
+    group = "cbopt.com",
+    version = "v1beta1",
+    kind = "FoxService",
+    singular = "foxservice",
+    plural = "foxservices",
+    shortname = "fox",
+    shortname = "fsvc",
+    derive = "PartialEq",
+    status = "FoxServiceStatus",
+    scale = r#"{"specReplicasPath": ".spec.replicas", "statusReplicasPath": ".status.replicas", "labelSelectorPath": ".status.selector"}"#,
+    printcolumn = r#"{"name": "Desired", "type": "integer", "jsonPath": ".spec.replicas", "description": "Number of pods requested"}"#,
+    printcolumn = r#"{"name": "Ready", "type": "integer", "jsonPath": ".status.readyReplicas", "description": "Number of pods that are ready"}"#,
+    printcolumn = r#"{"name": "Phase", "type": "string", "jsonPath": ".status.phase", "description": "Lifecycle phase of the service"}"#,
+    printcolumn = r#"{"name": "Age", "type": "date", "jsonPath": ".metadata.creationTimestamp"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct FoxServiceSpec {
+    /// Name of the service
+    #[schemars(schema_with = "crate::schema::dns_label")]
+    pub name: String,
+    /// Number of pods
+    pub replicas: i32,
+    /// A list of containers that will be run in the same network in this service
+    #[schemars(schema_with = "crate::schema::non_empty_list::<FoxServiceContainer>")]
+    pub containers: Vec<FoxServiceContainer>,
+    /// A list of HTTP ingress points
+    pub http_ingress: Option<Vec<HttpIngress>>,
+}
+
+impl From<FoxServiceContainer> for FoxServiceContainerV1 {
+    fn from(container: FoxServiceContainer) -> Self {
+        FoxServiceContainerV1 {
+            name: container.name,
+            image: container.image,
+            image_pull_policy: default_image_pull_policy(),
+            command: None,
+            args: container.args,
+            env: container.env.map(|env| {
+                env.into_iter()
+                    .map(|(name, value)| EnvEntry::literal(name, value))
+                    .collect()
+            }),
+            env_from: None,
+            // Host ports used to be forced onto every port, but are opt-in now. Only a host port
+            // other than the container port was chosen deliberately and is kept.
+            ports: container.ports.map(|ports| {
+                ports
+                    .into_iter()
+                    .map(|(host_port, container_port)| FoxServicePort {
+                        name: None,
+                        container_port,
+                        host_port: Some(host_port).filter(|port| *port != container_port),
+                        protocol: None,
+                        unknown_fields: BTreeMap::new(),
+                    })
+                    .collect()
+            }),
+            liveness_probe: None,
+            readiness_probe: None,
+            startup_probe: None,
+            volume_mounts: None,
+            security_context: None,
+            unknown_fields: BTreeMap::new(),
+            lifecycle: None,
+            batch: None,
+        }
+    }
+}
+
+impl From<HttpIngress> for HttpIngressV1 {
+    fn from(ingress: HttpIngress) -> Self {
+        HttpIngressV1 {
+            container: ingress.container,
+            name: None,
+            port: ingress.port,
+            target_port: None,
+            protocol: None,
+            endpoint: ingress.endpoint,
+            path: ingress.path,
+            unknown_fields: BTreeMap::new(),
+        }
+    }
+}
+
+/// Converts a `v1beta1` specification to `v1`, the same way the API server does when an object
+/// submitted as `v1beta1` is read as `v1`. Fields introduced by `v1` are left unset.
+impl From<FoxServiceSpec> for FoxServiceSpecV1 {
+    fn from(fs: FoxServiceSpec) -> Self {
+        FoxServiceSpecV1 {
+            name: fs.name,
+            replicas: Some(fs.replicas),
+            containers: fs.containers.into_iter().map(Into::into).collect(),
+            http_ingress: fs
+                .http_ingress
+                .map(|ingress| ingress.into_iter().map(Into::into).collect()),
+            ..FoxServiceSpecV1::default()
+        }
+    }
+}
+
+/// Converts a `FoxService` read as `v1beta1` to `v1`, keeping its metadata and status.
+impl From<FoxService> for FoxServiceV1 {
+    fn from(fox_svc: FoxService) -> Self {
+        let mut converted = FoxServiceV1::new(
+            fox_svc.metadata.name.as_deref().unwrap_or_default(),
+            fox_svc.spec.into(),
+        );
+        converted.metadata = fox_svc.metadata;
+        converted.status = fox_svc.status;
+        converted
+    }
+}
+
+/// Converts a `v1` container to `v1beta1`. Environment variables read from other sources and
+/// settings introduced by `v1` are dropped, ports without a host port are mapped onto themselves.
+impl From<FoxServiceContainerV1> for FoxServiceContainer {
+    fn from(container: FoxServiceContainerV1) -> Self {
+        FoxServiceContainer {
+            name: container.name,
+            image: container.image,
+            args: container.args,
+            env: container.env.map(|env| {
+                env.into_iter()
+                    .filter_map(|entry| Some((entry.name, entry.value?)))
+                    .collect()
+            }),
+            ports: container.ports.map(|ports| {
+                ports
+                    .into_iter()
+                    .map(|port| {
+                        let host_port = port.host_port.unwrap_or(port.container_port);
+                        (host_port, port.container_port)
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+impl From<HttpIngressV1> for HttpIngress {
+    fn from(ingress: HttpIngressV1) -> Self {
+        HttpIngress {
+            container: ingress.container,
+            port: ingress.port,
+            endpoint: ingress.endpoint,
+            path: ingress.path,
+        }
+    }
+}
+
+/// Converts a `v1` specification to `v1beta1`, for clients still reading the deprecated version.
+/// Fields introduced by `v1` are dropped, so a `v1beta1` client writing the object back removes
+/// them. A number of pods left to others is given as the default of 1.
+impl From<FoxServiceSpecV1> for FoxServiceSpec {
+    fn from(fs: FoxServiceSpecV1) -> Self {
+        FoxServiceSpec {
+            name: fs.name,
+            replicas: fs.replicas.unwrap_or(1),
+            containers: fs.containers.into_iter().map(Into::into).collect(),
+            http_ingress: fs
+                .http_ingress
+                .map(|ingress| ingress.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+
+/// Converts a `FoxService` read as `v1` to `v1beta1`, keeping its metadata and status.
+impl From<FoxServiceV1> for FoxService {
+    fn from(fox_svc: FoxServiceV1) -> Self {
+        let mut converted = FoxService::new(
+            fox_svc.metadata.name.as_deref().unwrap_or_default(),
+            fox_svc.spec.into(),
+        );
+        converted.metadata = fox_svc.metadata;
+        converted.status = fox_svc.status;
+        converted
+    }
+}
+
+/// Converts a `FoxService` object to another served API version, as requested by the API server
+/// through the conversion webhook.
+///
+/// # Arguments
+/// - `object` - The object to convert, in the version it is stored or submitted in
+/// - `api_version` - The version to convert it to, e.g., `cbopt.com/v1`
+///
+/// Returns the converted object, or why it couldn't be converted.
+pub fn convert(object: Value, api_version: &str) -> Result<Value, String> {
+    let from = object["apiVersion"].as_str().unwrap_or_default().to_owned();
+    let v1 = FoxServiceV1::api_version(&());
+    let v1beta1 = FoxService::api_version(&());
+    let invalid = |error: serde_json::Error| format!("invalid {} FoxService: {}", from, error);
+    if from == api_version {
+        Ok(object)
+    } else if from == v1beta1 && api_version == v1 {
+        let fox_svc: FoxService = serde_json::from_value(object).map_err(invalid)?;
+        serde_json::to_value(FoxServiceV1::from(fox_svc)).map_err(|error| error.to_string())
+    } else if from == v1 && api_version == v1beta1 {
+        let fox_svc: FoxServiceV1 = serde_json::from_value(object).map_err(invalid)?;
+        serde_json::to_value(FoxService::from(fox_svc)).map_err(|error| error.to_string())
+    } else {
+        Err(format!(
+            "can't convert a {} FoxService to {}",
+            from, api_version
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A `v1beta1` specification setting every field.
+    fn spec() -> FoxServiceSpec {
+        let container = FoxServiceContainer {
+            name: "web".to_string(),
+            image: "nginx:1.21".to_string(),
+            args: Some(vec!["--port".to_string(), "80".to_string()]),
+            env: Some(
+                vec![("LOG_LEVEL", "info"), ("ZONE", "eu-west-1a")]
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            // A host port other than the container port, and one mapped onto itself.
+            ports: Some(vec![(8080, 80), (9090, 9090)].into_iter().collect()),
+        };
+        let sidecar = FoxServiceContainer {
+            name: "proxy".to_string(),
+            image: "envoy:1.18".to_string(),
+            args: None,
+            env: None,
+            ports: None,
+        };
+        FoxServiceSpec {
+            name: "web".to_string(),
+            replicas: 3,
+            containers: vec![container, sidecar],
+            http_ingress: Some(vec![HttpIngress {
+                container: "web".to_string(),
+                port: 80,
+                endpoint: "example.com".to_string(),
+                path: "/".to_string(),
+            }]),
+        }
+    }
+
+    #[test]
+    fn spec_round_trip_through_v1_preserves_all_fields() {
+        let v1 = FoxServiceSpecV1::from(spec());
+
+        assert_eq!(v1.replicas, Some(3));
+        let ports = v1.containers[0].ports.as_ref().unwrap();
+        assert_eq!(ports[0].container_port, 80);
+        assert_eq!(ports[0].host_port, Some(8080));
+        assert_eq!(ports[1].host_port, None);
+        assert_eq!(FoxServiceSpec::from(v1), spec());
+    }
+
+    #[test]
+    fn object_round_trip_through_v1_preserves_metadata_and_status() {
+        let object = json!({
+            "apiVersion": "cbopt.com/v1beta1",
+            "kind": "FoxService",
+            "metadata": {
+                "name": "web",
+                "namespace": "default",
+                "uid": "uid-web",
+                "labels": { "tier": "frontend" },
+                "finalizers": ["foxservices.cbopt.com"]
+            },
+            "spec": serde_json::to_value(spec()).unwrap(),
+            "status": {
+                "replicas": 3,
+                "readyReplicas": 2,
+                "availableReplicas": 2,
+                "phase": "Ready",
+                "conditions": [],
+                "loadBalancerAddresses": [],
+                "ingressAddresses": []
+            }
+        });
+
+        let v1 = convert(object.clone(), "cbopt.com/v1").unwrap();
+        assert_eq!(v1["apiVersion"], "cbopt.com/v1");
+        let v1beta1 = convert(v1, "cbopt.com/v1beta1").unwrap();
+
+        let original: FoxService = serde_json::from_value(object).unwrap();
+        let converted: FoxService = serde_json::from_value(v1beta1).unwrap();
+        assert_eq!(converted, original);
+    }
+}
@@ -0,0 +1,1561 @@
+use super::{
+    EnvEntry, FoxService, FoxServiceAffinity, FoxServiceAutoscaling, FoxServiceContainer,
+    FoxServiceDns, FoxServiceExposure, FoxServiceNodeSelectorTerm, FoxServiceProbe,
+    FoxServiceResourceBounds, FoxServiceSpec, FoxServiceStrategy, FoxServiceToleration,
+    FoxServiceVerticalAutoscaling, FoxServiceVolume, NodeSelectorOperator, PortProtocol,
+    ServiceType, SessionAffinityType, StrategyType, TolerationOperator, WorkloadKind,
+};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool,
+};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Upper bound of the size of the data of a ConfigMap accepted by the API server, one MiB.
+const MAX_CONFIG_FILES_SIZE: usize = 1024 * 1024;
+
+/// Upper bound of the `ClientIP` session affinity timeout accepted by the API server, one day.
+const MAX_SESSION_AFFINITY_TIMEOUT_SECONDS: i32 = 86400;
+
+/// Domain of the labels and annotations managed by the operator.
+pub const RESERVED_DOMAIN: &str = "cbopt.com";
+
+/// Pod fields that may be exposed to containers as environment variables via the Downward API.
+const ENV_FIELD_PATHS: [&str; 8] = [
+    "metadata.name",
+    "metadata.namespace",
+    "metadata.uid",
+    "spec.nodeName",
+    "spec.serviceAccountName",
+    "status.hostIP",
+    "status.podIP",
+    "status.podIPs",
+];
+
+/// Shorthands of common cron schedules accepted by the CronJob controller.
+const CRON_MACROS: [&str; 7] = [
+    "@yearly",
+    "@annually",
+    "@monthly",
+    "@weekly",
+    "@daily",
+    "@midnight",
+    "@hourly",
+];
+
+/// Fields of a cron schedule as `(name, minimum, maximum, value names)`. Names are the values
+/// from the minimum onwards, e.g., `JAN` for 1.
+const CRON_FIELDS: [(&str, u32, u32, &[&str]); 5] = [
+    ("minute", 0, 59, &[]),
+    ("hour", 0, 23, &[]),
+    ("day of month", 1, 31, &[]),
+    (
+        "month",
+        1,
+        12,
+        &[
+            "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+        ],
+    ),
+    (
+        "day of week",
+        0,
+        6,
+        &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"],
+    ),
+];
+
+/// A problem found in a `FoxService` specification, located by the path of the field.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldError {
+    /// Path of the offending field, e.g., `containers[0].env.LOG_LEVEL`
+    pub field: String,
+    /// What is wrong with the field
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl FoxServiceSpec {
+    /// Checks the specification for mistakes that need no access to the cluster to be found, so
+    /// the operator, an admission webhook and command line tools can share the checks. All
+    /// problems are reported at once, rather than the first one only.
+    ///
+    /// Returns the problems found, empty if the specification is valid.
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        // Enforced by the CRD schema as well, unless the CRD of the cluster predates the pattern.
+        check_dns_label(&mut errors, "name", &self.name);
+        if self.containers.is_empty() {
+            errors.push(FieldError::new(
+                "containers",
+                "at least one container is required",
+            ));
+        }
+
+        let volumes: HashSet<&str> = self
+            .volumes
+            .iter()
+            .flatten()
+            .map(|volume| volume.name.as_str())
+            .collect();
+        let mut names = HashSet::new();
+        for (field, container) in self.all_containers() {
+            check_dns_label(&mut errors, &format!("{}.name", field), &container.name);
+            if !names.insert(container.name.as_str()) {
+                errors.push(FieldError::new(
+                    format!("{}.name", field),
+                    format!(
+                        "{} is used by another container, init container or hook",
+                        container.name
+                    ),
+                ));
+            }
+            for (index, entry) in container.env.iter().flatten().enumerate() {
+                check_env_entry(&mut errors, &format!("{}.env[{}]", field, index), entry);
+            }
+            let mounts = container.volume_mounts.iter().flatten().enumerate();
+            for (index, mount) in mounts {
+                if !volumes.contains(mount.name.as_str()) {
+                    errors.push(FieldError::new(
+                        format!("{}.volumeMounts[{}]", field, index),
+                        format!("volume {} is not declared in volumes", mount.name),
+                    ));
+                }
+            }
+        }
+
+        // The containers of a pod share its network, a port can only be bound by one of them.
+        let mut ports = HashSet::new();
+        for (index, container) in self.containers.iter().enumerate() {
+            for (port, spec) in container.ports.iter().flatten().enumerate() {
+                let protocol = spec.protocol.unwrap_or(PortProtocol::Tcp);
+                if !ports.insert((spec.container_port, protocol)) {
+                    errors.push(FieldError::new(
+                        format!("containers[{}].ports[{}].containerPort", index, port),
+                        format!(
+                            "{} is declared by another port of the pod",
+                            spec.container_port
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (index, volume) in self.volumes.iter().flatten().enumerate() {
+            if let Some(persistence) = &volume.persistence {
+                check_quantity(
+                    &mut errors,
+                    format!("volumes[{}].persistence.size", index),
+                    &persistence.size,
+                );
+            }
+        }
+        let policies = self
+            .vertical_autoscaling
+            .iter()
+            .flat_map(|vertical_autoscaling| vertical_autoscaling.container_policies.iter())
+            .flatten()
+            .enumerate();
+        for (index, policy) in policies {
+            let field = format!("verticalAutoscaling.containerPolicies[{}]", index);
+            let bounds = [
+                ("minAllowed", &policy.min_allowed),
+                ("maxAllowed", &policy.max_allowed),
+            ];
+            for (name, bounds) in bounds.iter() {
+                if let Some(bounds) = bounds {
+                    check_bounds(&mut errors, &format!("{}.{}", field, name), bounds);
+                }
+            }
+        }
+
+        self.check_volumes(&mut errors);
+        self.check_pods(&mut errors);
+        self.check_exposure(&mut errors);
+        self.check_scaling(&mut errors);
+        self.check_containers(&mut errors);
+        errors
+    }
+
+    /// Checks the volumes and the config files they may hold.
+    fn check_volumes(&self, errors: &mut Vec<FieldError>) {
+        for (index, volume) in self.volumes.iter().flatten().enumerate() {
+            let field = format!("volumes[{}]", index);
+            check_volume(errors, &field, volume);
+            if volume.config_files == Some(true) && self.config_files.is_none() {
+                errors.push(FieldError::new(
+                    format!("{}.configFiles", field),
+                    "requires configFiles of the service to be set",
+                ));
+            }
+        }
+        self.check_config_files(errors);
+    }
+
+    /// Checks the names of the config files are valid ConfigMap keys, and that the files fit into
+    /// a single ConfigMap.
+    fn check_config_files(&self, errors: &mut Vec<FieldError>) {
+        let files = match &self.config_files {
+            Some(files) => files,
+            None => return,
+        };
+        for name in files.keys() {
+            let valid = !name.is_empty()
+                && name.len() <= 253
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+                && name != "."
+                && name != "..";
+            if !valid {
+                errors.push(FieldError::new(
+                    format!("configFiles.{}", name),
+                    "names must be at most 253 letters, digits, dashes, underscores or dots",
+                ));
+            }
+        }
+        let size: usize = files
+            .iter()
+            .map(|(name, content)| name.len() + content.len())
+            .sum();
+        if size > MAX_CONFIG_FILES_SIZE {
+            errors.push(FieldError::new(
+                "configFiles",
+                format!(
+                    "{} bytes exceed the limit of {} bytes of a ConfigMap",
+                    size, MAX_CONFIG_FILES_SIZE
+                ),
+            ));
+        }
+    }
+
+    /// Checks the settings of the pods and their scheduling.
+    fn check_pods(&self, errors: &mut Vec<FieldError>) {
+        for (index, toleration) in self.tolerations.iter().flatten().enumerate() {
+            check_toleration(errors, &format!("tolerations[{}]", index), toleration);
+        }
+        if let Some(account) = &self.service_account {
+            if account.annotations.is_some() && account.create != Some(true) {
+                errors.push(FieldError::new(
+                    "serviceAccount.annotations",
+                    "only supported for created service accounts, set create: true",
+                ));
+            }
+        }
+        if matches!(self.revision_history_limit, Some(limit) if limit < 0) {
+            errors.push(FieldError::new(
+                "revisionHistoryLimit",
+                "must not be negative",
+            ));
+        }
+        if matches!(self.progress_deadline_seconds, Some(deadline) if deadline < 1) {
+            errors.push(FieldError::new(
+                "progressDeadlineSeconds",
+                "must be at least 1",
+            ));
+        }
+        if let Some(affinity) = &self.affinity {
+            check_affinity(errors, affinity);
+        }
+        for (index, constraint) in self.topology_spread.iter().flatten().enumerate() {
+            if constraint.max_skew < 1 {
+                errors.push(FieldError::new(
+                    format!("topologySpread[{}].maxSkew", index),
+                    format!("must be at least 1, got {}", constraint.max_skew),
+                ));
+            }
+        }
+    }
+
+    /// Checks how the service is exposed: the Service, the HTTP ingress points, the Ingress, the
+    /// DNS record, the network policy and the metrics.
+    fn check_exposure(&self, errors: &mut Vec<FieldError>) {
+        if let Some(service) = &self.service {
+            check_service(errors, service);
+        }
+
+        let peers = self
+            .network_policy
+            .iter()
+            .flat_map(|policy| policy.from.iter().flatten());
+        for (index, peer) in peers.enumerate() {
+            if peer.namespace_labels.is_none() && peer.pod_labels.is_none() {
+                errors.push(FieldError::new(
+                    format!("networkPolicy.from[{}]", index),
+                    "at least one of namespaceLabels and podLabels must be set",
+                ));
+            }
+        }
+
+        if self.gateway.is_some() && self.ingress.is_some() {
+            errors.push(FieldError::new(
+                "ingress",
+                "not supported for services routed through a gateway",
+            ));
+        }
+        let tls = self
+            .ingress
+            .iter()
+            .flat_map(|ingress| ingress.tls.iter().flatten());
+        let routed = self.routed_ingress();
+        for (index, tls) in tls.enumerate() {
+            if !routed.iter().any(|ingress| ingress.endpoint == tls.host) {
+                errors.push(FieldError::new(
+                    format!("ingress.tls[{}].host", index),
+                    format!("{} is not the endpoint of an HTTP ingress point", tls.host),
+                ));
+            }
+        }
+        if let Some(ingress) = self
+            .ingress
+            .as_ref()
+            .filter(|ingress| ingress.issuer_ref.is_some())
+        {
+            let conflicting = [
+                ("tls", ingress.tls.is_some()),
+                ("clusterIssuer", ingress.cluster_issuer.is_some()),
+            ];
+            for (field, _) in conflicting.iter().filter(|(_, set)| *set) {
+                errors.push(FieldError::new(
+                    format!("ingress.{}", field),
+                    "not supported together with issuerRef",
+                ));
+            }
+            if routed.is_empty() {
+                errors.push(FieldError::new(
+                    "ingress.issuerRef",
+                    "the service has no HTTP ingress points to issue a certificate for",
+                ));
+            }
+        }
+
+        let mut port_names = HashSet::new();
+        for (index, container) in self.containers.iter().enumerate() {
+            for (port, spec) in container.ports.iter().flatten().enumerate() {
+                if let Some(name) = &spec.name {
+                    let field = format!("containers[{}].ports[{}].name", index, port);
+                    check_port_name(errors, &field, name);
+                    if !port_names.insert(name.as_str()) {
+                        errors.push(FieldError::new(
+                            field,
+                            format!("{} is used by another port", name),
+                        ));
+                    }
+                }
+            }
+        }
+        self.check_http_ingress(errors);
+        if let Some(dns) = &self.dns {
+            self.check_dns(errors, dns);
+        }
+
+        if let Some(metrics) = &self.metrics {
+            let ports = self.http_ingress.iter().flatten();
+            if !ports
+                .filter_map(|ingress| self.ingress_port_name(ingress))
+                .any(|name| name == metrics.port)
+            {
+                errors.push(FieldError::new(
+                    "metrics.port",
+                    format!("the service has no port named {}", metrics.port),
+                ));
+            }
+        }
+    }
+
+    /// Checks the HTTP ingress points target declared containers and container ports, and that
+    /// the names of the Service ports are valid and unique.
+    fn check_http_ingress(&self, errors: &mut Vec<FieldError>) {
+        let mut names = HashSet::new();
+        for (index, ingress) in self.http_ingress.iter().flatten().enumerate() {
+            let field = format!("httpIngress[{}]", index);
+            match self
+                .containers
+                .iter()
+                .find(|container| container.name == ingress.container)
+            {
+                None => errors.push(FieldError::new(
+                    format!("{}.container", field),
+                    format!("there is no container named {}", ingress.container),
+                )),
+                Some(container) if container.batch == Some(true) => errors.push(FieldError::new(
+                    format!("{}.container", field),
+                    format!(
+                        "{} is marked as batch and doesn't keep running",
+                        ingress.container
+                    ),
+                )),
+                Some(_) => {}
+            }
+            if let Some(name) = &ingress.name {
+                check_port_name(errors, &format!("{}.name", field), name);
+                if !names.insert(name.as_str()) {
+                    errors.push(FieldError::new(
+                        format!("{}.name", field),
+                        format!("{} is used by another ingress point", name),
+                    ));
+                }
+            }
+            if !ingress.path.starts_with('/') {
+                errors.push(FieldError::new(
+                    format!("{}.path", field),
+                    format!("{} must start with /", ingress.path),
+                ));
+            }
+            let container_port = self.ingress_container_port(ingress);
+            if let Some(IntOrString::String(name)) = &ingress.target_port {
+                if container_port.is_none() {
+                    errors.push(FieldError::new(
+                        format!("{}.targetPort", field),
+                        format!(
+                            "container {} declares no port named {}",
+                            ingress.container, name
+                        ),
+                    ));
+                }
+            }
+            let port_protocol =
+                container_port.map(|port| port.protocol.unwrap_or(PortProtocol::Tcp));
+            if let (Some(protocol), Some(port_protocol)) = (ingress.protocol, port_protocol) {
+                if protocol != port_protocol {
+                    errors.push(FieldError::new(
+                        format!("{}.protocol", field),
+                        format!(
+                            "{} doesn't match the protocol {} of the targeted container port",
+                            protocol.as_str(),
+                            port_protocol.as_str()
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Checks the DNS record has a plausible name and time to live, and that there is a Service or
+    /// an Ingress to publish it for.
+    fn check_dns(&self, errors: &mut Vec<FieldError>, dns: &FoxServiceDns) {
+        if self.http_ingress.iter().flatten().next().is_none() {
+            errors.push(FieldError::new(
+                "dns",
+                "the service has no HTTP ingress points to publish a record for",
+            ));
+        }
+        let hostname = dns.hostname.strip_suffix('.').unwrap_or(&dns.hostname);
+        let labels: Vec<&str> = hostname.split('.').collect();
+        // A wildcard is only valid as the leftmost label.
+        let valid = hostname.len() <= 253
+            && labels.len() >= 2
+            && labels
+                .iter()
+                .enumerate()
+                .all(|(index, label)| (index == 0 && *label == "*") || is_dns_label(label));
+        if !valid {
+            errors.push(FieldError::new(
+                "dns.hostname",
+                format!(
+                    "{} is not a fully qualified DNS name of lowercase labels",
+                    dns.hostname
+                ),
+            ));
+        }
+        if let Some(ttl) = dns.ttl.filter(|ttl| *ttl < 1) {
+            errors.push(FieldError::new(
+                "dns.ttl",
+                format!("must be at least 1 second, got {}", ttl),
+            ));
+        }
+    }
+
+    /// Checks the autoscaling, the disruption budget, the batch containers and the rollout
+    /// strategy, which depend on the kind of workload and the number of pods.
+    fn check_scaling(&self, errors: &mut Vec<FieldError>) {
+        if let Some(autoscaling) = &self.autoscaling {
+            check_autoscaling(errors, autoscaling);
+        }
+        if let Some(vertical_autoscaling) = &self.vertical_autoscaling {
+            self.check_vertical_autoscaling(errors, vertical_autoscaling);
+        }
+
+        self.check_cron(errors);
+        let long_running = self
+            .containers
+            .iter()
+            .any(|container| container.batch != Some(true));
+        if !long_running {
+            let pre_deploy = self
+                .hooks
+                .iter()
+                .flat_map(|hooks| hooks.pre_deploy.iter())
+                .any(|hooks| !hooks.is_empty());
+            let scaled = [
+                ("autoscaling", self.autoscaling.is_some()),
+                ("verticalAutoscaling", self.vertical_autoscaling.is_some()),
+                ("disruptionBudget", self.disruption_budget.is_some()),
+                ("hooks.preDeploy", pre_deploy),
+            ];
+            for (field, _) in scaled.iter().filter(|(_, set)| *set) {
+                errors.push(FieldError::new(
+                    *field,
+                    "all containers are marked as batch, there are no long-running pods",
+                ));
+            }
+        }
+
+        // Unset replicas are left to others, they are assumed to be the single pod a new workload
+        // starts with.
+        let replicas = self.replicas.unwrap_or(1);
+        if let Some(budget) = &self.disruption_budget {
+            let bounds = [
+                ("disruptionBudget.minAvailable", &budget.min_available),
+                ("disruptionBudget.maxUnavailable", &budget.max_unavailable),
+            ];
+            if bounds.iter().filter(|(_, bound)| bound.is_some()).count() != 1 {
+                errors.push(FieldError::new(
+                    "disruptionBudget",
+                    "exactly one of minAvailable and maxUnavailable must be set",
+                ));
+            }
+            for (field, bound) in bounds.iter() {
+                if let Some(bound) = bound {
+                    check_int_or_percent(errors, field, bound, replicas, true);
+                }
+            }
+        }
+
+        let workload = self.workload_kind();
+        let unsupported = match workload {
+            WorkloadKind::Deployment => vec![],
+            WorkloadKind::StatefulSet => vec![
+                ("strategy", self.strategy.is_some()),
+                (
+                    "progressDeadlineSeconds",
+                    self.progress_deadline_seconds.is_some(),
+                ),
+            ],
+            WorkloadKind::DaemonSet => vec![
+                ("autoscaling", self.autoscaling.is_some()),
+                (
+                    "progressDeadlineSeconds",
+                    self.progress_deadline_seconds.is_some(),
+                ),
+            ],
+        };
+        for (field, _) in unsupported.into_iter().filter(|(_, set)| *set) {
+            errors.push(FieldError::new(
+                field,
+                format!(
+                    "not supported by the {} workload",
+                    format!("{:?}", workload).to_lowercase()
+                ),
+            ));
+        }
+        match &self.strategy {
+            Some(strategy) if workload == WorkloadKind::DaemonSet => {
+                check_update_strategy(errors, strategy)
+            }
+            Some(strategy) => check_strategy(errors, strategy, replicas),
+            None => {}
+        }
+    }
+
+    /// Checks vertical autoscaling doesn't compete with the horizontal autoscaler over the same
+    /// resources, and that its container policies refer to main containers of the service.
+    fn check_vertical_autoscaling(
+        &self,
+        errors: &mut Vec<FieldError>,
+        vertical_autoscaling: &FoxServiceVerticalAutoscaling,
+    ) {
+        if let Some(autoscaling) = &self.autoscaling {
+            let targets = [
+                ("targetCpuUtilization", autoscaling.target_cpu_utilization),
+                (
+                    "targetMemoryUtilization",
+                    autoscaling.target_memory_utilization,
+                ),
+            ];
+            if let Some((field, _)) = targets.iter().find(|(_, target)| target.is_some()) {
+                errors.push(FieldError::new(
+                    "verticalAutoscaling",
+                    format!(
+                        "cannot be combined with autoscaling.{}, both would scale on the same resource",
+                        field
+                    ),
+                ));
+            }
+        }
+        let policies = vertical_autoscaling.container_policies.iter().flatten();
+        for (index, policy) in policies.enumerate() {
+            let known = policy.container_name == "*"
+                || self
+                    .containers
+                    .iter()
+                    .any(|container| container.name == policy.container_name);
+            if !known {
+                errors.push(FieldError::new(
+                    format!(
+                        "verticalAutoscaling.containerPolicies[{}].containerName",
+                        index
+                    ),
+                    format!(
+                        "{} is neither a container of the service nor *",
+                        policy.container_name
+                    ),
+                ));
+            }
+        }
+    }
+
+    /// Checks `cron` is set if and only if there are containers marked as `batch`, and that its
+    /// schedule is understood by the CronJob controller.
+    fn check_cron(&self, errors: &mut Vec<FieldError>) {
+        let mut batch = self
+            .containers
+            .iter()
+            .enumerate()
+            .filter(|(_, container)| container.batch == Some(true));
+        let cron = match (&self.cron, batch.next()) {
+            (Some(cron), Some(_)) => cron,
+            (Some(_), None) => {
+                return errors.push(FieldError::new("cron", "no container is marked as batch"))
+            }
+            (None, Some((index, _))) => {
+                return errors.push(FieldError::new(
+                    format!("containers[{}].batch", index),
+                    "requires cron to be set",
+                ))
+            }
+            (None, None) => return,
+        };
+        check_schedule(errors, &cron.schedule);
+        if matches!(cron.successful_jobs_history_limit, Some(limit) if limit < 0) {
+            errors.push(FieldError::new(
+                "cron.successfulJobsHistoryLimit",
+                "must not be negative",
+            ));
+        }
+
+        // The claims of a StatefulSet are bound to its pods, the jobs can't mount them.
+        if self.workload_kind() == WorkloadKind::StatefulSet {
+            let persistent: HashSet<&str> = self
+                .volumes
+                .iter()
+                .flatten()
+                .filter(|volume| volume.persistence.is_some())
+                .map(|volume| volume.name.as_str())
+                .collect();
+            for (index, container) in self.containers.iter().enumerate() {
+                let mounts = container.volume_mounts.iter().flatten().enumerate();
+                for (mount, spec) in mounts {
+                    if container.batch == Some(true) && persistent.contains(spec.name.as_str()) {
+                        errors.push(FieldError::new(
+                            format!("containers[{}].volumeMounts[{}]", index, mount),
+                            format!(
+                                "persistent volume {} of the statefulset workload can't be mounted by batch containers",
+                                spec.name
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the settings of the containers beyond their names, environment variable names and
+    /// volume mounts.
+    fn check_containers(&self, errors: &mut Vec<FieldError>) {
+        let allow_privileged = self.allow_privileged == Some(true);
+        for (field, container) in self.all_containers() {
+            check_env_field_refs(errors, &field, container);
+            check_env_from(errors, &field, container, self.config_files.is_some());
+            check_security_context(errors, &field, container, allow_privileged);
+            check_lifecycle(errors, &field, container);
+        }
+
+        for (index, container) in self.init_containers.iter().flatten().enumerate() {
+            let field = format!("initContainers[{}]", index);
+            if container.batch.is_some() {
+                errors.push(FieldError::new(
+                    format!("{}.batch", field),
+                    "init containers run in the pods of both the workload and the jobs",
+                ));
+            }
+            check_run_to_completion(errors, &field, "init containers", container);
+        }
+        let hooks = self
+            .hooks
+            .iter()
+            .flat_map(|hooks| hooks.pre_deploy.iter())
+            .flatten();
+        for (index, container) in hooks.enumerate() {
+            let field = format!("hooks.preDeploy[{}]", index);
+            if container.batch.is_some() {
+                errors.push(FieldError::new(
+                    format!("{}.batch", field),
+                    "pre-deploy hooks are run by jobs of their own",
+                ));
+            }
+            check_run_to_completion(errors, &field, "pre-deploy hooks", container);
+        }
+
+        for (index, container) in self.containers.iter().enumerate() {
+            let field = format!("containers[{}]", index);
+            let probes = [
+                ("livenessProbe", &container.liveness_probe),
+                ("readinessProbe", &container.readiness_probe),
+                ("startupProbe", &container.startup_probe),
+            ];
+            for (name, probe) in probes.iter() {
+                if let Some(probe) = probe {
+                    check_probe(errors, &format!("{}.{}", field, name), probe);
+                }
+            }
+            check_startup_window(errors, &field, container);
+        }
+    }
+
+    /// Lists the fields of the specification not recognized by the operator by their path, e.g.,
+    /// `containers[0].imagePullPolcy`. They are kept in the `unknown_fields` of the specification
+    /// and of the types nested in it, and found by comparing them with the schema of the CRD.
+    /// Sorted by path.
+    pub fn unknown_field_paths(&self) -> Vec<String> {
+        let spec = serde_json::to_value(self).expect("FoxServiceSpec serializes to JSON");
+        let crd = FoxService::crd();
+        let schema = crd
+            .spec
+            .versions
+            .iter()
+            .filter_map(|version| version.schema.as_ref()?.open_api_v3_schema.as_ref())
+            .filter_map(|schema| schema.properties.as_ref()?.get("spec"))
+            .next()
+            .expect("CRD has a specification schema");
+        let mut paths = Vec::new();
+        collect_unknown_fields(&mut paths, "", &spec, schema);
+        paths.sort();
+        paths
+    }
+
+    /// The init containers, the main containers and the pre-deploy hooks, each with the path of
+    /// its field.
+    fn all_containers(&self) -> impl Iterator<Item = (String, &FoxServiceContainer)> {
+        let init_containers = self.init_containers.iter().flatten().enumerate();
+        let init_containers = init_containers
+            .map(|(index, container)| (format!("initContainers[{}]", index), container));
+        let containers = self.containers.iter().enumerate();
+        let containers =
+            containers.map(|(index, container)| (format!("containers[{}]", index), container));
+        let hooks = self
+            .hooks
+            .iter()
+            .flat_map(|hooks| hooks.pre_deploy.iter())
+            .flatten()
+            .enumerate();
+        let hooks =
+            hooks.map(|(index, container)| (format!("hooks.preDeploy[{}]", index), container));
+        init_containers.chain(containers).chain(hooks)
+    }
+}
+
+/// Collects the paths of the keys of an object its schema doesn't describe, descending into the
+/// known ones, the items of lists and the values of maps.
+fn collect_unknown_fields(
+    paths: &mut Vec<String>,
+    path: &str,
+    value: &Value,
+    schema: &JSONSchemaProps,
+) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match (&schema.properties, &schema.additional_properties) {
+                    (Some(properties), _) => match properties.get(key) {
+                        Some(schema) => collect_unknown_fields(paths, &field, value, schema),
+                        None => paths.push(field),
+                    },
+                    (None, Some(JSONSchemaPropsOrBool::Schema(schema))) => {
+                        collect_unknown_fields(paths, &field, value, schema)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(JSONSchemaPropsOrArray::Schema(schema)) = &schema.items {
+                for (index, item) in items.iter().enumerate() {
+                    collect_unknown_fields(paths, &format!("{}[{}]", path, index), item, schema);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks the name of an environment variable and that its value is given in exactly one way.
+fn check_env_entry(errors: &mut Vec<FieldError>, field: &str, entry: &EnvEntry) {
+    if !is_c_identifier(&entry.name) {
+        errors.push(FieldError::new(
+            format!("{}.name", field),
+            format!(
+                "{} must consist of letters, digits and underscores, not starting with a digit",
+                entry.name
+            ),
+        ));
+    }
+    match (&entry.value, &entry.value_from) {
+        (Some(_), Some(_)) => errors.push(FieldError::new(
+            field,
+            "only one of value and valueFrom may be set",
+        )),
+        (None, None) => errors.push(FieldError::new(
+            field,
+            "one of value and valueFrom is required",
+        )),
+        (None, Some(source)) => {
+            let sources = [
+                source.secret_key_ref.is_some(),
+                source.config_map_key_ref.is_some(),
+                source.field_ref.is_some(),
+            ];
+            if sources.iter().filter(|set| **set).count() != 1 {
+                errors.push(FieldError::new(
+                    format!("{}.valueFrom", field),
+                    "exactly one of secretKeyRef, configMapKeyRef and fieldRef is required",
+                ));
+            }
+        }
+        (Some(_), None) => {}
+    }
+}
+
+/// Checks the quantities of bounds of compute resources.
+fn check_bounds(errors: &mut Vec<FieldError>, field: &str, bounds: &FoxServiceResourceBounds) {
+    if let Some(cpu) = &bounds.cpu {
+        check_quantity(errors, format!("{}.cpu", field), cpu);
+    }
+    if let Some(memory) = &bounds.memory {
+        check_quantity(errors, format!("{}.memory", field), memory);
+    }
+}
+
+/// Checks that a value is a Kubernetes quantity, e.g., `10Gi`, `250m` or `1e3`.
+fn check_quantity(errors: &mut Vec<FieldError>, field: String, quantity: &str) {
+    if !is_quantity(quantity) {
+        errors.push(FieldError::new(
+            field,
+            format!(
+                "{} is not a quantity, e.g., 512Mi, 1.5Gi, 250m or 2",
+                quantity
+            ),
+        ));
+    }
+}
+
+/// Checks whether a value is a Kubernetes quantity: a decimal number, optionally followed by a
+/// binary suffix (`Ki` to `Ei`), a decimal suffix (`n` to `E`) or a decimal exponent.
+fn is_quantity(quantity: &str) -> bool {
+    let unsigned = quantity.strip_prefix(&['+', '-'][..]).unwrap_or(quantity);
+    let end = unsigned
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(unsigned.len());
+    let (number, suffix) = unsigned.split_at(end);
+    let digits = number.chars().filter(char::is_ascii_digit).count();
+    if digits == 0 || number.matches('.').count() > 1 {
+        return false;
+    }
+    const SUFFIXES: [&str; 16] = [
+        "", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei", "n", "u", "m", "k", "M", "G", "T", "P", "E",
+    ];
+    if SUFFIXES.contains(&suffix) {
+        return true;
+    }
+    // A decimal exponent, e.g., `1e3`.
+    match suffix.strip_prefix(&['e', 'E'][..]) {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(&['+', '-'][..]).unwrap_or(exponent);
+            !exponent.is_empty() && exponent.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Checks whether a name is a C identifier, the portable form of environment variable names.
+fn is_c_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Checks whether a name is a DNS-1123 label: at most 63 lowercase alphanumeric characters or
+/// dashes, starting and ending with an alphanumeric character.
+pub fn is_dns_label(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 63
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+/// Checks that a name is a DNS-1123 label, as names of Kubernetes objects and containers derived
+/// from it have to be.
+fn check_dns_label(errors: &mut Vec<FieldError>, field: &str, name: &str) {
+    if !is_dns_label(name) {
+        errors.push(FieldError::new(
+            field,
+            format!(
+                "{} must be at most 63 lowercase letters, digits or dashes, starting and ending with a letter or digit",
+                name
+            ),
+        ));
+    }
+}
+
+/// Checks whether a label or annotation key is in the domain reserved for the operator, e.g.,
+/// `cbopt.com/managed` or `foxservices.cbopt.com/owner`.
+pub fn is_reserved(key: &str) -> bool {
+    match key.split_once('/') {
+        Some((prefix, _)) => {
+            prefix == RESERVED_DOMAIN || prefix.ends_with(&format!(".{}", RESERVED_DOMAIN))
+        }
+        None => false,
+    }
+}
+
+/// Checks that a port name is a valid IANA service name, as required by Kubernetes: at most 15
+/// lowercase alphanumeric characters or dashes, with at least one letter and no leading, trailing
+/// or consecutive dashes.
+fn check_port_name(errors: &mut Vec<FieldError>, field: &str, name: &str) {
+    let valid = !name.is_empty()
+        && name.len() <= 15
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && name.chars().any(|c| c.is_ascii_lowercase())
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--");
+    if !valid {
+        errors.push(FieldError::new(
+            field,
+            format!(
+                "{} must be at most 15 lowercase letters, digits or dashes, with at least one letter",
+                name
+            ),
+        ));
+    }
+}
+
+/// Checks that a volume defines exactly one source.
+fn check_volume(errors: &mut Vec<FieldError>, field: &str, volume: &FoxServiceVolume) {
+    let sources = [
+        volume.empty_dir.is_some(),
+        volume.config_map.is_some(),
+        volume.secret.is_some(),
+        volume.persistence.is_some(),
+        volume.config_files == Some(true),
+    ];
+    if sources.iter().filter(|set| **set).count() != 1 {
+        errors.push(FieldError::new(
+            field,
+            format!(
+                "exactly one of emptyDir, configMap, secret, persistence or configFiles must be set for volume {}",
+                volume.name
+            ),
+        ));
+    }
+}
+
+/// Checks that a toleration matching any value of a taint doesn't specify a value as well.
+fn check_toleration(errors: &mut Vec<FieldError>, field: &str, toleration: &FoxServiceToleration) {
+    if toleration.operator == Some(TolerationOperator::Exists) && toleration.value.is_some() {
+        errors.push(FieldError::new(
+            field,
+            "value must not be set for operator Exists",
+        ));
+    }
+}
+
+/// Checks the weights of preferred affinity terms and the values of node selector requirements.
+fn check_affinity(errors: &mut Vec<FieldError>, affinity: &FoxServiceAffinity) {
+    if let Some(node) = &affinity.node_affinity {
+        for (index, term) in node.required.iter().flatten().enumerate() {
+            let field = format!("affinity.nodeAffinity.required[{}]", index);
+            check_node_selector_term(errors, &field, term);
+        }
+        for (index, term) in node.preferred.iter().flatten().enumerate() {
+            let field = format!("affinity.nodeAffinity.preferred[{}]", index);
+            check_weight(errors, &field, term.weight);
+            check_node_selector_term(errors, &format!("{}.preference", field), &term.preference);
+        }
+    }
+    if let Some(pod) = &affinity.pod_anti_affinity {
+        for (index, term) in pod.preferred.iter().flatten().enumerate() {
+            let field = format!("affinity.podAntiAffinity.preferred[{}]", index);
+            check_weight(errors, &field, term.weight);
+        }
+    }
+}
+
+/// Checks that the weight of a preferred affinity term is within the range accepted by Kubernetes.
+fn check_weight(errors: &mut Vec<FieldError>, field: &str, weight: i32) {
+    if !(1..=100).contains(&weight) {
+        errors.push(FieldError::new(
+            format!("{}.weight", field),
+            format!("must be between 1 and 100, got {}", weight),
+        ));
+    }
+}
+
+/// Checks that each requirement of a node selector term has as many values as its operator needs.
+fn check_node_selector_term(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    term: &FoxServiceNodeSelectorTerm,
+) {
+    for (index, requirement) in term.match_expressions.iter().enumerate() {
+        let values = requirement.values.as_deref().unwrap_or_default();
+        let message = match requirement.operator {
+            NodeSelectorOperator::In | NodeSelectorOperator::NotIn if values.is_empty() => {
+                "values must not be empty for operators In and NotIn"
+            }
+            NodeSelectorOperator::Exists | NodeSelectorOperator::DoesNotExist
+                if !values.is_empty() =>
+            {
+                "values must be empty for operators Exists and DoesNotExist"
+            }
+            NodeSelectorOperator::Gt | NodeSelectorOperator::Lt
+                if values.len() != 1 || values[0].parse::<i64>().is_err() =>
+            {
+                "values must be a single integer for operators Gt and Lt"
+            }
+            _ => continue,
+        };
+        errors.push(FieldError::new(
+            format!("{}.matchExpressions[{}]", field, index),
+            message,
+        ));
+    }
+}
+
+/// Checks the settings of the Service exposing the HTTP ingress points.
+fn check_service(errors: &mut Vec<FieldError>, service: &FoxServiceExposure) {
+    let annotations = service
+        .annotations
+        .iter()
+        .flat_map(|annotations| annotations.keys());
+    for key in annotations.filter(|key| is_reserved(key)) {
+        errors.push(FieldError::new(
+            format!("service.annotations.{}", key),
+            format!(
+                "the {} domain is reserved for the operator",
+                RESERVED_DOMAIN
+            ),
+        ));
+    }
+
+    if service.external_traffic_policy.is_some()
+        && matches!(service.type_, None | Some(ServiceType::ClusterIP))
+    {
+        errors.push(FieldError::new(
+            "service.externalTrafficPolicy",
+            "only valid with NodePort and LoadBalancer services",
+        ));
+    }
+
+    let timeout = service.session_affinity.as_ref().and_then(|affinity| {
+        let timeout_seconds = affinity.timeout_seconds?;
+        Some((affinity, timeout_seconds))
+    });
+    if let Some((affinity, timeout_seconds)) = timeout {
+        if affinity.type_ != SessionAffinityType::ClientIP {
+            errors.push(FieldError::new(
+                "service.sessionAffinity.timeoutSeconds",
+                "only valid with ClientIP affinity",
+            ));
+        }
+        if !(1..=MAX_SESSION_AFFINITY_TIMEOUT_SECONDS).contains(&timeout_seconds) {
+            errors.push(FieldError::new(
+                "service.sessionAffinity.timeoutSeconds",
+                format!(
+                    "must be between 1 and {}",
+                    MAX_SESSION_AFFINITY_TIMEOUT_SECONDS
+                ),
+            ));
+        }
+    }
+}
+
+/// Checks that the bounds of the autoscaling are consistent and that there is something to scale
+/// on.
+fn check_autoscaling(errors: &mut Vec<FieldError>, autoscaling: &FoxServiceAutoscaling) {
+    let min_replicas = autoscaling.min_replicas.unwrap_or(1);
+    if min_replicas < 1 {
+        errors.push(FieldError::new(
+            "autoscaling.minReplicas",
+            "must be at least 1",
+        ));
+    } else if autoscaling.max_replicas < min_replicas {
+        errors.push(FieldError::new(
+            "autoscaling.maxReplicas",
+            format!("must be at least minReplicas ({})", min_replicas),
+        ));
+    }
+    let targets = [
+        ("targetCpuUtilization", autoscaling.target_cpu_utilization),
+        (
+            "targetMemoryUtilization",
+            autoscaling.target_memory_utilization,
+        ),
+    ];
+    if targets.iter().all(|(_, target)| target.is_none()) {
+        errors.push(FieldError::new(
+            "autoscaling",
+            "at least one of targetCpuUtilization and targetMemoryUtilization must be set",
+        ));
+    }
+    for (field, target) in targets.iter() {
+        if matches!(target, Some(target) if *target < 1) {
+            errors.push(FieldError::new(
+                format!("autoscaling.{}", field),
+                "must be at least 1",
+            ));
+        }
+    }
+}
+
+/// Checks that only rolling updates configure a surge and unavailability, and that a rolling
+/// update is able to make progress, i.e., either may create or stop at least one pod.
+fn check_strategy(errors: &mut Vec<FieldError>, strategy: &FoxServiceStrategy, replicas: i32) {
+    match strategy.type_ {
+        StrategyType::OnDelete => {
+            return errors.push(FieldError::new(
+                "strategy.type",
+                "OnDelete is only supported by the daemonset workload",
+            ))
+        }
+        StrategyType::Recreate => {
+            if strategy.max_surge.is_some() || strategy.max_unavailable.is_some() {
+                errors.push(FieldError::new(
+                    "strategy",
+                    "maxSurge and maxUnavailable are only supported for RollingUpdate",
+                ));
+            }
+            return;
+        }
+        StrategyType::RollingUpdate => {}
+    }
+    let default = IntOrString::String("25%".to_string());
+    let max_surge = strategy.max_surge.as_ref().unwrap_or(&default);
+    let max_unavailable = strategy.max_unavailable.as_ref().unwrap_or(&default);
+    let max_surge = check_int_or_percent(errors, "strategy.maxSurge", max_surge, replicas, true);
+    let max_unavailable = check_int_or_percent(
+        errors,
+        "strategy.maxUnavailable",
+        max_unavailable,
+        replicas,
+        false,
+    );
+    if max_surge == Some(0) && max_unavailable == Some(0) {
+        errors.push(FieldError::new(
+            "strategy",
+            "maxSurge and maxUnavailable must not both resolve to zero",
+        ));
+    }
+}
+
+/// Checks the rollout strategy of a `daemonset` workload, which replaces the pods without creating
+/// additional ones.
+fn check_update_strategy(errors: &mut Vec<FieldError>, strategy: &FoxServiceStrategy) {
+    match strategy.type_ {
+        StrategyType::Recreate => errors.push(FieldError::new(
+            "strategy.type",
+            "Recreate is not supported by the daemonset workload, use OnDelete",
+        )),
+        StrategyType::OnDelete if strategy.max_unavailable.is_some() => {
+            errors.push(FieldError::new(
+                "strategy.maxUnavailable",
+                "only supported for RollingUpdate",
+            ))
+        }
+        _ => {}
+    }
+    if strategy.max_surge.is_some() {
+        errors.push(FieldError::new(
+            "strategy.maxSurge",
+            "not supported by the daemonset workload",
+        ));
+    }
+    // The number of pods depends on the nodes, resolving against 100 pods keeps a percentage as is.
+    if let Some(max_unavailable) = &strategy.max_unavailable {
+        let field = "strategy.maxUnavailable";
+        if check_int_or_percent(errors, field, max_unavailable, 100, true) == Some(0) {
+            errors.push(FieldError::new(field, "must not be zero"));
+        }
+    }
+}
+
+/// Resolves a number or a percentage of the replicas to a number of pods, the way the deployment
+/// controller does.
+///
+/// Returns `None` if the value is neither a non-negative number nor a percentage.
+pub fn resolve_int_or_percent(value: &IntOrString, replicas: i32, round_up: bool) -> Option<i32> {
+    let percent = match value {
+        IntOrString::Int(value) if *value >= 0 => return Some(*value),
+        IntOrString::Int(_) => None,
+        IntOrString::String(value) => value
+            .strip_suffix('%')
+            .and_then(|percent| percent.parse::<i32>().ok())
+            .filter(|percent| *percent >= 0),
+    };
+    match percent {
+        Some(percent) if round_up => Some((percent * replicas + 99) / 100),
+        Some(percent) => Some(percent * replicas / 100),
+        None => None,
+    }
+}
+
+/// Checks that a value is a number or a percentage, see `resolve_int_or_percent`.
+///
+/// Returns the number of pods it resolves to, if valid.
+fn check_int_or_percent(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    value: &IntOrString,
+    replicas: i32,
+    round_up: bool,
+) -> Option<i32> {
+    let resolved = resolve_int_or_percent(value, replicas, round_up);
+    if resolved.is_none() {
+        errors.push(FieldError::new(
+            field,
+            "must be a non-negative number or a percentage, e.g., 25%",
+        ));
+    }
+    resolved
+}
+
+/// Checks that a schedule is either a macro like `@hourly` or consists of the five fields minute,
+/// hour, day of month, month and day of week. Each field is a comma-separated list of `*`, values
+/// and ranges, optionally with a step, e.g., `*/15` or `1-5/2`.
+fn check_schedule(errors: &mut Vec<FieldError>, schedule: &str) {
+    let field = "cron.schedule";
+    let schedule = schedule.trim();
+    if schedule.starts_with('@') {
+        if !CRON_MACROS.contains(&schedule) {
+            errors.push(FieldError::new(
+                field,
+                format!(
+                    "unknown macro {}, expected one of {}",
+                    schedule,
+                    CRON_MACROS.join(", ")
+                ),
+            ));
+        }
+        return;
+    }
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    if fields.len() != CRON_FIELDS.len() {
+        return errors.push(FieldError::new(
+            field,
+            format!(
+                "expected 5 fields (minute, hour, day of month, month, day of week), got {} in \"{}\"",
+                fields.len(),
+                schedule
+            ),
+        ));
+    }
+    for (value, (name, min, max, names)) in fields.iter().zip(CRON_FIELDS.iter()) {
+        let parse = |item: &str| {
+            let position = names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(item))
+                .map(|position| position as u32 + min);
+            position
+                .or_else(|| item.parse::<u32>().ok())
+                .filter(|value| (*min..=*max).contains(value))
+        };
+        let valid = |item: &str| {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (item, None),
+            };
+            if step.is_some_and(|step| step.parse::<u32>().map_or(true, |step| step == 0)) {
+                return false;
+            }
+            // `?` is accepted in place of `*` for the days.
+            if range == "*" || range == "?" {
+                return true;
+            }
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (parse(start), parse(end)),
+                None => (parse(range), parse(range)),
+            };
+            matches!((start, end), (Some(start), Some(end)) if start <= end)
+        };
+        for item in value.split(',').filter(|item| !valid(item)) {
+            errors.push(FieldError::new(
+                field,
+                format!(
+                    "{} is not a valid {} in \"{}\", expected values from {} to {}",
+                    item, name, schedule, min, max
+                ),
+            ));
+        }
+    }
+}
+
+/// Checks that environment variables only reference pod fields exposed by the Downward API.
+fn check_env_field_refs(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    container: &FoxServiceContainer,
+) {
+    let field_refs = container
+        .env
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let source = entry.value_from.as_ref()?;
+            Some((index, source.field_ref.as_ref()?))
+        });
+    for (index, field_ref) in field_refs {
+        if !ENV_FIELD_PATHS.contains(&field_ref.field_path.as_str()) {
+            errors.push(FieldError::new(
+                format!("{}.env[{}].valueFrom.fieldRef", field, index),
+                format!(
+                    "unsupported fieldPath {}, expected one of {}",
+                    field_ref.field_path,
+                    ENV_FIELD_PATHS.join(", ")
+                ),
+            ));
+        }
+    }
+}
+
+/// Checks that an import of environment variables references exactly one ConfigMap or Secret,
+/// and that the config files of the service are only imported if there are any.
+fn check_env_from(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    container: &FoxServiceContainer,
+    config_files: bool,
+) {
+    for (index, env_from) in container.env_from.iter().flatten().enumerate() {
+        let sources = [
+            env_from.config_map.is_some(),
+            env_from.secret.is_some(),
+            env_from.config_files == Some(true),
+        ];
+        if sources.iter().filter(|set| **set).count() != 1 {
+            errors.push(FieldError::new(
+                format!("{}.envFrom[{}]", field, index),
+                "exactly one of configMap, secret or configFiles must be set",
+            ));
+        }
+        if env_from.config_files == Some(true) && !config_files {
+            errors.push(FieldError::new(
+                format!("{}.envFrom[{}].configFiles", field, index),
+                "requires configFiles of the service to be set",
+            ));
+        }
+    }
+}
+
+/// Checks that a container only runs privileged if the service opted in, and that a privileged
+/// container doesn't forbid privilege escalation, a combination rejected by Kubernetes.
+fn check_security_context(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    container: &FoxServiceContainer,
+    allow_privileged: bool,
+) {
+    let context = match &container.security_context {
+        Some(context) if context.privileged == Some(true) => context,
+        _ => return,
+    };
+    if !allow_privileged {
+        errors.push(FieldError::new(
+            format!("{}.securityContext.privileged", field),
+            "privileged containers require allowPrivileged: true",
+        ));
+    }
+    if context.allow_privilege_escalation == Some(false) {
+        errors.push(FieldError::new(
+            format!("{}.securityContext.allowPrivilegeEscalation", field),
+            "must not be false for privileged containers",
+        ));
+    }
+}
+
+/// Checks that a container run to completion, an init container or a pre-deploy hook, doesn't
+/// declare ports, probes or lifecycle hooks, which are only meaningful for long-running containers.
+fn check_run_to_completion(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    kind: &str,
+    container: &FoxServiceContainer,
+) {
+    if container.ports.iter().any(|ports| !ports.is_empty()) {
+        errors.push(FieldError::new(
+            format!("{}.ports", field),
+            format!("{} must not declare ports", kind),
+        ));
+    }
+    let probes = [
+        ("livenessProbe", &container.liveness_probe),
+        ("readinessProbe", &container.readiness_probe),
+        ("startupProbe", &container.startup_probe),
+    ];
+    for (probe, _) in probes.iter().filter(|(_, probe)| probe.is_some()) {
+        errors.push(FieldError::new(
+            format!("{}.{}", field, probe),
+            format!("{} must not declare probes", kind),
+        ));
+    }
+    if container.lifecycle.is_some() {
+        errors.push(FieldError::new(
+            format!("{}.lifecycle", field),
+            format!("{} must not declare lifecycle hooks", kind),
+        ));
+    }
+}
+
+/// Checks that a probe defines exactly one way of checking the container.
+fn check_probe(errors: &mut Vec<FieldError>, field: &str, probe: &FoxServiceProbe) {
+    let handlers = [
+        probe.http_get.is_some(),
+        probe.tcp_socket.is_some(),
+        probe.exec.is_some(),
+    ];
+    if handlers.iter().filter(|set| **set).count() != 1 {
+        errors.push(FieldError::new(
+            field,
+            "exactly one of httpGet, tcpSocket or exec must be set",
+        ));
+    }
+}
+
+/// Checks that each lifecycle hook of a container has exactly one handler.
+fn check_lifecycle(errors: &mut Vec<FieldError>, field: &str, container: &FoxServiceContainer) {
+    let lifecycle = match &container.lifecycle {
+        Some(lifecycle) => lifecycle,
+        None => return,
+    };
+    let hooks = [
+        ("postStart", &lifecycle.post_start),
+        ("preStop", &lifecycle.pre_stop),
+    ];
+    for (name, hook) in hooks.iter() {
+        if let Some(handler) = hook {
+            if handler.http_get.is_some() == handler.exec.is_some() {
+                errors.push(FieldError::new(
+                    format!("{}.lifecycle.{}", field, name),
+                    "exactly one of httpGet or exec must be set",
+                ));
+            }
+        }
+    }
+}
+
+/// Checks that the startup probe gives the container at least as much time to start as the
+/// liveness probe waits initially. Otherwise the startup probe fails before the liveness probe
+/// would even start checking, which is never intended.
+fn check_startup_window(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    container: &FoxServiceContainer,
+) {
+    let (startup, liveness) = match (&container.startup_probe, &container.liveness_probe) {
+        (Some(startup), Some(liveness)) => (startup, liveness),
+        _ => return,
+    };
+    // Defaults as applied by Kubernetes
+    let window = startup.failure_threshold.unwrap_or(3) * startup.period_seconds.unwrap_or(10);
+    let initial_delay = liveness.initial_delay_seconds.unwrap_or(0);
+    if window < initial_delay {
+        errors.push(FieldError::new(
+            format!("{}.startupProbe", field),
+            format!(
+                "failureThreshold * periodSeconds ({}s) must not be shorter than \
+                 livenessProbe.initialDelaySeconds ({}s)",
+                window, initial_delay
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_reports_all_problems_at_once() {
+        let errors = FoxServiceSpec::builder()
+            .name("Web")
+            .container(|container| container.image("nginx:1.21").env("1LOG", "info"))
+            .strategy(StrategyType::OnDelete)
+            .build()
+            .unwrap_err();
+
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "name",
+                "containers[0].name",
+                "containers[0].env[0].name",
+                "strategy.type"
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_field_paths_reach_into_nested_fields() {
+        let spec: FoxServiceSpec = serde_json::from_value(serde_json::json!({
+            "name": "web",
+            "replicsa": 2,
+            "podLabels": {"team": "payments"},
+            "containers": [{
+                "name": "web",
+                "image": "nginx:1.21",
+                "imagePullPolcy": "Always",
+                "env": [{"name": "LOG_LEVEL", "value": "info"}],
+                "ports": [{"containerPort": 80, "protocl": "TCP"}],
+                "livenessProbe": {"httpGet": {"path": "/", "port": 80}, "periodSecond": 5},
+            }],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            spec.unknown_field_paths(),
+            vec![
+                "containers[0].imagePullPolcy",
+                "containers[0].livenessProbe.periodSecond",
+                "containers[0].ports[0].protocl",
+                "replicsa",
+            ]
+        );
+    }
+}
@@ -1,2 +1,2 @@
 pub mod fox_service;
-mod kubernetes_crd;
+mod schema;
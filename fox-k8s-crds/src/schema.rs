@@ -0,0 +1,175 @@
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+/// Schema of a Kubernetes `IntOrString` value, e.g., a port referenced either by its number or by
+/// its name. A plain `anyOf` is not allowed in structural CRD schemas, the dedicated
+/// `x-kubernetes-int-or-string` extension has to be used instead.
+pub(crate) fn int_or_string(_: &mut SchemaGenerator) -> Schema {
+    let mut schema = SchemaObject::default();
+    schema
+        .extensions
+        .insert("x-kubernetes-int-or-string".to_string(), Value::Bool(true));
+    Schema::Object(schema)
+}
+
+/// Schema of an optional 64-bit integer which must not be negative, e.g., a duration in seconds.
+/// The schemars version in use doesn't support range validation attributes yet.
+pub(crate) fn non_negative_i64(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<Option<i64>>().into_object();
+    schema.number().minimum = Some(0.0);
+    Schema::Object(schema)
+}
+
+/// Highest number of replicas accepted by the API server. Far beyond what a single service is
+/// expected to run, it catches typos such as an extra zero.
+pub(crate) const MAX_REPLICAS: i32 = 1000;
+
+/// Schema of the optional number of replicas of a service, between 0 and `MAX_REPLICAS`.
+pub(crate) fn replicas(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<Option<i32>>().into_object();
+    schema.number().minimum = Some(0.0);
+    schema.number().maximum = Some(MAX_REPLICAS as f64);
+    Schema::Object(schema)
+}
+
+/// Schema of a string which must not be empty, e.g., a name or an image reference.
+pub(crate) fn non_empty_string(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<String>().into_object();
+    schema.string().min_length = Some(1);
+    Schema::Object(schema)
+}
+
+/// Schema of a name that has to be a DNS-1123 label, e.g., as the names of the subresources are
+/// derived from it: at most 63 lowercase alphanumeric characters or dashes, starting and ending
+/// with an alphanumeric character.
+pub(crate) fn dns_label(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<String>().into_object();
+    schema.string().max_length = Some(63);
+    schema.string().pattern = Some("^[a-z0-9]([-a-z0-9]*[a-z0-9])?$".to_string());
+    Schema::Object(schema)
+}
+
+/// Schema of a list which must hold at least one item, e.g., the containers of a pod.
+pub(crate) fn non_empty_list<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<Vec<T>>().into_object();
+    schema.array().min_items = Some(1);
+    Schema::Object(schema)
+}
+
+/// A rule across fields the API server checks at admission: a CEL expression over the object of
+/// the schema it is attached to, and the message reported if it doesn't hold.
+pub(crate) struct ValidationRule {
+    pub rule: &'static str,
+    pub message: &'static str,
+}
+
+/// Attaches validation rules to the specification schema of a version of a CRD, as
+/// `x-kubernetes-validations`. The schema model of the Kubernetes version in use predates them, so
+/// the CRD is amended as JSON. API servers without support for them ignore the rules.
+///
+/// # Arguments
+/// - `crd` - The CRD to attach the rules to
+/// - `version` - Name of the version whose specification schema the rules apply to
+/// - `rules` - Rules to attach
+pub(crate) fn with_validation_rules(
+    crd: &CustomResourceDefinition,
+    version: &str,
+    rules: &[ValidationRule],
+) -> Value {
+    let mut crd = serde_json::to_value(crd).expect("CRD serializes to JSON");
+    let rules: Vec<Value> = rules
+        .iter()
+        .map(|rule| json!({ "rule": rule.rule, "message": rule.message }))
+        .collect();
+    let versions = crd["spec"]["versions"].as_array_mut().into_iter().flatten();
+    for served in versions.filter(|served| served["name"] == version) {
+        served["schema"]["openAPIV3Schema"]["properties"]["spec"]["x-kubernetes-validations"] =
+            json!(rules);
+    }
+    crd
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fox_service::FoxServiceSpec;
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+        CustomResourceDefinition, JSONSchemaProps, JSONSchemaPropsOrArray,
+    };
+
+    /// Schema of the `FoxService` objects of a version, as read back from the emitted CRD YAML.
+    fn emitted_schema(version: &str) -> JSONSchemaProps {
+        let yaml = serde_yaml::to_string(&FoxServiceSpec::kubernetes_crd_manifest()).unwrap();
+        let crd: CustomResourceDefinition = serde_yaml::from_str(&yaml).unwrap();
+        let served = crd.spec.versions.into_iter().find(|v| v.name == version);
+        served.unwrap().schema.unwrap().open_api_v3_schema.unwrap()
+    }
+
+    fn property(schema: &JSONSchemaProps, name: &str) -> JSONSchemaProps {
+        schema.properties.as_ref().unwrap()[name].clone()
+    }
+
+    #[test]
+    fn replicas_are_bounded() {
+        let spec = property(&emitted_schema("v1"), "spec");
+        let replicas = property(&spec, "replicas");
+        assert_eq!(replicas.minimum, Some(0.0));
+        assert_eq!(replicas.maximum, Some(1000.0));
+    }
+
+    #[test]
+    fn required_fields() {
+        let schema = emitted_schema("v1");
+        assert_eq!(schema.required, Some(vec!["spec".to_string()]));
+        let spec = property(&schema, "spec");
+        assert_eq!(
+            spec.required,
+            Some(vec!["containers".to_string(), "name".to_string()])
+        );
+        let containers = property(&spec, "containers");
+        assert_eq!(containers.min_items, Some(1));
+        let container = match containers.items {
+            Some(JSONSchemaPropsOrArray::Schema(schema)) => *schema,
+            other => panic!("containers are not a list of objects: {:?}", other),
+        };
+        assert_eq!(
+            container.required,
+            Some(vec!["image".to_string(), "name".to_string()])
+        );
+    }
+
+    #[test]
+    fn validation_rules_are_attached_to_the_v1_spec() {
+        let yaml = serde_yaml::to_string(&FoxServiceSpec::kubernetes_crd_manifest()).unwrap();
+        let crd: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        let versions = crd["spec"]["versions"].as_sequence().unwrap();
+        let spec_schema = |version: &str| {
+            let served = versions
+                .iter()
+                .find(|v| v["name"].as_str() == Some(version));
+            served.unwrap()["schema"]["openAPIV3Schema"]["properties"]["spec"].clone()
+        };
+        let v1 = spec_schema("v1");
+        let rules: Vec<&str> = v1["x-kubernetes-validations"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|rule| {
+                assert!(rule["message"].as_str().is_some());
+                rule["rule"].as_str().unwrap()
+            })
+            .collect();
+        assert!(
+            rules.contains(&"!has(self.autoscaling) || !has(self.replicas) || self.replicas == 1")
+        );
+        assert!(rules.contains(
+            &"!has(self.workload) || self.workload != 'statefulset' || !has(self.service) \
+              || !has(self.service.type) || self.service.type != 'LoadBalancer'"
+        ));
+        assert!(spec_schema("v1beta1")
+            .get("x-kubernetes-validations")
+            .is_none());
+    }
+}
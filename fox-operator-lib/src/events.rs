@@ -0,0 +1,147 @@
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::core::v1::ObjectReference;
+use k8s_openapi::api::events::v1::Event;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use k8s_openapi::chrono::Utc;
+use kube::api::{ObjectMeta, PostParams};
+use kube::{Api, Client, Resource};
+use std::fmt::Display;
+
+/// Name of the controller publishing the events, as shown by `kubectl describe`.
+const REPORTING_CONTROLLER: &str = "cbopt.com/fox-operator";
+
+/// Maximum length of an event note accepted by the API server.
+const MAX_NOTE_LENGTH: usize = 1024;
+
+/// Severity of an event
+pub enum EventType {
+    /// Informational event, e.g., about a created subresource
+    Normal,
+    /// Something went wrong and may require attention
+    Warning,
+}
+
+/// Publishes Kubernetes events regarding `FoxService` resources, so they show up in
+/// `kubectl describe foxservice`. Meant to be constructed once and shared across reconciliations.
+pub struct Recorder {
+    /// Kubernetes client to create the events with
+    client: Client,
+    /// Identifies this instance of the operator, the pod name when running inside the cluster
+    reporting_instance: String,
+}
+
+impl Recorder {
+    /// Constructs a new instance of Recorder.
+    ///
+    /// # Arguments:
+    /// - `client`: A Kubernetes client to create the events with.
+    pub fn new(client: Client) -> Self {
+        let reporting_instance =
+            std::env::var("HOSTNAME").unwrap_or_else(|_| "fox-operator".to_string());
+        Recorder::with_reporting_instance(client, reporting_instance)
+    }
+
+    /// Constructs a new instance of Recorder identified by given name, rather than the `HOSTNAME`
+    /// environment variable, so the published events don't depend on where the operator runs.
+    ///
+    /// # Arguments:
+    /// - `client`: A Kubernetes client to create the events with.
+    /// - `reporting_instance`: Identifies this instance of the operator in the events.
+    pub fn with_reporting_instance(client: Client, reporting_instance: impl Into<String>) -> Self {
+        Recorder {
+            client,
+            reporting_instance: reporting_instance.into(),
+        }
+    }
+
+    /// Publishes an event regarding given `FoxService` resource. Publishing is best-effort, a
+    /// failure is logged as a warning and otherwise ignored, as it must not fail the reconciliation.
+    ///
+    /// # Arguments:
+    /// - `fox_svc`: The `FoxService` resource the event is about.
+    /// - `type_`: Severity of the event.
+    /// - `reason`: Short, machine-readable reason, e.g., `DeploymentCreated`.
+    /// - `action`: What the operator did or failed to do, e.g., `Deploy`.
+    /// - `note`: Human-readable description of the event.
+    pub async fn publish(
+        &self,
+        fox_svc: &FoxService,
+        type_: EventType,
+        reason: &str,
+        action: &str,
+        note: Option<String>,
+    ) {
+        let namespace = fox_svc.meta().namespace.clone().unwrap_or_default();
+        let event = Event {
+            metadata: ObjectMeta {
+                generate_name: Some(format!(
+                    "{}-",
+                    fox_svc.meta().name.as_deref().unwrap_or_default()
+                )),
+                namespace: Some(namespace.clone()),
+                ..ObjectMeta::default()
+            },
+            event_time: MicroTime(Utc::now()),
+            type_: Some(
+                match type_ {
+                    EventType::Normal => "Normal",
+                    EventType::Warning => "Warning",
+                }
+                .to_string(),
+            ),
+            reason: Some(reason.to_string()),
+            action: Some(action.to_string()),
+            note: note.map(|note| note.chars().take(MAX_NOTE_LENGTH).collect()),
+            regarding: Some(ObjectReference {
+                api_version: Some(FoxService::api_version(&()).to_string()),
+                kind: Some(FoxService::kind(&()).to_string()),
+                name: fox_svc.meta().name.clone(),
+                namespace: Some(namespace.clone()),
+                uid: fox_svc.meta().uid.clone(),
+                resource_version: fox_svc.meta().resource_version.clone(),
+                ..ObjectReference::default()
+            }),
+            reporting_controller: Some(REPORTING_CONTROLLER.to_string()),
+            reporting_instance: Some(self.reporting_instance.clone()),
+            related: None,
+            series: None,
+            deprecated_count: None,
+            deprecated_first_timestamp: None,
+            deprecated_last_timestamp: None,
+            deprecated_source: None,
+        };
+
+        let api: Api<Event> = Api::namespaced(self.client.clone(), &namespace);
+        if let Err(error) = api.create(&PostParams::default(), &event).await {
+            tracing::warn!(reason, error = %error, "Failed to publish event");
+        }
+    }
+
+    /// Publishes a warning event with the error text if `result` is an error. The result is
+    /// returned unchanged, so this can wrap any fallible call made during reconciliation.
+    ///
+    /// # Arguments:
+    /// - `fox_svc`: The `FoxService` resource the call was made for.
+    /// - `reason`: Short, machine-readable reason of the failure, e.g., `DeployFailed`.
+    /// - `action`: What the operator attempted to do, e.g., `Deploy`.
+    /// - `result`: Result of the call.
+    pub async fn warn_if_failed<T, E: Display>(
+        &self,
+        fox_svc: &FoxService,
+        reason: &str,
+        action: &str,
+        result: Result<T, E>,
+    ) -> Result<T, E> {
+        if let Err(error) = &result {
+            self.publish(
+                fox_svc,
+                EventType::Warning,
+                reason,
+                action,
+                Some(error.to_string()),
+            )
+            .await;
+        }
+        result
+    }
+}
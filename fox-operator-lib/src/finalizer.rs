@@ -0,0 +1,215 @@
+use crate::{fox_service, retry};
+use fox_k8s_crds::fox_service::*;
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::{json, Value};
+
+/// Name of the finalizer this operator places on `FoxService` resources.
+pub const FINALIZER: &str = "foxservices.cbopt.com";
+
+/// Checks whether the finalizer of this operator is present on given `FoxService` resource.
+/// Finalizers placed by other controllers or users are not taken into account.
+///
+/// # Arguments:
+/// - `fox_svc` - The `FoxService` resource to check.
+pub fn is_present(fox_svc: &FoxService) -> bool {
+    fox_svc
+        .meta()
+        .finalizers
+        .iter()
+        .flatten()
+        .any(|finalizer| finalizer == FINALIZER)
+}
+
+/// Adds a finalizer record into an `FoxService` kind of resource. If the finalizer already exists,
+/// this action has no effect. Finalizers placed by other controllers are preserved.
+///
+/// # Arguments:
+/// - `client` - Kubernetes client to modify the `FoxService` resource with.
+/// - `name` - Name of the `FoxService` resource to modify.
+/// - `namespace` - Namespace where the `FoxService` resource with given `name` resides.
+///
+/// Note: The finalizer is applied server-side as `FIELD_MANAGER`. Finalizers are a set to the API
+/// server, so the entry is merged into the existing ones and no read is needed beforehand.
+pub async fn add(client: Client, name: &str, namespace: &str) -> Result<FoxService, Error> {
+    let api: Api<FoxService> = Api::namespaced(client, namespace);
+    let finalizer: Value = apply_body(name);
+    let patch: Patch<&Value> = Patch::Apply(&finalizer);
+    api.patch(name, &fox_service::apply_params(), &patch).await
+}
+
+/// Builds the body applying the finalizer of this operator, see `add`. It holds nothing but the
+/// finalizer, so no other field of the `FoxService` resource is claimed by `FIELD_MANAGER`.
+///
+/// # Arguments:
+/// - `name` - Name of the `FoxService` resource to modify.
+fn apply_body(name: &str) -> Value {
+    json!({
+        "apiVersion": FoxService::api_version(&()),
+        "kind": FoxService::kind(&()),
+        "metadata": {
+            "name": name,
+            "finalizers": [FINALIZER]
+        }
+    })
+}
+
+/// Removes the finalizer of this operator from an `FoxService` resource. If the finalizer is not
+/// present, this action has no effect. Finalizers placed by other controllers are preserved.
+///
+/// # Arguments:
+/// - `client` - Kubernetes client to modify the `FoxService` resource with.
+/// - `name` - Name of the `FoxService` resource to modify.
+/// - `namespace` - Namespace where the `FoxService` resource with given `name` resides.
+///
+/// Returns the modified resource, or `None` if it no longer exists, e.g., because the finalizer
+/// was removed by hand already. There is nothing left to remove the finalizer from then.
+///
+/// Note: Applying the finalizers without ours would only remove it if it was applied by
+/// `FIELD_MANAGER`, not if it was added by an earlier version of the operator. A merge patch bound
+/// to the `resourceVersion` of the resource read is used instead, and retried on conflicts.
+pub async fn delete(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<FoxService>, Error> {
+    let api: Api<FoxService> = Api::namespaced(client, namespace);
+    match retry::on_conflict(|| delete_once(&api, name)).await {
+        Ok(fox_svc) => Ok(Some(fox_svc)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Builds the merge patch removing the finalizer of this operator, see `delete`. A merge patch
+/// replaces the list as a whole, therefore it keeps all other finalizers, in order.
+///
+/// # Arguments:
+/// - `fox_svc` - The `FoxService` resource as currently stored.
+fn removal_patch(fox_svc: &FoxService) -> Value {
+    let finalizers: Vec<&String> = fox_svc
+        .meta()
+        .finalizers
+        .iter()
+        .flatten()
+        .filter(|finalizer| *finalizer != FINALIZER)
+        .collect();
+    json!({
+        "metadata": {
+            "resourceVersion": fox_svc.meta().resource_version,
+            "finalizers": finalizers
+        }
+    })
+}
+
+/// Removes the finalizer from the `FoxService` resource as currently stored, see `delete`.
+async fn delete_once(api: &Api<FoxService>, name: &str) -> Result<FoxService, Error> {
+    let fox_svc: FoxService = api.get(name).await?;
+    if !is_present(&fox_svc) {
+        return Ok(fox_svc);
+    }
+
+    let finalizer: Value = removal_patch(&fox_svc);
+
+    let patch: Patch<&Value> = Patch::Merge(&finalizer);
+    api.patch(name, &PatchParams::default(), &patch).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use hyper::{Method, StatusCode};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// The `FoxService` of `mock::fox_service` carrying given finalizers.
+    fn with_finalizers(finalizers: Option<Vec<&str>>) -> FoxService {
+        let mut fox_svc = mock::fox_service();
+        fox_svc.meta_mut().finalizers =
+            finalizers.map(|finalizers| finalizers.into_iter().map(String::from).collect());
+        fox_svc
+    }
+
+    #[test]
+    fn is_present_without_finalizers() {
+        assert!(!is_present(&with_finalizers(None)));
+        assert!(!is_present(&with_finalizers(Some(vec![]))));
+    }
+
+    #[test]
+    fn is_present_with_only_ours() {
+        assert!(is_present(&with_finalizers(Some(vec![FINALIZER]))));
+    }
+
+    #[test]
+    fn is_present_with_only_a_foreign_one() {
+        assert!(!is_present(&with_finalizers(Some(vec!["other.example"]))));
+    }
+
+    #[test]
+    fn is_present_with_ours_and_a_foreign_one() {
+        let fox_svc = with_finalizers(Some(vec!["other.example", FINALIZER]));
+        assert!(is_present(&fox_svc));
+    }
+
+    #[test]
+    fn removal_patch_keeps_foreign_finalizers() {
+        let mut fox_svc = with_finalizers(Some(vec!["other.example", FINALIZER]));
+        fox_svc.meta_mut().resource_version = Some("7".to_string());
+
+        assert_eq!(
+            removal_patch(&fox_svc),
+            json!({ "metadata": { "resourceVersion": "7", "finalizers": ["other.example"] } })
+        );
+    }
+
+    #[test]
+    fn apply_body_holds_only_the_finalizer() {
+        assert_eq!(
+            apply_body("web"),
+            json!({
+                "apiVersion": "cbopt.com/v1",
+                "kind": "FoxService",
+                "metadata": { "name": "web", "finalizers": ["foxservices.cbopt.com"] }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_reads_the_resource_again_after_a_conflict() {
+        let (reads, patches) = (AtomicU32::new(0), AtomicU32::new(0));
+        let (client, requests) = mock::client(move |method, _, _| {
+            let mut fox_svc = mock::fox_service();
+            fox_svc.meta_mut().finalizers = Some(vec![FINALIZER.to_string()]);
+            match *method {
+                Method::GET => {
+                    let version = reads.fetch_add(1, Ordering::SeqCst) + 1;
+                    fox_svc.meta_mut().resource_version = Some(version.to_string());
+                    (StatusCode::OK, serde_json::to_value(fox_svc).unwrap())
+                }
+                _ if patches.fetch_add(1, Ordering::SeqCst) == 0 => mock::status(409, "Conflict"),
+                _ => (StatusCode::OK, serde_json::to_value(fox_svc).unwrap()),
+            }
+        });
+
+        let deleted = delete(client, "web", "default").await.unwrap();
+
+        assert!(deleted.is_some());
+        let path = "/apis/cbopt.com/v1/namespaces/default/foxservices/web";
+        assert_eq!(
+            requests.lines(),
+            vec![
+                format!("GET {}", path),
+                format!("PATCH {}?", path),
+                format!("GET {}", path),
+                format!("PATCH {}?", path),
+            ]
+        );
+        let versions: Vec<Value> = requests
+            .bodies(Method::PATCH, path)
+            .iter()
+            .map(|patch| patch["metadata"]["resourceVersion"].clone())
+            .collect();
+        assert_eq!(versions, vec![json!("1"), json!("2")]);
+    }
+}
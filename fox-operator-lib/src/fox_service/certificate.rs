@@ -0,0 +1,201 @@
+use super::{ingress, is_subset, metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use kube::api::{
+    ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams, PostParams,
+};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+
+/// API group of the cert-manager resources.
+const CERT_MANAGER_GROUP: &str = "cert-manager.io";
+
+/// The Certificate resource of cert-manager. cert-manager is optional, so certificates are managed
+/// as dynamic objects rather than depending on its types.
+fn api_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk(CERT_MANAGER_GROUP, "v1", "Certificate");
+    ApiResource::from_gvk_with_plural(&gvk, "certificates")
+}
+
+/// cert-manager issuer of the certificate of a `FoxService`, if it has an Ingress requesting one.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn issuer_ref(fs: &FoxServiceSpec) -> Option<&FoxServiceIssuerRef> {
+    fs.ingress
+        .as_ref()
+        .and_then(|ingress| ingress.issuer_ref.as_ref())
+        .filter(|_| ingress::is_routed(fs))
+}
+
+/// Name of the Secret the certificate of a `FoxService` is stored in.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn secret_name(fs: &FoxServiceSpec) -> String {
+    format!("{}-tls", fs.name)
+}
+
+/// Endpoints of the HTTP ingress points routed by the Ingress of a `FoxService`, sorted and
+/// without duplicates. The certificate is issued for all of them.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn dns_names(fs: &FoxServiceSpec) -> Vec<String> {
+    let mut names: Vec<String> = fs
+        .routed_ingress()
+        .iter()
+        .map(|ingress| ingress.endpoint.to_owned())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Builds the desired state of the Certificate cert-manager issues the TLS certificate of the
+/// Ingress of a `FoxService` for.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the Certificate
+/// - `issuer_ref` - Issuer of the certificate
+/// - `namespace` - Namespace the Certificate is going to reside in.
+pub fn build_certificate(
+    fox_svc: &FoxService,
+    issuer_ref: &FoxServiceIssuerRef,
+    namespace: &str,
+) -> DynamicObject {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let kind = issuer_ref.kind.unwrap_or(IssuerKind::Issuer);
+    let mut certificate = DynamicObject::new(&fs.name, &api_resource())
+        .within(namespace)
+        .data(json!({
+            "spec": {
+                "secretName": secret_name(fs),
+                "dnsNames": dns_names(fs),
+                "issuerRef": {
+                    "name": issuer_ref.name,
+                    "kind": format!("{:?}", kind),
+                    "group": CERT_MANAGER_GROUP
+                }
+            }
+        }));
+    certificate.metadata.labels = Some(super::labels(fox_svc));
+    certificate.metadata.annotations = super::annotations(fox_svc, None);
+    certificate.metadata.owner_references = Some(vec![owner_reference(fox_svc)]);
+    certificate
+}
+
+/// Fetches the Certificate with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the Certificate with
+/// - `name` - Name of the Certificate to fetch
+/// - `namespace` - Namespace the Certificate resides in
+///
+/// Returns `Ok(None)` if there is no such Certificate, or if cert-manager isn't installed.
+pub async fn get_certificate(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<DynamicObject>, Error> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client, namespace, &api_resource());
+    match api.get(name).await {
+        Ok(certificate) => Ok(Some(certificate)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Creates the Certificate of a `FoxService` if its Ingress requests one and the Certificate
+/// doesn't exist yet, and patches an existing Certificate if the endpoints or the issuer changed.
+/// The Certificate is deleted once it is no longer requested, the Secret issued by cert-manager is
+/// kept.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the Certificate with.
+/// - `fox_svc` - The `FoxService` resource owning the Certificate
+/// - `namespace` - Namespace the Certificate resides in.
+///
+/// Returns the Certificate if it was created. Fails with a 404 if cert-manager isn't installed and
+/// a certificate is requested.
+pub async fn apply_certificate(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<DynamicObject>, Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &api_resource());
+    match (
+        issuer_ref(fs),
+        get_certificate(client.clone(), &fs.name, namespace).await?,
+    ) {
+        (Some(issuer_ref), None) => {
+            let certificate = build_certificate(fox_svc, issuer_ref, namespace);
+            Ok(Some(
+                api.create(&PostParams::default(), &certificate).await?,
+            ))
+        }
+        (Some(issuer_ref), Some(live)) => {
+            let desired = build_certificate(fox_svc, issuer_ref, namespace);
+            if !is_subset(&desired.data["spec"], &live.data["spec"])
+                || metadata_differs(fox_svc, &live.metadata)
+            {
+                let mut patch = desired.data.clone();
+                patch["metadata"] = json!({
+                    "labels": desired.metadata.labels,
+                    "annotations": desired.metadata.annotations
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+            Ok(None)
+        }
+        (None, None) => Ok(None),
+        // A Certificate of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_certificate(client, &fs.name, namespace).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// The `Ready` condition cert-manager reports for a Certificate, as its status and message, if
+/// any. The certificate is issued into its Secret once the status is `True`.
+///
+/// # Arguments
+/// - `certificate` - Certificate as currently present in the cluster
+pub fn ready_condition(certificate: &DynamicObject) -> Option<(bool, Option<String>)> {
+    let conditions = certificate.data["status"]["conditions"].as_array()?;
+    let ready = conditions
+        .iter()
+        .find(|condition| condition["type"] == "Ready")?;
+    Some((
+        ready["status"] == "True",
+        ready["message"].as_str().map(str::to_string),
+    ))
+}
+
+/// Deletes an existing Certificate.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the Certificate with
+/// - `name` - Name of the Certificate to delete
+/// - `namespace` - Namespace the existing Certificate resides in
+///
+/// Note: A Certificate that doesn't exist is considered deleted, as is one of a cluster without
+/// cert-manager.
+pub async fn delete_certificate(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client, namespace, &api_resource());
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
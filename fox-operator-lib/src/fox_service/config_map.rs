@@ -0,0 +1,160 @@
+use super::{metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Annotation of the pod template holding a checksum of the `configFiles` of a `FoxService`. A
+/// changed file changes the pod template, rolling the pods to pick it up.
+pub const CHECKSUM_ANNOTATION: &str = "fox.cbopt.com/config-checksum";
+
+/// Name of the ConfigMap holding the `configFiles` of a `FoxService`.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn config_map_name(fs: &FoxServiceSpec) -> String {
+    format!("{}-config", fs.name)
+}
+
+/// Checksum of the `configFiles` of a Fox service specification, if there are any.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn checksum(fs: &FoxServiceSpec) -> Option<String> {
+    let files = fs.config_files.as_ref()?;
+    let mut hasher = Sha256::new();
+    // Keys are iterated in order, lengths are prefixed so no two maps produce the same input.
+    for (name, content) in files {
+        for part in [name, content].iter() {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part.as_bytes());
+        }
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Builds the desired state of the ConfigMap holding the `configFiles` of a `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the ConfigMap
+/// - `files` - Contents of the files by file name
+/// - `namespace` - Namespace the ConfigMap is going to reside in.
+pub fn build_config_map(
+    fox_svc: &FoxService,
+    files: &BTreeMap<String, String>,
+    namespace: &str,
+) -> ConfigMap {
+    ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(config_map_name(&fox_svc.spec)),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        data: Some(files.clone()),
+        ..ConfigMap::default()
+    }
+}
+
+/// Creates the ConfigMap of a `FoxService` if `configFiles` is set and the ConfigMap doesn't exist
+/// yet, and patches an existing ConfigMap if the files changed. Keys of files removed from the
+/// specification are removed from the ConfigMap as well. The ConfigMap is deleted once
+/// `configFiles` is removed.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the ConfigMap with.
+/// - `fox_svc` - The `FoxService` resource owning the ConfigMap
+/// - `namespace` - Namespace the ConfigMap resides in.
+pub async fn apply_config_map(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let name = config_map_name(fs);
+    let api: Api<ConfigMap> = Api::namespaced(client.clone(), namespace);
+    let live = match api.get(&name).await {
+        Ok(config_map) => Some(config_map),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    match (&fs.config_files, live) {
+        (Some(files), None) => {
+            let config_map = build_config_map(fox_svc, files, namespace);
+            api.create(&PostParams::default(), &config_map).await?;
+        }
+        (Some(files), Some(live)) => {
+            let desired = build_config_map(fox_svc, files, namespace);
+            if live.data.as_ref().cloned().unwrap_or_default() != *files
+                || desired.metadata.owner_references != live.metadata.owner_references
+                || metadata_differs(fox_svc, &live.metadata)
+            {
+                // A merge patch only removes keys explicitly set to `null`.
+                let mut data: Map<String, Value> = live
+                    .data
+                    .iter()
+                    .flatten()
+                    .map(|(key, _)| (key.to_owned(), Value::Null))
+                    .collect();
+                data.extend(
+                    files
+                        .iter()
+                        .map(|(key, content)| (key.to_owned(), json!(content))),
+                );
+                let patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": desired.metadata.annotations,
+                        "ownerReferences": desired.metadata.owner_references
+                    },
+                    "data": data
+                });
+                api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+        }
+        (None, None) => {}
+        // A ConfigMap of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_config_map(client, fs, namespace).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the ConfigMap holding the `configFiles` of a `FoxService`.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the ConfigMap with
+/// - `fs` - Fox service specification
+/// - `namespace` - Namespace the existing ConfigMap resides in
+///
+/// Note: A ConfigMap that doesn't exist is considered deleted.
+pub async fn delete_config_map(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<ConfigMap> = Api::namespaced(client, namespace);
+    match api
+        .delete(&config_map_name(fs), &DeleteParams::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
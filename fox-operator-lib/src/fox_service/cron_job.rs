@@ -0,0 +1,203 @@
+use super::deployment::{build_pod_template, pod_template_differs};
+use super::workload::is_suspended;
+use super::{foreground_deletion, job_labels, metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::batch::v1::JobSpec;
+use k8s_openapi::api::batch::v1beta1::{CronJob, CronJobSpec, JobTemplateSpec};
+use k8s_openapi::api::core::v1::PodTemplateSpec;
+use kube::api::{ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+
+/// Number of successfully finished jobs the API server defaults CronJobs to keep.
+const DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT: i32 = 3;
+
+/// Builds the desired state of the CronJob running the containers of a `FoxService` marked as
+/// `batch`. The CronJob is owned by the `FoxService`.
+///
+/// The pods of the jobs are not labeled with the selector labels of the `FoxService`, they would
+/// be selected by its service and pod disruption budget otherwise.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the CronJob
+/// - `cron` - Schedule of the batch containers
+/// - `namespace` - Namespace the CronJob is going to reside in.
+pub fn build_cron_job(fox_svc: &FoxService, cron: &FoxServiceCron, namespace: &str) -> CronJob {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let mut template: PodTemplateSpec = build_pod_template(fox_svc, true);
+    if let Some(spec) = template.spec.as_mut() {
+        // Jobs don't support restarting containers which completed successfully.
+        spec.restart_policy = Some("OnFailure".to_string());
+    }
+    if let Some(metadata) = template.metadata.as_mut() {
+        metadata.labels = Some(job_labels(fox_svc, "batch"));
+    }
+    CronJob {
+        metadata: ObjectMeta {
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(CronJobSpec {
+            schedule: cron.schedule.trim().to_owned(),
+            concurrency_policy: cron
+                .concurrency_policy
+                .map(|policy| format!("{:?}", policy)),
+            suspend: if is_suspended(fs) {
+                Some(true)
+            } else {
+                cron.suspend
+            },
+            successful_jobs_history_limit: cron.successful_jobs_history_limit,
+            job_template: JobTemplateSpec {
+                metadata: None,
+                spec: Some(JobSpec {
+                    template,
+                    ..JobSpec::default()
+                }),
+            },
+            ..CronJobSpec::default()
+        }),
+        ..CronJob::default()
+    }
+}
+
+/// Fetches the CronJob with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the CronJob with
+/// - `name` - Name of the CronJob to fetch
+/// - `namespace` - Namespace the CronJob resides in
+///
+/// Returns `Ok(None)` if there is no such CronJob.
+pub async fn get_cron_job(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<CronJob>, Error> {
+    let api: Api<CronJob> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(cron_job) => Ok(Some(cron_job)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Creates the CronJob of a `FoxService` if `cron` is set and the CronJob doesn't exist yet, and
+/// patches an existing CronJob if the schedule or the batch containers changed. The CronJob is
+/// deleted once `cron` is removed.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the CronJob with.
+/// - `fox_svc` - The `FoxService` resource owning the CronJob
+/// - `namespace` - Namespace the CronJob resides in.
+pub async fn apply_cron_job(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<CronJob> = Api::namespaced(client.clone(), namespace);
+    match (
+        &fs.cron,
+        get_cron_job(client.clone(), &fs.name, namespace).await?,
+    ) {
+        (Some(cron), None) => {
+            let cron_job = build_cron_job(fox_svc, cron, namespace);
+            api.create(&PostParams::default(), &cron_job).await?;
+        }
+        (Some(cron), Some(live)) => {
+            let desired = build_cron_job(fox_svc, cron, namespace);
+            if needs_update(&desired, &live) || metadata_differs(fox_svc, &live.metadata) {
+                let spec = desired.spec.unwrap_or_default();
+                let patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": desired.metadata.annotations,
+                        "ownerReferences": desired.metadata.owner_references
+                    },
+                    "spec": {
+                        "schedule": spec.schedule,
+                        "concurrencyPolicy": spec.concurrency_policy,
+                        "suspend": spec.suspend,
+                        "successfulJobsHistoryLimit": spec.successful_jobs_history_limit,
+                        "jobTemplate": spec.job_template
+                    }
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+        }
+        (None, None) => {}
+        // A CronJob of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_cron_job(client, &fs.name, namespace).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decides whether a live CronJob has drifted from the desired state. Omitted settings are
+/// compared with the defaults applied by the API server.
+///
+/// # Arguments
+/// - `desired` - CronJob as built by `build_cron_job`
+/// - `live` - CronJob as currently present in the cluster
+pub fn needs_update(desired: &CronJob, live: &CronJob) -> bool {
+    if desired.metadata.owner_references != live.metadata.owner_references {
+        return true;
+    }
+    let desired = desired.spec.clone().unwrap_or_default();
+    let live = live.spec.clone().unwrap_or_default();
+    let concurrency_policy = |spec: &CronJobSpec| {
+        spec.concurrency_policy
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", ConcurrencyPolicy::Allow))
+    };
+    let template = |spec: &CronJobSpec| {
+        spec.job_template
+            .spec
+            .as_ref()
+            .map(|spec| spec.template.clone())
+            .unwrap_or_default()
+    };
+    desired.schedule != live.schedule
+        || concurrency_policy(&desired) != concurrency_policy(&live)
+        || desired.suspend.unwrap_or(false) != live.suspend.unwrap_or(false)
+        || desired
+            .successful_jobs_history_limit
+            .unwrap_or(DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT)
+            != live
+                .successful_jobs_history_limit
+                .unwrap_or(DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT)
+        || pod_template_differs(&template(&desired), &template(&live))
+}
+
+/// Deletes an existing CronJob along with its jobs.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the CronJob with
+/// - `name` - Name of the CronJob to delete
+/// - `namespace` - Namespace the existing CronJob resides in
+///
+/// Note: A CronJob that doesn't exist is considered deleted.
+pub async fn delete_cron_job(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<CronJob> = Api::namespaced(client, namespace);
+    // The jobs of a `batch/v1beta1` CronJob are orphaned by default.
+    match api.delete(name, &foreground_deletion()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
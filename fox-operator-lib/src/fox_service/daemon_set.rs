@@ -0,0 +1,213 @@
+use super::deployment::{build_pod_template, pod_template_differs};
+use super::hook::generation_annotation;
+use super::workload::is_suspended;
+use super::{controller_uid, foreground_deletion, owner_reference, selector_labels};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::apps::v1::{
+    DaemonSet, DaemonSetSpec, DaemonSetUpdateStrategy, RollingUpdateDaemonSet,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error};
+use serde_json::Value;
+
+/// Number of old revisions the API server defaults DaemonSets to keep.
+const DEFAULT_REVISION_HISTORY_LIMIT: i32 = 10;
+
+/// Node label selected by the pods of a suspended `FoxService`. No node has it, so a DaemonSet,
+/// which can't be scaled, runs no pods at all.
+const SUSPENDED_NODE_LABEL: &str = "fox.cbopt.com/suspended";
+
+/// Builds the desired state of the DaemonSet backing a `FoxService`. It runs one pod on every
+/// node the pods tolerate, `replicas` is ignored. While suspended, it runs no pods.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the DaemonSet
+/// - `namespace` - Namespace the DaemonSet is going to reside in.
+pub fn build_daemon_set(fox_svc: &FoxService, namespace: &str) -> DaemonSet {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let mut template = build_pod_template(fox_svc, false);
+    if let Some(spec) = template.spec.as_mut().filter(|_| is_suspended(fs)) {
+        spec.node_selector
+            .get_or_insert_with(Default::default)
+            .insert(SUSPENDED_NODE_LABEL.to_string(), "true".to_string());
+    }
+    DaemonSet {
+        metadata: ObjectMeta {
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, generation_annotation(fox_svc)),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(DaemonSetSpec {
+            revision_history_limit: fs.revision_history_limit,
+            update_strategy: fs.strategy.as_ref().map(build_update_strategy),
+            selector: LabelSelector {
+                match_labels: Some(selector_labels(fox_svc)),
+                match_expressions: None,
+            },
+            template,
+            ..DaemonSetSpec::default()
+        }),
+        ..DaemonSet::default()
+    }
+}
+
+/// Translates the rollout strategy of the `FoxService` specification into the update strategy of
+/// a DaemonSet.
+fn build_update_strategy(strategy: &FoxServiceStrategy) -> DaemonSetUpdateStrategy {
+    let rolling_update = match strategy.type_ {
+        // `Recreate` is rejected by the validation, DaemonSets don't support it.
+        StrategyType::Recreate | StrategyType::OnDelete => None,
+        StrategyType::RollingUpdate => Some(RollingUpdateDaemonSet {
+            max_unavailable: strategy.max_unavailable.clone(),
+        }),
+    };
+    DaemonSetUpdateStrategy {
+        type_: Some(format!("{:?}", strategy.type_)),
+        rolling_update,
+    }
+}
+
+/// Creates a new DaemonSet backing a `FoxService`.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to create the DaemonSet with.
+/// - `fox_svc` - The `FoxService` resource owning the DaemonSet
+/// - `namespace` - Namespace to create the DaemonSet in.
+///
+/// Note: It is assumed the resource does not already exists for simplicity. Returns an `Error` if it does.
+pub async fn create_daemon_set(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<DaemonSet, Error> {
+    let daemon_set: DaemonSet = build_daemon_set(fox_svc, namespace);
+    let api: Api<DaemonSet> = Api::namespaced(client, namespace);
+    api.create(&PostParams::default(), &daemon_set).await
+}
+
+/// Fetches the DaemonSet with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the DaemonSet with
+/// - `name` - Name of the DaemonSet to fetch
+/// - `namespace` - Namespace the DaemonSet resides in
+///
+/// Returns `Ok(None)` if there is no such DaemonSet.
+pub async fn get_daemon_set(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<DaemonSet>, Error> {
+    let api: Api<DaemonSet> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(daemon_set) => Ok(Some(daemon_set)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Patches an existing DaemonSet to match the given Fox service specification.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to patch the DaemonSet with.
+/// - `fox_svc` - The `FoxService` resource owning the DaemonSet
+/// - `namespace` - Namespace the existing DaemonSet resides in.
+///
+/// Note: The selector of a DaemonSet can't be changed, it is derived from values which don't
+/// change for the lifetime of the DaemonSet.
+pub async fn update_daemon_set(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<DaemonSet, Error> {
+    let daemon_set: DaemonSet = build_daemon_set(fox_svc, namespace);
+    let mut patch = serde_json::to_value(&daemon_set).map_err(Error::SerdeError)?;
+    // Omitted fields are left untouched by a merge patch, the parameters of a previous rolling
+    // update strategy have to be removed explicitly. The API server rejects them for `OnDelete`.
+    let on_delete = fox_svc
+        .spec
+        .strategy
+        .as_ref()
+        .map(|strategy| strategy.type_);
+    if on_delete == Some(StrategyType::OnDelete) {
+        patch["spec"]["updateStrategy"]["rollingUpdate"] = Value::Null;
+    }
+
+    let api: Api<DaemonSet> = Api::namespaced(client, namespace);
+    api.patch(
+        &fox_svc.spec.name,
+        &PatchParams::default(),
+        &Patch::Merge(&patch),
+    )
+    .await
+}
+
+/// Decides whether a live DaemonSet has drifted from the desired state. Only the fields set by
+/// `build_daemon_set` are compared, with the defaults applied by the API server filled in.
+///
+/// # Arguments
+/// - `desired` - DaemonSet as built by `build_daemon_set`
+/// - `live` - DaemonSet as currently present in the cluster
+pub fn needs_update(desired: &DaemonSet, live: &DaemonSet) -> bool {
+    let (desired_metadata, live_metadata) = (&desired.metadata, &live.metadata);
+    let (desired, live) = match (desired.spec.as_ref(), live.spec.as_ref()) {
+        (Some(desired), Some(live)) => (desired, live),
+        (desired, live) => return desired.is_some() != live.is_some(),
+    };
+    if with_strategy_defaults(&desired.update_strategy)
+        != with_strategy_defaults(&live.update_strategy)
+    {
+        return true;
+    }
+    if desired
+        .revision_history_limit
+        .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT)
+        != live
+            .revision_history_limit
+            .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT)
+    {
+        return true;
+    }
+    if controller_uid(desired_metadata) != controller_uid(live_metadata) {
+        return true;
+    }
+    pod_template_differs(&desired.template, &live.template)
+}
+
+/// An update strategy with the defaults applied by the API server filled in.
+fn with_strategy_defaults(strategy: &Option<DaemonSetUpdateStrategy>) -> DaemonSetUpdateStrategy {
+    let mut strategy = strategy.clone().unwrap_or_default();
+    let type_ = strategy
+        .type_
+        .get_or_insert_with(|| format!("{:?}", StrategyType::RollingUpdate));
+    if type_ == &format!("{:?}", StrategyType::RollingUpdate) {
+        let rolling_update = strategy.rolling_update.get_or_insert_with(Default::default);
+        rolling_update
+            .max_unavailable
+            .get_or_insert(IntOrString::Int(1));
+    }
+    strategy
+}
+
+/// Deletes an existing DaemonSet. Its pods are deleted before the DaemonSet itself is gone, so a
+/// workload replacing it doesn't run alongside them for long.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the DaemonSet with
+/// - `name` - Name of the DaemonSet to delete
+/// - `namespace` - Namespace the existing DaemonSet resides in
+///
+/// Note: A DaemonSet that doesn't exist is considered deleted.
+pub async fn delete_daemon_set(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<DaemonSet> = Api::namespaced(client, namespace);
+    match api.delete(name, &foreground_deletion()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,1098 @@
+use super::config_map;
+use super::hook::generation_annotation;
+use super::persistent_volume_claim::claim_name;
+use super::{
+    already_exists_error, apply_params, contains_all, controller_uid, foreground_deletion,
+};
+use super::{is_managed, owner_reference, selector_labels};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
+use k8s_openapi::api::apps::v1::{DeploymentStrategy, RollingUpdateDeployment};
+use k8s_openapi::api::core::v1::SecretKeySelector;
+use k8s_openapi::api::core::v1::{Affinity, NodeSelector, PreferredSchedulingTerm};
+use k8s_openapi::api::core::v1::{Capabilities, PodSecurityContext, SecurityContext};
+use k8s_openapi::api::core::v1::{ConfigMapEnvSource, EnvFromSource, SecretEnvSource};
+use k8s_openapi::api::core::v1::{ConfigMapKeySelector, EnvVarSource, ObjectFieldSelector};
+use k8s_openapi::api::core::v1::{
+    ConfigMapVolumeSource, EmptyDirVolumeSource, PersistentVolumeClaimVolumeSource,
+    SecretVolumeSource, Volume, VolumeMount,
+};
+use k8s_openapi::api::core::v1::{Container, ContainerPort, PodSpec, PodTemplateSpec};
+use k8s_openapi::api::core::v1::{EnvVar, ExecAction, HTTPGetAction, Probe, TCPSocketAction};
+use k8s_openapi::api::core::v1::{Handler, Lifecycle};
+use k8s_openapi::api::core::v1::{LocalObjectReference, Toleration, TopologySpreadConstraint};
+use k8s_openapi::api::core::v1::{NodeAffinity, NodeSelectorRequirement, NodeSelectorTerm};
+use k8s_openapi::api::core::v1::{PodAffinityTerm, PodAntiAffinity, WeightedPodAffinityTerm};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{ObjectMeta, Patch};
+use kube::{Api, Client, Error};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Termination grace period the API server defaults pods to, in seconds.
+const DEFAULT_TERMINATION_GRACE_PERIOD_SECONDS: i64 = 30;
+
+/// Number of old ReplicaSets the API server defaults deployments to keep.
+const DEFAULT_REVISION_HISTORY_LIMIT: i32 = 10;
+
+/// Progress deadline the API server defaults deployments to, in seconds.
+const DEFAULT_PROGRESS_DEADLINE_SECONDS: i32 = 600;
+
+/// Builds the desired state of the Deployment backing a `FoxService`. The deployment is owned by
+/// the `FoxService`, so Kubernetes garbage collects it should the explicit deletion be skipped.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the deployment
+/// - `namespace` - Namespace the Deployment is going to reside in.
+pub fn build_deployment(fox_svc: &FoxService, namespace: &str) -> Deployment {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    Deployment {
+        metadata: ObjectMeta {
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, generation_annotation(fox_svc)),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(DeploymentSpec {
+            replicas: super::workload::replicas(fox_svc),
+            strategy: fs.strategy.as_ref().map(build_strategy),
+            revision_history_limit: fs.revision_history_limit,
+            progress_deadline_seconds: fs.progress_deadline_seconds,
+            // The API server requires the selector to match the labels of the pod template.
+            selector: LabelSelector {
+                match_labels: Some(selector_labels(fox_svc)),
+                match_expressions: None,
+            },
+            template: build_pod_template(fox_svc, false),
+            ..DeploymentSpec::default()
+        }),
+        ..Deployment::default()
+    }
+}
+
+/// Builds the pod template shared by all kinds of workloads backing a `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the pods belong to
+/// - `batch` - Whether the pods run the containers marked as `batch` rather than the long-running
+///   ones
+///
+/// Note: Persistent volumes of a `statefulset` workload are provided by its volume claim templates,
+/// they are not part of the pod template.
+pub fn build_pod_template(fox_svc: &FoxService, batch: bool) -> PodTemplateSpec {
+    let containers: Vec<&FoxServiceContainer> = fox_svc
+        .spec
+        .containers
+        .iter()
+        .filter(|container| container.batch.unwrap_or(false) == batch)
+        .collect();
+    build_pod_template_with(fox_svc, &containers)
+}
+
+/// Builds a pod template running the given containers, with the pod-level settings of a
+/// `FoxService`, e.g., its volumes, init containers and service account.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the pods belong to
+/// - `containers` - Containers of the pods
+pub fn build_pod_template_with(
+    fox_svc: &FoxService,
+    containers: &[&FoxServiceContainer],
+) -> PodTemplateSpec {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let containers = containers
+        .iter()
+        .map(|container| build_container(fs, container))
+        .collect();
+    // The selector labels win on conflict, the pods wouldn't be selected by the workload otherwise.
+    let mut pod_labels = super::labels(fox_svc);
+    pod_labels.extend(fs.pod_labels.clone().unwrap_or_default());
+    pod_labels.extend(selector_labels(fox_svc));
+    let claim_templates = super::stateful_set::is_stateful(fs);
+    let mut annotations = fs.pod_annotations.clone().unwrap_or_default();
+    if let Some(checksum) = config_map::checksum(fs) {
+        annotations.insert(config_map::CHECKSUM_ANNOTATION.to_string(), checksum);
+    }
+    PodTemplateSpec {
+        spec: Some(PodSpec {
+            containers,
+            volumes: fs.volumes.as_ref().map(|volumes| {
+                volumes
+                    .iter()
+                    .filter(|volume| !(claim_templates && volume.persistence.is_some()))
+                    .map(|volume| build_volume(fs, volume))
+                    .collect()
+            }),
+            image_pull_secrets: fs.image_pull_secrets.as_ref().map(|secrets| {
+                secrets
+                    .iter()
+                    .map(|name| LocalObjectReference {
+                        name: Some(name.to_owned()),
+                    })
+                    .collect()
+            }),
+            node_selector: fs
+                .node_selector
+                .clone()
+                .filter(|node_selector| !node_selector.is_empty()),
+            tolerations: fs
+                .tolerations
+                .as_ref()
+                .map(|tolerations| tolerations.iter().map(build_toleration).collect()),
+            affinity: fs
+                .affinity
+                .as_ref()
+                .and_then(|affinity| build_affinity(fox_svc, affinity)),
+            topology_spread_constraints: non_empty(&fs.topology_spread).map(|constraints| {
+                constraints
+                    .iter()
+                    .map(|constraint| build_topology_spread(fox_svc, constraint))
+                    .collect()
+            }),
+            init_containers: non_empty(&fs.init_containers).map(|containers| {
+                containers
+                    .iter()
+                    .map(|container| build_container(fs, container))
+                    .collect()
+            }),
+            security_context: fs
+                .security_context
+                .as_ref()
+                .map(|context| PodSecurityContext {
+                    run_as_user: context.run_as_user,
+                    run_as_group: context.run_as_group,
+                    fs_group: context.fs_group,
+                    run_as_non_root: context.run_as_non_root,
+                    ..PodSecurityContext::default()
+                }),
+            service_account_name: fs
+                .service_account
+                .as_ref()
+                .map(|account| account.name.to_owned()),
+            termination_grace_period_seconds: fs.termination_grace_period_seconds,
+            priority_class_name: fs.priority_class_name.clone(),
+            ..PodSpec::default()
+        }),
+        metadata: Some(ObjectMeta {
+            labels: Some(pod_labels),
+            annotations: super::annotations(fox_svc, Some(annotations)),
+            ..ObjectMeta::default()
+        }),
+    }
+}
+
+/// Translates the rollout strategy of the `FoxService` specification into a Kubernetes
+/// `DeploymentStrategy`.
+fn build_strategy(strategy: &FoxServiceStrategy) -> DeploymentStrategy {
+    let rolling_update = match strategy.type_ {
+        // `OnDelete` is rejected by the validation, deployments don't support it.
+        StrategyType::Recreate | StrategyType::OnDelete => None,
+        StrategyType::RollingUpdate => Some(RollingUpdateDeployment {
+            max_surge: strategy.max_surge.clone(),
+            max_unavailable: strategy.max_unavailable.clone(),
+        }),
+    };
+    DeploymentStrategy {
+        type_: Some(format!("{:?}", strategy.type_)),
+        rolling_update,
+    }
+}
+
+/// Translates a container of the `FoxService` specification into a Kubernetes container. Used for
+/// both init containers and main containers.
+fn build_container(fs: &FoxServiceSpec, container: &FoxServiceContainer) -> Container {
+    let ports = container.ports.as_ref().map(|ports| {
+        ports
+            .iter()
+            .map(|port| ContainerPort {
+                name: port.name.clone(),
+                container_port: port.container_port,
+                host_port: port.host_port,
+                protocol: Some(
+                    port.protocol
+                        .unwrap_or(PortProtocol::Tcp)
+                        .as_str()
+                        .to_string(),
+                ),
+                ..ContainerPort::default()
+            })
+            .collect()
+    });
+    let env = container
+        .env
+        .as_ref()
+        .map(|env| env.iter().map(build_env_var).collect());
+    Container {
+        name: container.name.to_owned(),
+        image: Some(container.image.to_owned()),
+        image_pull_policy: Some(
+            match container.effective_image_pull_policy() {
+                ImagePullPolicy::Always => "Always",
+                ImagePullPolicy::IfNotPresent => "IfNotPresent",
+                ImagePullPolicy::Never => "Never",
+            }
+            .to_string(),
+        ),
+        command: container.command.clone(),
+        args: container.args.clone(),
+        env,
+        env_from: container.env_from.as_ref().map(|env_from| {
+            env_from
+                .iter()
+                .map(|env_from| build_env_from(fs, env_from))
+                .collect()
+        }),
+        ports,
+        liveness_probe: container.liveness_probe.as_ref().map(build_probe),
+        readiness_probe: container.readiness_probe.as_ref().map(build_probe),
+        startup_probe: container.startup_probe.as_ref().map(build_probe),
+        volume_mounts: container.volume_mounts.as_ref().map(|mounts| {
+            mounts
+                .iter()
+                .map(|mount| VolumeMount {
+                    name: mount.name.to_owned(),
+                    mount_path: mount.mount_path.to_owned(),
+                    read_only: mount.read_only,
+                    sub_path: mount.sub_path.clone(),
+                    ..VolumeMount::default()
+                })
+                .collect()
+        }),
+        lifecycle: container.lifecycle.as_ref().map(|lifecycle| Lifecycle {
+            post_start: lifecycle.post_start.as_ref().map(build_handler),
+            pre_stop: lifecycle.pre_stop.as_ref().map(build_handler),
+        }),
+        security_context: container
+            .security_context
+            .as_ref()
+            .map(|context| SecurityContext {
+                allow_privilege_escalation: context.allow_privilege_escalation,
+                read_only_root_filesystem: context.read_only_root_filesystem,
+                capabilities: context
+                    .capabilities
+                    .as_ref()
+                    .map(|capabilities| Capabilities {
+                        add: capabilities.add.clone(),
+                        drop: capabilities.drop.clone(),
+                    }),
+                privileged: context.privileged,
+                ..SecurityContext::default()
+            }),
+        ..Container::default()
+    }
+}
+
+/// Translates an environment variable of the `FoxService` specification into a Kubernetes
+/// `EnvVar`, setting either its literal value or the source it is read from.
+fn build_env_var(entry: &EnvEntry) -> EnvVar {
+    let value_from = entry.value_from.as_ref().map(|source| EnvVarSource {
+        secret_key_ref: source
+            .secret_key_ref
+            .as_ref()
+            .map(|secret_key_ref| SecretKeySelector {
+                name: Some(secret_key_ref.name.to_owned()),
+                key: secret_key_ref.key.to_owned(),
+                optional: None,
+            }),
+        config_map_key_ref: source
+            .config_map_key_ref
+            .as_ref()
+            .map(|config_map_key_ref| ConfigMapKeySelector {
+                name: Some(config_map_key_ref.name.to_owned()),
+                key: config_map_key_ref.key.to_owned(),
+                optional: None,
+            }),
+        field_ref: source
+            .field_ref
+            .as_ref()
+            .map(|field_ref| ObjectFieldSelector {
+                // Defaulted by the API server, set here so the live pod spec compares equal.
+                api_version: Some("v1".to_string()),
+                field_path: field_ref.field_path.to_owned(),
+            }),
+        ..EnvVarSource::default()
+    });
+    EnvVar {
+        name: entry.name.to_owned(),
+        value: entry.value.clone(),
+        value_from,
+    }
+}
+
+/// Translates a toleration of the `FoxService` specification into a Kubernetes `Toleration`.
+fn build_toleration(toleration: &FoxServiceToleration) -> Toleration {
+    Toleration {
+        key: toleration.key.clone(),
+        operator: toleration
+            .operator
+            .map(|operator| format!("{:?}", operator)),
+        value: toleration.value.clone(),
+        effect: toleration.effect.map(|effect| format!("{:?}", effect)),
+        toleration_seconds: toleration.toleration_seconds,
+    }
+}
+
+/// Translates the affinity of the `FoxService` specification into a Kubernetes `Affinity`. Parts
+/// that are absent in the specification are omitted, instead of being emitted as empty objects
+/// that would differ from the live pod spec.
+fn build_affinity(fox_svc: &FoxService, affinity: &FoxServiceAffinity) -> Option<Affinity> {
+    let node_affinity = affinity.node_affinity.as_ref().map(|node| NodeAffinity {
+        required_during_scheduling_ignored_during_execution: non_empty(&node.required).map(
+            |terms| NodeSelector {
+                node_selector_terms: terms.iter().map(build_node_selector_term).collect(),
+            },
+        ),
+        preferred_during_scheduling_ignored_during_execution: non_empty(&node.preferred).map(
+            |terms| {
+                terms
+                    .iter()
+                    .map(|term| PreferredSchedulingTerm {
+                        weight: term.weight,
+                        preference: build_node_selector_term(&term.preference),
+                    })
+                    .collect()
+            },
+        ),
+    });
+    let pod_anti_affinity = affinity
+        .pod_anti_affinity
+        .as_ref()
+        .map(|pod| PodAntiAffinity {
+            required_during_scheduling_ignored_during_execution: non_empty(&pod.required).map(
+                |terms| {
+                    terms
+                        .iter()
+                        .map(|term| build_pod_affinity_term(fox_svc, term))
+                        .collect()
+                },
+            ),
+            preferred_during_scheduling_ignored_during_execution: non_empty(&pod.preferred).map(
+                |terms| {
+                    terms
+                        .iter()
+                        .map(|term| WeightedPodAffinityTerm {
+                            weight: term.weight,
+                            pod_affinity_term: build_pod_affinity_term(fox_svc, &term.term),
+                        })
+                        .collect()
+                },
+            ),
+        });
+    if node_affinity.is_none() && pod_anti_affinity.is_none() {
+        return None;
+    }
+    Some(Affinity {
+        node_affinity,
+        pod_affinity: None,
+        pod_anti_affinity,
+    })
+}
+
+/// Translates a node selector term of the `FoxService` specification into a Kubernetes one.
+fn build_node_selector_term(term: &FoxServiceNodeSelectorTerm) -> NodeSelectorTerm {
+    NodeSelectorTerm {
+        match_expressions: Some(
+            term.match_expressions
+                .iter()
+                .map(|requirement| NodeSelectorRequirement {
+                    key: requirement.key.to_owned(),
+                    operator: format!("{:?}", requirement.operator),
+                    values: requirement.values.clone(),
+                })
+                .collect(),
+        ),
+        match_fields: None,
+    }
+}
+
+/// Translates a pod affinity term of the `FoxService` specification into a Kubernetes one. The
+/// term selects the pods of the service itself, unless other labels are given.
+fn build_pod_affinity_term(
+    fox_svc: &FoxService,
+    term: &FoxServicePodAffinityTerm,
+) -> PodAffinityTerm {
+    PodAffinityTerm {
+        label_selector: Some(LabelSelector {
+            match_labels: Some(
+                term.match_labels
+                    .clone()
+                    .unwrap_or_else(|| selector_labels(fox_svc)),
+            ),
+            ..LabelSelector::default()
+        }),
+        namespaces: None,
+        topology_key: term.topology_key.to_owned(),
+    }
+}
+
+/// Translates a topology spread constraint of the `FoxService` specification into a Kubernetes
+/// one. The constraint spreads the pods of the service itself, unless other labels are given, as
+/// users can't be expected to know the labels the operator puts on the pods.
+fn build_topology_spread(
+    fox_svc: &FoxService,
+    constraint: &FoxServiceTopologySpread,
+) -> TopologySpreadConstraint {
+    TopologySpreadConstraint {
+        label_selector: Some(LabelSelector {
+            match_labels: Some(
+                constraint
+                    .match_labels
+                    .clone()
+                    .unwrap_or_else(|| selector_labels(fox_svc)),
+            ),
+            ..LabelSelector::default()
+        }),
+        max_skew: constraint.max_skew,
+        topology_key: constraint.topology_key.to_owned(),
+        when_unsatisfiable: format!("{:?}", constraint.when_unsatisfiable),
+    }
+}
+
+/// Returns the list unless it is absent or empty.
+fn non_empty<T>(list: &Option<Vec<T>>) -> Option<&Vec<T>> {
+    list.as_ref().filter(|list| !list.is_empty())
+}
+
+/// Translates an import of environment variables of the `FoxService` specification into a
+/// Kubernetes `EnvFromSource`.
+fn build_env_from(fs: &FoxServiceSpec, env_from: &FoxServiceEnvFrom) -> EnvFromSource {
+    let config_map = match env_from.config_files {
+        Some(true) => Some(config_map::config_map_name(fs)),
+        _ => env_from.config_map.clone(),
+    };
+    EnvFromSource {
+        config_map_ref: config_map.map(|name| ConfigMapEnvSource {
+            name: Some(name),
+            optional: env_from.optional,
+        }),
+        secret_ref: env_from.secret.as_ref().map(|name| SecretEnvSource {
+            name: Some(name.to_owned()),
+            optional: env_from.optional,
+        }),
+        prefix: env_from.prefix.clone(),
+    }
+}
+
+/// Translates a volume of the `FoxService` specification into a Kubernetes volume.
+fn build_volume(fs: &FoxServiceSpec, volume: &FoxServiceVolume) -> Volume {
+    Volume {
+        name: volume.name.to_owned(),
+        empty_dir: volume
+            .empty_dir
+            .as_ref()
+            .map(|empty_dir| EmptyDirVolumeSource {
+                medium: empty_dir.medium.clone(),
+                size_limit: empty_dir.size_limit.clone().map(Quantity),
+            }),
+        config_map: match volume.config_files {
+            Some(true) => Some(ConfigMapVolumeSource {
+                name: Some(config_map::config_map_name(fs)),
+                ..ConfigMapVolumeSource::default()
+            }),
+            _ => volume
+                .config_map
+                .as_ref()
+                .map(|config_map| ConfigMapVolumeSource {
+                    name: Some(config_map.name.to_owned()),
+                    optional: config_map.optional,
+                    ..ConfigMapVolumeSource::default()
+                }),
+        },
+        secret: volume.secret.as_ref().map(|secret| SecretVolumeSource {
+            secret_name: Some(secret.secret_name.to_owned()),
+            optional: secret.optional,
+            ..SecretVolumeSource::default()
+        }),
+        persistent_volume_claim: volume.persistence.as_ref().map(|_| {
+            PersistentVolumeClaimVolumeSource {
+                claim_name: claim_name(fs, volume),
+                read_only: None,
+            }
+        }),
+        ..Volume::default()
+    }
+}
+
+/// Translates a probe of the `FoxService` specification into a Kubernetes probe.
+fn build_probe(probe: &FoxServiceProbe) -> Probe {
+    Probe {
+        http_get: probe.http_get.as_ref().map(|http_get| HTTPGetAction {
+            path: Some(http_get.path.to_owned()),
+            port: http_get.port.clone(),
+            ..HTTPGetAction::default()
+        }),
+        tcp_socket: probe.tcp_socket.as_ref().map(|tcp_socket| TCPSocketAction {
+            port: tcp_socket.port.clone(),
+            ..TCPSocketAction::default()
+        }),
+        exec: probe.exec.as_ref().map(|exec| ExecAction {
+            command: Some(exec.command.clone()),
+        }),
+        initial_delay_seconds: probe.initial_delay_seconds,
+        period_seconds: probe.period_seconds,
+        failure_threshold: probe.failure_threshold,
+        ..Probe::default()
+    }
+}
+
+/// Translates a lifecycle hook of the `FoxService` specification into a Kubernetes handler.
+fn build_handler(handler: &LifecycleHandler) -> Handler {
+    Handler {
+        http_get: handler.http_get.as_ref().map(|http_get| HTTPGetAction {
+            path: Some(http_get.path.to_owned()),
+            port: http_get.port.clone(),
+            ..HTTPGetAction::default()
+        }),
+        exec: handler.exec.as_ref().map(|exec| ExecAction {
+            command: Some(exec.command.clone()),
+        }),
+        tcp_socket: None,
+    }
+}
+
+/// Creates the deployment running the pods of a `FoxService`: looks up a deployment of the same
+/// name first, then applies the desired state built by `build_deployment` unless the existing one
+/// belongs to someone else.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to create the deployment with.
+/// - `fox_svc` - The `FoxService` resource owning the deployment
+/// - `namespace` - Namespace to create the Kubernetes Deployment in.
+///
+/// Note: A deployment of the same name left behind, e.g., by a crash after creating it but before
+/// recording it in the status, is adopted and brought in line with the specification if it
+/// belongs to the `FoxService`, see `is_managed`. Returns an `AlreadyExists` error if it doesn't.
+pub async fn create_deployment(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Deployment, Error> {
+    let name = &fox_svc.spec.name;
+    match get_deployment(client.clone(), name, namespace).await? {
+        Some(live) if !is_managed(&live.metadata, fox_svc) => {
+            Err(already_exists_error("deployments.apps", name))
+        }
+        _ => apply_deployment(client, fox_svc, namespace).await,
+    }
+}
+
+/// Fetches the deployment with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the Deployment with
+/// - `name` - Name of the deployment to fetch
+/// - `namespace` - Namespace the deployment resides in
+///
+/// Returns `Ok(None)` if there is no such deployment.
+pub async fn get_deployment(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<Deployment>, Error> {
+    let api: Api<Deployment> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(deployment) => Ok(Some(deployment)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Applies the deployment built from the given Fox service specification server-side, creating
+/// it if it doesn't exist yet.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to apply the deployment with.
+/// - `fox_svc` - The `FoxService` resource owning the deployment
+/// - `namespace` - Namespace the deployment resides in.
+///
+/// Note: The full desired deployment is applied as `FIELD_MANAGER`, so fields removed from the
+/// specification are removed from the deployment as well, while fields set by others are kept.
+pub async fn apply_deployment(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Deployment, Error> {
+    let patch = apply_body(fox_svc, namespace)?;
+    let deployment_api: Api<Deployment> = Api::namespaced(client, namespace);
+    deployment_api
+        .patch(&fox_svc.spec.name, &apply_params(), &Patch::Apply(&patch))
+        .await
+}
+
+/// Builds the body applying the deployment of a `FoxService`, see `apply_deployment`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the deployment
+/// - `namespace` - Namespace the deployment resides in.
+pub fn apply_body(fox_svc: &FoxService, namespace: &str) -> Result<Value, Error> {
+    let deployment: Deployment = build_deployment(fox_svc, namespace);
+    let mut body = serde_json::to_value(&deployment).map_err(Error::SerdeError)?;
+    // The API server defaults the parameters of a rolling update strategy, those aren't managed
+    // by anyone and have to be removed explicitly. The API server rejects them for `Recreate`.
+    let recreate = fox_svc
+        .spec
+        .strategy
+        .as_ref()
+        .map(|strategy| strategy.type_);
+    if recreate == Some(StrategyType::Recreate) {
+        body["spec"]["strategy"]["rollingUpdate"] = Value::Null;
+    }
+    Ok(body)
+}
+
+/// Decides whether a live deployment has drifted from the desired state.
+///
+/// Only the fields set by `build_deployment` are compared. The API server fills in defaults for
+/// many other fields (and for some of ours, e.g. the port protocol), comparing those would cause
+/// an update on every single reconciliation.
+///
+/// # Arguments
+/// - `desired` - Deployment as built by `build_deployment`
+/// - `live` - Deployment as currently present in the cluster
+pub fn needs_update(desired: &Deployment, live: &Deployment) -> bool {
+    let (desired_metadata, live_metadata) = (&desired.metadata, &live.metadata);
+    let (desired, live) = match (desired.spec.as_ref(), live.spec.as_ref()) {
+        (Some(desired), Some(live)) => (desired, live),
+        (desired, live) => return desired.is_some() != live.is_some(),
+    };
+    if desired.replicas.is_some() && desired.replicas != live.replicas {
+        return true;
+    }
+    if with_strategy_defaults(&desired.strategy) != with_strategy_defaults(&live.strategy) {
+        return true;
+    }
+    if desired
+        .revision_history_limit
+        .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT)
+        != live
+            .revision_history_limit
+            .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT)
+        || desired
+            .progress_deadline_seconds
+            .unwrap_or(DEFAULT_PROGRESS_DEADLINE_SECONDS)
+            != live
+                .progress_deadline_seconds
+                .unwrap_or(DEFAULT_PROGRESS_DEADLINE_SECONDS)
+    {
+        return true;
+    }
+    // Deployments created by earlier versions of the operator are adopted this way. Owner
+    // references added by others, e.g., by backup tools, are left alone.
+    if controller_uid(desired_metadata) != controller_uid(live_metadata) {
+        return true;
+    }
+    pod_template_differs(&desired.template, &live.template)
+}
+
+/// Compares the operator-managed fields of two pod templates, as built by `build_pod_template`.
+///
+/// # Arguments
+/// - `desired` - Pod template as built by `build_pod_template`
+/// - `live` - Pod template of a workload as currently present in the cluster
+pub fn pod_template_differs(desired: &PodTemplateSpec, live: &PodTemplateSpec) -> bool {
+    // Labels and annotations added by others, e.g., by `kubectl rollout restart`, are tolerated.
+    let desired_meta = desired.metadata.clone().unwrap_or_default();
+    let live_meta = live.metadata.clone().unwrap_or_default();
+    if !contains_all(&live_meta.labels, &desired_meta.labels)
+        || !contains_all(&live_meta.annotations, &desired_meta.annotations)
+    {
+        return true;
+    }
+    pod_spec_differs(desired.spec.as_ref(), live.spec.as_ref())
+}
+
+/// Compares the operator-managed fields of two pod specifications.
+fn pod_spec_differs(desired: Option<&PodSpec>, live: Option<&PodSpec>) -> bool {
+    let (desired, live) = match (desired, live) {
+        (Some(desired), Some(live)) => (desired, live),
+        (desired, live) => return desired.is_some() != live.is_some(),
+    };
+    containers_differ(&desired.containers, &live.containers)
+        || containers_differ(
+            desired.init_containers.as_deref().unwrap_or_default(),
+            live.init_containers.as_deref().unwrap_or_default(),
+        )
+        || with_volume_defaults(&desired.volumes) != with_volume_defaults(&live.volumes)
+        || desired.image_pull_secrets.as_deref().unwrap_or_default()
+            != live.image_pull_secrets.as_deref().unwrap_or_default()
+        || node_selector(desired) != node_selector(live)
+        || desired.tolerations.as_deref().unwrap_or_default()
+            != live.tolerations.as_deref().unwrap_or_default()
+        || desired.affinity != live.affinity
+        || desired.service_account_name != live.service_account_name
+        || desired.priority_class_name != live.priority_class_name
+        || desired
+            .termination_grace_period_seconds
+            .unwrap_or(DEFAULT_TERMINATION_GRACE_PERIOD_SECONDS)
+            != live
+                .termination_grace_period_seconds
+                .unwrap_or(DEFAULT_TERMINATION_GRACE_PERIOD_SECONDS)
+        // The API server defaults the pod security context to an empty one.
+        || desired.security_context.clone().unwrap_or_default()
+            != live.security_context.clone().unwrap_or_default()
+        || desired
+            .topology_spread_constraints
+            .as_deref()
+            .unwrap_or_default()
+            != live
+                .topology_spread_constraints
+                .as_deref()
+                .unwrap_or_default()
+}
+
+/// Returns the node selector of a pod spec, an empty one being the same as none at all.
+fn node_selector(pod_spec: &PodSpec) -> Option<&BTreeMap<String, String>> {
+    pod_spec
+        .node_selector
+        .as_ref()
+        .filter(|node_selector| !node_selector.is_empty())
+}
+
+/// Compares two lists of containers pairwise.
+fn containers_differ(desired: &[Container], live: &[Container]) -> bool {
+    desired.len() != live.len()
+        || desired
+            .iter()
+            .zip(live)
+            .any(|(desired, live)| container_differs(desired, live))
+}
+
+/// Compares the operator-managed fields of two containers.
+fn container_differs(desired: &Container, live: &Container) -> bool {
+    desired.name != live.name
+        || desired.image != live.image
+        || desired.image_pull_policy != live.image_pull_policy
+        || desired.command.as_deref().unwrap_or_default()
+            != live.command.as_deref().unwrap_or_default()
+        || desired.args.as_deref().unwrap_or_default() != live.args.as_deref().unwrap_or_default()
+        || desired.env.as_deref().unwrap_or_default() != live.env.as_deref().unwrap_or_default()
+        || desired.env_from.as_deref().unwrap_or_default()
+            != live.env_from.as_deref().unwrap_or_default()
+        || sorted_ports(desired) != sorted_ports(live)
+        || with_probe_defaults(&desired.liveness_probe) != with_probe_defaults(&live.liveness_probe)
+        || with_probe_defaults(&desired.readiness_probe)
+            != with_probe_defaults(&live.readiness_probe)
+        || with_probe_defaults(&desired.startup_probe) != with_probe_defaults(&live.startup_probe)
+        || desired.security_context != live.security_context
+        || with_lifecycle_defaults(&desired.lifecycle) != with_lifecycle_defaults(&live.lifecycle)
+        || with_mount_defaults(&desired.volume_mounts) != with_mount_defaults(&live.volume_mounts)
+}
+
+/// Rollout strategy with the defaults applied by the API server filled in, a rolling update with
+/// a maximum surge and a maximum of unavailable pods of 25% each.
+fn with_strategy_defaults(strategy: &Option<DeploymentStrategy>) -> DeploymentStrategy {
+    let mut strategy = strategy.clone().unwrap_or_default();
+    let type_ = strategy
+        .type_
+        .get_or_insert_with(|| format!("{:?}", StrategyType::RollingUpdate));
+    if type_ == &format!("{:?}", StrategyType::RollingUpdate) {
+        let rolling_update = strategy.rolling_update.get_or_insert_with(Default::default);
+        let default = || IntOrString::String("25%".to_string());
+        rolling_update.max_surge.get_or_insert_with(default);
+        rolling_update.max_unavailable.get_or_insert_with(default);
+    }
+    strategy
+}
+
+/// Volumes with the defaults applied by the API server filled in.
+fn with_volume_defaults(volumes: &Option<Vec<Volume>>) -> Vec<Volume> {
+    let mut volumes = volumes.clone().unwrap_or_default();
+    for volume in volumes.iter_mut() {
+        if let Some(config_map) = volume.config_map.as_mut() {
+            config_map.default_mode.get_or_insert(0o644);
+        }
+        if let Some(secret) = volume.secret.as_mut() {
+            secret.default_mode.get_or_insert(0o644);
+        }
+    }
+    volumes
+}
+
+/// Volume mounts with omitted `false` values filled in.
+fn with_mount_defaults(mounts: &Option<Vec<VolumeMount>>) -> Vec<VolumeMount> {
+    let mut mounts = mounts.clone().unwrap_or_default();
+    for mount in mounts.iter_mut() {
+        mount.read_only.get_or_insert(false);
+    }
+    mounts
+}
+
+/// A probe with the defaults applied by the API server filled in. Zero values are omitted by the
+/// API server, which is covered by defaulting them to zero here as well.
+fn with_probe_defaults(probe: &Option<Probe>) -> Option<Probe> {
+    probe.clone().map(|mut probe| {
+        probe.initial_delay_seconds.get_or_insert(0);
+        probe.timeout_seconds.get_or_insert(1);
+        probe.period_seconds.get_or_insert(10);
+        probe.success_threshold.get_or_insert(1);
+        probe.failure_threshold.get_or_insert(3);
+        if let Some(http_get) = probe.http_get.as_mut() {
+            http_get.scheme.get_or_insert_with(|| "HTTP".to_string());
+        }
+        probe
+    })
+}
+
+/// Lifecycle hooks with the scheme of HTTP requests defaulted, as done by the API server.
+fn with_lifecycle_defaults(lifecycle: &Option<Lifecycle>) -> Option<Lifecycle> {
+    lifecycle.clone().map(|mut lifecycle| {
+        let hooks = lifecycle
+            .post_start
+            .iter_mut()
+            .chain(lifecycle.pre_stop.iter_mut());
+        for http_get in hooks.filter_map(|handler| handler.http_get.as_mut()) {
+            http_get.scheme.get_or_insert_with(|| "HTTP".to_string());
+        }
+        lifecycle
+    })
+}
+
+/// Ports of a container as `(container port, host port, protocol, name)`, ordered by container
+/// port. A missing protocol is treated as `TCP`, which is what the API server defaults it to.
+fn sorted_ports(container: &Container) -> Vec<(i32, Option<i32>, &str, Option<&str>)> {
+    let mut ports: Vec<(i32, Option<i32>, &str, Option<&str>)> = container
+        .ports
+        .iter()
+        .flatten()
+        .map(|port| {
+            (
+                port.container_port,
+                port.host_port,
+                port.protocol.as_deref().unwrap_or("TCP"),
+                port.name.as_deref(),
+            )
+        })
+        .collect();
+    ports.sort_unstable();
+    ports
+}
+
+/// Deletes an existing deployment. Its pods are deleted before the deployment itself is gone, so
+/// a workload replacing it doesn't run alongside them for long.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the Deployment with
+/// - `name` - Name of the deployment to delete
+/// - `namespace` - Namespace the existing deployment resides in
+///
+/// Note: A deployment that doesn't exist is considered deleted, the workload may have been a
+/// StatefulSet instead.
+pub async fn delete_deployment(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<Deployment> = Api::namespaced(client, namespace);
+    match api.delete(name, &foreground_deletion()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use fox_k8s_crds::fox_service::builder::FoxServiceContainerBuilder;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+    use serde_json::json;
+
+    /// A `FoxService` with several environment variables, ports, pod labels and annotations,
+    /// which are all kept in maps or lists by the specification.
+    fn with_maps() -> FoxService {
+        let spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|container| {
+                container
+                    .image("nginx:1.21")
+                    .named_port("http", 80, PortProtocol::Tcp)
+                    .named_port("metrics", 9090, PortProtocol::Tcp)
+                    .env("LOG_LEVEL", "info")
+                    .env("BACKEND_URL", "http://backend")
+                    .env("ZONE", "eu-west-1a")
+            })
+            .pod_label("tier", "frontend")
+            .pod_label("app", "shop")
+            .pod_annotation("prometheus.io/scrape", "true")
+            .pod_annotation("example.com/owner", "team-a")
+            .build()
+            .unwrap();
+        FoxService::new("web", spec)
+    }
+
+    #[test]
+    fn build_deployment_is_deterministic() {
+        let first = serde_yaml::to_string(&build_deployment(&with_maps(), "default")).unwrap();
+        let second = serde_yaml::to_string(&build_deployment(&with_maps(), "default")).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn build_deployment_selects_its_own_pods() {
+        let deployment = serde_json::to_value(build_deployment(&with_maps(), "default")).unwrap();
+
+        let selector = deployment["spec"]["selector"]["matchLabels"]
+            .as_object()
+            .unwrap();
+        let labels = &deployment["spec"]["template"]["metadata"]["labels"];
+        assert!(!selector.is_empty());
+        for (key, value) in selector {
+            assert_eq!(&labels[key], value, "pod label {}", key);
+        }
+    }
+
+    #[test]
+    fn apply_body_is_the_full_deployment() {
+        let body = apply_body(&mock::fox_service(), "default").unwrap();
+
+        assert_eq!(body["apiVersion"], "apps/v1");
+        assert_eq!(body["kind"], "Deployment");
+        assert_eq!(body["metadata"]["name"], "web");
+        assert_eq!(body["metadata"]["namespace"], "default");
+        assert_eq!(body["spec"]["replicas"], 1);
+        let container = &body["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(container["name"], "web");
+        assert_eq!(container["image"], "nginx:1.21");
+        assert!(body["spec"].get("strategy").is_none());
+    }
+
+    #[test]
+    fn needs_update_tolerates_owner_references_of_others() {
+        let desired = build_deployment(&mock::fox_service(), "default");
+        let mut live = desired.clone();
+        live.metadata
+            .owner_references
+            .get_or_insert_with(Vec::new)
+            .push(OwnerReference {
+                api_version: "velero.io/v1".to_string(),
+                kind: "Backup".to_string(),
+                name: "nightly".to_string(),
+                uid: "uid-nightly".to_string(),
+                controller: None,
+                block_owner_deletion: None,
+            });
+        assert!(!needs_update(&desired, &live));
+
+        live.metadata.owner_references = None;
+        assert!(needs_update(&desired, &live));
+    }
+
+    #[test]
+    fn apply_body_removes_rolling_update_for_recreate() {
+        let spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|container| container.image("nginx:1.21").port(80))
+            .strategy(StrategyType::Recreate)
+            .build()
+            .unwrap();
+        let fox_svc = FoxService::new("web", spec);
+
+        let body = apply_body(&fox_svc, "default").unwrap();
+
+        assert_eq!(
+            body["spec"]["strategy"],
+            json!({ "type": "Recreate", "rollingUpdate": null })
+        );
+    }
+
+    /// The main container of the deployment of a single container service, configured by
+    /// `container`.
+    fn main_container(
+        container: impl FnOnce(FoxServiceContainerBuilder) -> FoxServiceContainerBuilder,
+    ) -> Container {
+        let spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|c| container(c.image("nginx:1.21").port(80)))
+            .build()
+            .unwrap();
+        build_container(&spec, &spec.containers[0])
+    }
+
+    #[test]
+    fn build_container_with_command_and_args() {
+        let container = main_container(|c| c.command(["/bin/server"]).arg("--port").arg("80"));
+
+        assert_eq!(container.command, Some(vec!["/bin/server".to_string()]));
+        assert_eq!(
+            container.args,
+            Some(vec!["--port".to_string(), "80".to_string()])
+        );
+    }
+
+    #[test]
+    fn build_container_with_only_args() {
+        let container = main_container(|c| c.arg("--verbose"));
+
+        assert_eq!(container.command, None);
+        assert_eq!(container.args, Some(vec!["--verbose".to_string()]));
+    }
+
+    #[test]
+    fn build_container_with_only_command() {
+        let container = main_container(|c| c.command(["sh", "-c", "exec nginx"]));
+
+        assert_eq!(
+            container.command,
+            Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "exec nginx".to_string()
+            ])
+        );
+        assert_eq!(container.args, None);
+    }
+
+    #[test]
+    fn build_container_without_command_and_args() {
+        let container = main_container(|c| c);
+
+        assert_eq!(container.command, None);
+        assert_eq!(container.args, None);
+    }
+
+    #[test]
+    fn build_deployment_sleeps_before_stop_within_the_grace_period() {
+        let sleep = LifecycleHandler {
+            http_get: None,
+            exec: Some(ExecProbe {
+                command: vec!["sh".to_string(), "-c".to_string(), "sleep 15".to_string()],
+                unknown_fields: BTreeMap::new(),
+            }),
+            unknown_fields: BTreeMap::new(),
+        };
+        let spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|c| {
+                c.image("nginx:1.21")
+                    .port(80)
+                    .lifecycle(FoxServiceLifecycle {
+                        post_start: None,
+                        pre_stop: Some(sleep.clone()),
+                        unknown_fields: BTreeMap::new(),
+                    })
+            })
+            .termination_grace_period_seconds(60)
+            .build()
+            .unwrap();
+
+        let handler = build_handler(&sleep);
+        assert_eq!(
+            handler.exec.and_then(|exec| exec.command),
+            Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "sleep 15".to_string()
+            ])
+        );
+        assert!(handler.http_get.is_none());
+
+        let deployment = build_deployment(&FoxService::new("web", spec), "default");
+        let pod_spec = deployment.spec.unwrap().template.spec.unwrap();
+        assert_eq!(pod_spec.termination_grace_period_seconds, Some(60));
+        let lifecycle = pod_spec.containers[0].lifecycle.clone().unwrap();
+        assert_eq!(lifecycle.pre_stop, Some(build_handler(&sleep)));
+        assert_eq!(lifecycle.post_start, None);
+    }
+}
@@ -0,0 +1,79 @@
+use super::ingress;
+use fox_k8s_crds::fox_service::*;
+use kube::api::ObjectMeta;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Annotation telling external-dns the name of the record pointing to a Service or an Ingress.
+const HOSTNAME_ANNOTATION: &str = "external-dns.alpha.kubernetes.io/hostname";
+
+/// Annotation telling external-dns the time to live of the record in seconds.
+const TTL_ANNOTATION: &str = "external-dns.alpha.kubernetes.io/ttl";
+
+/// Annotations read by external-dns. They are managed by the operator, set on the Service or the
+/// Ingress depending on the `dns` block and removed from the other.
+const ANNOTATIONS: [&str; 2] = [HOSTNAME_ANNOTATION, TTL_ANNOTATION];
+
+/// Which subresource of a `FoxService` external-dns publishes the record for.
+#[derive(PartialEq, Clone, Copy)]
+pub enum Target {
+    Service,
+    Ingress,
+}
+
+/// Annotations making external-dns publish the DNS record of a `FoxService` for the given
+/// subresource. Empty if there is no `dns` block, or if the record points to the other
+/// subresource: the Ingress if there is one, the Service otherwise.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+/// - `target` - Subresource to annotate
+pub fn annotations(fs: &FoxServiceSpec, target: Target) -> BTreeMap<String, String> {
+    let mut annotations = BTreeMap::new();
+    let published = if ingress::is_routed(fs) {
+        Target::Ingress
+    } else {
+        Target::Service
+    };
+    if let Some(dns) = fs.dns.as_ref().filter(|_| target == published) {
+        annotations.insert(HOSTNAME_ANNOTATION.to_string(), dns.hostname.to_owned());
+        if let Some(ttl) = dns.ttl {
+            annotations.insert(TTL_ANNOTATION.to_string(), ttl.to_string());
+        }
+    }
+    annotations
+}
+
+/// Checks whether the external-dns annotations of a live subresource differ from the desired ones,
+/// including annotations left behind by a removed `dns` block.
+///
+/// # Arguments
+/// - `desired` - Metadata of the subresource as built by the operator
+/// - `live` - Metadata of the subresource as currently present in the cluster
+pub fn differs(desired: &ObjectMeta, live: &ObjectMeta) -> bool {
+    let get = |metadata: &ObjectMeta, key: &str| {
+        metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(key).cloned())
+    };
+    ANNOTATIONS
+        .iter()
+        .any(|key| get(desired, key) != get(live, key))
+}
+
+/// Annotations of a merge patch of a subresource: the desired ones, with the external-dns
+/// annotations no longer desired set to `null`, so they are removed from the live subresource.
+/// Other annotations of the live subresource are kept.
+///
+/// # Arguments
+/// - `desired` - Annotations of the subresource as built by the operator
+pub fn patch_annotations(desired: &Option<BTreeMap<String, String>>) -> Value {
+    let mut patch = json!(desired.clone().unwrap_or_default());
+    for key in ANNOTATIONS.iter() {
+        if patch.get(key).is_none() {
+            patch[key] = Value::Null;
+        }
+    }
+    patch
+}
@@ -0,0 +1,162 @@
+use super::deployment::build_pod_template_with;
+use super::{job_labels, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use kube::api::{ObjectMeta, PostParams};
+use kube::{Api, Client, Error, Resource};
+use std::collections::BTreeMap;
+
+/// Annotation of the workload recording the generation of the `FoxService` its pre-deploy hooks
+/// last completed for. Hooks don't run again while the workload is brought back in line with an
+/// unchanged specification.
+pub const GENERATION_ANNOTATION: &str = "fox.cbopt.com/pre-deploy-generation";
+
+/// Seconds finished hook Jobs are kept for inspection before they are deleted.
+const TTL_SECONDS_AFTER_FINISHED: i32 = 3600;
+
+/// Progress of the pre-deploy hooks of a `FoxService`.
+pub enum HookState {
+    /// All hooks completed, the workload may be rolled out.
+    Completed,
+    /// The Job of a hook was just created.
+    Started(String),
+    /// The Job of a hook is still running.
+    Running,
+    /// The Job of a hook failed, the rollout is stopped.
+    Failed(String),
+}
+
+/// Name of the Job running a pre-deploy hook for a generation of a `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the hook belongs to
+/// - `hook` - The hook run by the Job
+pub fn job_name(fox_svc: &FoxService, hook: &FoxServiceContainer) -> String {
+    format!(
+        "{}-{}-{}",
+        fox_svc.spec.name,
+        hook.name,
+        fox_svc.meta().generation.unwrap_or_default()
+    )
+}
+
+/// Pre-deploy hooks of a Fox service specification, in the order they are run.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn pre_deploy_hooks(fs: &FoxServiceSpec) -> &[FoxServiceContainer] {
+    fs.hooks
+        .as_ref()
+        .and_then(|hooks| hooks.pre_deploy.as_deref())
+        .unwrap_or_default()
+}
+
+/// Annotations of the workload of a `FoxService` recording the generation its pre-deploy hooks
+/// completed for, if there are any hooks.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the workload
+pub fn generation_annotation(fox_svc: &FoxService) -> Option<BTreeMap<String, String>> {
+    if pre_deploy_hooks(&fox_svc.spec).is_empty() {
+        return None;
+    }
+    let mut annotations = BTreeMap::new();
+    annotations.insert(
+        GENERATION_ANNOTATION.to_string(),
+        fox_svc.meta().generation.unwrap_or_default().to_string(),
+    );
+    Some(annotations)
+}
+
+/// Checks whether the pre-deploy hooks already completed for the current generation of a
+/// `FoxService`, as recorded on its live workload.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the hooks belong to
+/// - `workload` - Metadata of the live workload, if any
+pub fn is_done(fox_svc: &FoxService, workload: Option<&ObjectMeta>) -> bool {
+    let annotations = match generation_annotation(fox_svc) {
+        Some(annotations) => annotations,
+        None => return true,
+    };
+    let recorded = workload
+        .and_then(|metadata| metadata.annotations.as_ref())
+        .and_then(|annotations| annotations.get(GENERATION_ANNOTATION));
+    recorded == annotations.get(GENERATION_ANNOTATION)
+}
+
+/// Builds the Job running a pre-deploy hook. Its pods share the pod-level settings of the
+/// workload, e.g., volumes and the service account, and are not retried in place.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the Job
+/// - `hook` - The hook to run
+/// - `namespace` - Namespace the Job is going to reside in.
+pub fn build_job(fox_svc: &FoxService, hook: &FoxServiceContainer, namespace: &str) -> Job {
+    let mut template = build_pod_template_with(fox_svc, &[hook]);
+    if let Some(spec) = template.spec.as_mut() {
+        spec.restart_policy = Some("Never".to_string());
+    }
+    if let Some(metadata) = template.metadata.as_mut() {
+        metadata.labels = Some(job_labels(fox_svc, "pre-deploy-hook"));
+    }
+    Job {
+        metadata: ObjectMeta {
+            name: Some(job_name(fox_svc, hook)),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(job_labels(fox_svc, "pre-deploy-hook")),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(JobSpec {
+            template,
+            ttl_seconds_after_finished: Some(TTL_SECONDS_AFTER_FINISHED),
+            ..JobSpec::default()
+        }),
+        ..Job::default()
+    }
+}
+
+/// Advances the pre-deploy hooks of a `FoxService` for its current generation. The hooks are run
+/// one after another, the Job of the first hook not completed yet is created if it doesn't exist.
+/// The function doesn't wait for the Job, the caller is expected to check again later.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the Jobs with.
+/// - `fox_svc` - The `FoxService` resource owning the hooks
+/// - `namespace` - Namespace the Jobs reside in.
+pub async fn run_pre_deploy_hooks(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<HookState, Error> {
+    let api: Api<Job> = Api::namespaced(client, namespace);
+    for hook in pre_deploy_hooks(&fox_svc.spec) {
+        let name = job_name(fox_svc, hook);
+        let job = match api.get(&name).await {
+            Ok(job) => job,
+            Err(Error::Api(response)) if response.code == 404 => {
+                api.create(&PostParams::default(), &build_job(fox_svc, hook, namespace))
+                    .await?;
+                return Ok(HookState::Started(name));
+            }
+            Err(error) => return Err(error),
+        };
+        if has_condition(&job, "Failed") {
+            return Ok(HookState::Failed(name));
+        }
+        if !has_condition(&job, "Complete") {
+            return Ok(HookState::Running);
+        }
+    }
+    Ok(HookState::Completed)
+}
+
+/// Checks whether the Job controller reports a condition of the given type as true.
+fn has_condition(job: &Job, type_: &str) -> bool {
+    job.status
+        .iter()
+        .flat_map(|status| status.conditions.iter().flatten())
+        .any(|condition| condition.type_ == type_ && condition.status == "True")
+}
@@ -0,0 +1,165 @@
+use super::{metadata_differs, owner_reference, workload};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::autoscaling::v2beta2::{
+    HorizontalPodAutoscaler, HorizontalPodAutoscalerSpec, MetricSpec, MetricTarget,
+    ResourceMetricSource,
+};
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+
+/// Builds a metric targeting the average utilization of a resource by the pods.
+fn utilization(resource: &str, average_utilization: i32) -> MetricSpec {
+    MetricSpec {
+        resource: Some(ResourceMetricSource {
+            name: resource.to_string(),
+            target: MetricTarget {
+                average_utilization: Some(average_utilization),
+                type_: "Utilization".to_string(),
+                ..MetricTarget::default()
+            },
+        }),
+        type_: "Resource".to_string(),
+        ..MetricSpec::default()
+    }
+}
+
+/// Builds the desired state of the HorizontalPodAutoscaler scaling the workload of a
+/// `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the autoscaler
+/// - `autoscaling` - Autoscaling configuration of the service
+/// - `namespace` - Namespace the HorizontalPodAutoscaler is going to reside in.
+pub fn build_horizontal_pod_autoscaler(
+    fox_svc: &FoxService,
+    autoscaling: &FoxServiceAutoscaling,
+    namespace: &str,
+) -> HorizontalPodAutoscaler {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let cpu = autoscaling
+        .target_cpu_utilization
+        .map(|target| utilization("cpu", target));
+    let memory = autoscaling
+        .target_memory_utilization
+        .map(|target| utilization("memory", target));
+    HorizontalPodAutoscaler {
+        metadata: ObjectMeta {
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(HorizontalPodAutoscalerSpec {
+            behavior: None,
+            max_replicas: autoscaling.max_replicas,
+            metrics: Some(cpu.into_iter().chain(memory).collect()),
+            min_replicas: autoscaling.min_replicas,
+            // A DaemonSet can't be scaled, autoscaling is rejected by the validation for it.
+            scale_target_ref: workload::target_ref(fs),
+        }),
+        ..HorizontalPodAutoscaler::default()
+    }
+}
+
+/// Creates the HorizontalPodAutoscaler of a `FoxService` if autoscaling is enabled and the
+/// autoscaler doesn't exist yet, and patches an existing autoscaler if the bounds or targets
+/// changed. The autoscaler is deleted once autoscaling is disabled, the workload is scaled to
+/// `replicas` again by its next update.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the HorizontalPodAutoscaler with.
+/// - `fox_svc` - The `FoxService` resource owning the autoscaler
+/// - `namespace` - Namespace the HorizontalPodAutoscaler resides in.
+pub async fn apply_horizontal_pod_autoscaler(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<HorizontalPodAutoscaler> = Api::namespaced(client.clone(), namespace);
+    let live = match api.get(&fs.name).await {
+        Ok(live) => Some(live),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    match (&fs.autoscaling, live) {
+        (Some(autoscaling), None) => {
+            let autoscaler = build_horizontal_pod_autoscaler(fox_svc, autoscaling, namespace);
+            api.create(&PostParams::default(), &autoscaler).await?;
+        }
+        (Some(autoscaling), Some(live)) => {
+            let desired = build_horizontal_pod_autoscaler(fox_svc, autoscaling, namespace);
+            if needs_update(&desired, &live) || metadata_differs(fox_svc, &live.metadata) {
+                let spec = desired.spec.unwrap_or_default();
+                // `null` restores the default minimum, lists are replaced as a whole.
+                let patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": desired.metadata.annotations
+                    },
+                    "spec": {
+                        "minReplicas": spec.min_replicas,
+                        "maxReplicas": spec.max_replicas,
+                        "metrics": spec.metrics,
+                        "scaleTargetRef": spec.scale_target_ref
+                    }
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+        }
+        (None, None) => {}
+        // An autoscaler of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_horizontal_pod_autoscaler(client, &fs.name, namespace).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decides whether a live autoscaler has drifted from the desired state. The scaling behavior is
+/// defaulted by the API server and not compared.
+///
+/// # Arguments
+/// - `desired` - HorizontalPodAutoscaler as built by `build_horizontal_pod_autoscaler`
+/// - `live` - HorizontalPodAutoscaler as currently present in the cluster
+pub fn needs_update(desired: &HorizontalPodAutoscaler, live: &HorizontalPodAutoscaler) -> bool {
+    let desired = desired.spec.clone().unwrap_or_default();
+    let live = live.spec.clone().unwrap_or_default();
+    desired.min_replicas.unwrap_or(1) != live.min_replicas.unwrap_or(1)
+        || desired.max_replicas != live.max_replicas
+        || desired.metrics.unwrap_or_default() != live.metrics.unwrap_or_default()
+        || desired.scale_target_ref != live.scale_target_ref
+}
+
+/// Deletes an existing HorizontalPodAutoscaler.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the HorizontalPodAutoscaler with
+/// - `name` - Name of the HorizontalPodAutoscaler to delete
+/// - `namespace` - Namespace the existing HorizontalPodAutoscaler resides in
+///
+/// Note: A HorizontalPodAutoscaler that doesn't exist is considered deleted.
+pub async fn delete_horizontal_pod_autoscaler(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<HorizontalPodAutoscaler> = Api::namespaced(client, namespace);
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
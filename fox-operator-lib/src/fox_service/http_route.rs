@@ -0,0 +1,170 @@
+use super::{is_subset, metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use kube::api::{
+    ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams, PostParams,
+};
+use kube::{Api, Client, Error, Resource};
+use serde_json::{json, Value};
+
+/// API group of the Gateway API.
+const GATEWAY_API_GROUP: &str = "gateway.networking.k8s.io";
+
+/// The HTTPRoute resource of the Gateway API. Its types are not part of `k8s-openapi`, so routes
+/// are managed as dynamic objects.
+fn api_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk(GATEWAY_API_GROUP, "v1", "HTTPRoute");
+    ApiResource::from_gvk_with_plural(&gvk, "httproutes")
+}
+
+/// Whether a `FoxService` routes its HTTP ingress points through a Gateway, and therefore has an
+/// HTTPRoute.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn is_routed(fs: &FoxServiceSpec) -> bool {
+    fs.gateway.is_some() && !fs.routed_ingress().is_empty()
+}
+
+/// Builds the desired state of the HTTPRoute attaching the HTTP ingress points of a `FoxService`
+/// to its Gateway. Hostnames apply to a route as a whole, so the route matches the endpoints of
+/// all HTTP ingress points, with a rule matching the path of each.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the route
+/// - `namespace` - Namespace the HTTPRoute is going to reside in.
+pub fn build_http_route(fox_svc: &FoxService, namespace: &str) -> DynamicObject {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let routed = fs.routed_ingress();
+    let mut hostnames: Vec<&str> = routed
+        .iter()
+        .map(|ingress| ingress.endpoint.as_str())
+        .collect();
+    hostnames.sort_unstable();
+    hostnames.dedup();
+    let rules: Vec<Value> = routed
+        .iter()
+        .map(|ingress| {
+            json!({
+                "matches": [{
+                    "path": { "type": "PathPrefix", "value": ingress.path }
+                }],
+                "backendRefs": [{ "name": fs.name, "port": ingress.port }]
+            })
+        })
+        .collect();
+
+    let parent_refs: Vec<Value> = fs
+        .gateway
+        .iter()
+        .map(|gateway| {
+            let mut parent_ref = json!({ "name": gateway.name });
+            if let Some(gateway_namespace) = &gateway.namespace {
+                parent_ref["namespace"] = json!(gateway_namespace);
+            }
+            if let Some(section_name) = &gateway.section_name {
+                parent_ref["sectionName"] = json!(section_name);
+            }
+            parent_ref
+        })
+        .collect();
+
+    let mut route = DynamicObject::new(&fs.name, &api_resource())
+        .within(namespace)
+        .data(json!({
+            "spec": {
+                "parentRefs": parent_refs,
+                "hostnames": hostnames,
+                "rules": rules
+            }
+        }));
+    route.metadata.labels = Some(super::labels(fox_svc));
+    route.metadata.annotations = super::annotations(fox_svc, None);
+    route.metadata.owner_references = Some(vec![owner_reference(fox_svc)]);
+    route
+}
+
+/// Creates the HTTPRoute of a `FoxService` if it routes HTTP ingress points through a Gateway and
+/// the route doesn't exist yet, and patches an existing route in place if it drifted from the
+/// specification. The route is deleted once the Gateway or the HTTP ingress points are removed.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the route with.
+/// - `fox_svc` - The `FoxService` resource owning the route
+/// - `namespace` - Namespace the route resides in.
+///
+/// Returns the route if it was created.
+pub async fn apply_http_route(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<DynamicObject>, Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &api_resource());
+    let live = match api.get(&fs.name).await {
+        Ok(route) => Some(route),
+        // Also returned if the Gateway API isn't installed, in which case there is nothing to delete
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    match live {
+        None if is_routed(fs) => {
+            let route = build_http_route(fox_svc, namespace);
+            Ok(Some(api.create(&PostParams::default(), &route).await?))
+        }
+        None => Ok(None),
+        Some(live) if is_routed(fs) => {
+            let desired = build_http_route(fox_svc, namespace);
+            if needs_update(&desired, &live) || metadata_differs(fox_svc, &live.metadata) {
+                let mut patch = desired.data.clone();
+                patch["metadata"] = json!({
+                    "labels": desired.metadata.labels,
+                    "annotations": desired.metadata.annotations
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+            Ok(None)
+        }
+        // A route of the same name not owned by the `FoxService` is left alone
+        Some(live) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_http_route(client, &fs.name, namespace).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Decides whether a live route has drifted from the desired state. The API server fills in
+/// defaults, e.g., the kind of the parent and backend references, so the live specification only
+/// has to contain the desired one.
+///
+/// # Arguments
+/// - `desired` - Route as built by `build_http_route`
+/// - `live` - Route as currently present in the cluster
+pub fn needs_update(desired: &DynamicObject, live: &DynamicObject) -> bool {
+    !is_subset(&desired.data["spec"], &live.data["spec"])
+}
+
+/// Deletes an existing route.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the HTTPRoute with
+/// - `name` - Name of the route to delete
+/// - `namespace` - Namespace the existing route resides in
+///
+/// Note: A route that doesn't exist is considered deleted.
+pub async fn delete_http_route(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client, namespace, &api_resource());
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
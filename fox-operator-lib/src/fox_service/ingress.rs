@@ -0,0 +1,278 @@
+use super::{certificate, dns, metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::networking::v1::{
+    HTTPIngressPath, HTTPIngressRuleValue, Ingress, IngressBackend, IngressRule,
+    IngressServiceBackend, IngressSpec, IngressTLS, ServiceBackendPort,
+};
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Environment variable holding the IngressClass used by Ingresses of services that don't name
+/// one. If neither is set, the default class of the cluster applies.
+const DEFAULT_CLASS_NAME_VAR: &str = "FOX_DEFAULT_INGRESS_CLASS";
+
+/// Annotation telling cert-manager which ClusterIssuer to issue the certificates of an Ingress with.
+const CLUSTER_ISSUER_ANNOTATION: &str = "cert-manager.io/cluster-issuer";
+
+/// Whether a `FoxService` has an Ingress, which is the case if it has HTTP ingress points using TCP
+/// and doesn't route them through a Gateway instead.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn is_routed(fs: &FoxServiceSpec) -> bool {
+    fs.gateway.is_none() && !fs.routed_ingress().is_empty()
+}
+
+/// Builds the desired state of the Ingress routing requests for the endpoints of the HTTP ingress
+/// points of a `FoxService` to its Service. There is one rule per endpoint, with a path per HTTP
+/// ingress point.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the ingress
+/// - `namespace` - Namespace the Ingress is going to reside in.
+pub fn build_ingress(fox_svc: &FoxService, namespace: &str) -> Ingress {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let mut paths: BTreeMap<&str, Vec<HTTPIngressPath>> = BTreeMap::new();
+    for ingress in fs.routed_ingress() {
+        // Named Service ports are referenced by their name, like the Service targets containers.
+        let port = match fs.ingress_port_name(ingress) {
+            Some(name) => ServiceBackendPort {
+                name: Some(name),
+                number: None,
+            },
+            None => ServiceBackendPort {
+                name: None,
+                number: Some(ingress.port),
+            },
+        };
+        paths
+            .entry(ingress.endpoint.as_str())
+            .or_default()
+            .push(HTTPIngressPath {
+                backend: IngressBackend {
+                    resource: None,
+                    service: Some(IngressServiceBackend {
+                        name: fs.name.to_owned(),
+                        port: Some(port),
+                    }),
+                },
+                path: Some(ingress.path.to_owned()),
+                path_type: Some("Prefix".to_string()),
+            });
+    }
+    let rules = paths
+        .into_iter()
+        .map(|(host, paths)| IngressRule {
+            host: Some(host.to_owned()),
+            http: Some(HTTPIngressRuleValue { paths }),
+        })
+        .collect();
+
+    let tls = match certificate::issuer_ref(fs) {
+        // The certificate requested by the operator covers all endpoints.
+        Some(_) => Some(vec![IngressTLS {
+            hosts: Some(certificate::dns_names(fs)),
+            secret_name: Some(certificate::secret_name(fs)),
+        }]),
+        None => fs
+            .ingress
+            .as_ref()
+            .and_then(|ingress| ingress.tls.as_ref())
+            .map(|tls| {
+                tls.iter()
+                    .map(|tls| IngressTLS {
+                        hosts: Some(vec![tls.host.to_owned()]),
+                        secret_name: Some(tls.secret_name.to_owned()),
+                    })
+                    .collect()
+            }),
+    };
+    let mut annotations = dns::annotations(fs, dns::Target::Ingress);
+    if let Some(cluster_issuer) = cluster_issuer(fs) {
+        annotations.insert(CLUSTER_ISSUER_ANNOTATION.to_string(), cluster_issuer);
+    }
+
+    Ingress {
+        metadata: ObjectMeta {
+            annotations: super::annotations(fox_svc, Some(annotations)),
+            labels: Some(super::labels(fox_svc)),
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(IngressSpec {
+            ingress_class_name: class_name(fs),
+            rules: Some(rules),
+            tls,
+            ..IngressSpec::default()
+        }),
+        ..Ingress::default()
+    }
+}
+
+/// IngressClass of the Ingress of a `FoxService`, the one configured for the operator unless the
+/// service names one.
+fn class_name(fs: &FoxServiceSpec) -> Option<String> {
+    fs.ingress
+        .as_ref()
+        .and_then(|ingress| ingress.class_name.clone())
+        .or_else(|| std::env::var(DEFAULT_CLASS_NAME_VAR).ok())
+}
+
+/// cert-manager ClusterIssuer issuing the TLS certificates of the Ingress of a `FoxService`, if any.
+fn cluster_issuer(fs: &FoxServiceSpec) -> Option<String> {
+    fs.ingress
+        .as_ref()
+        .and_then(|ingress| ingress.cluster_issuer.clone())
+}
+
+/// Fetches the ingress with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the Ingress with
+/// - `name` - Name of the ingress to fetch
+/// - `namespace` - Namespace the ingress resides in
+///
+/// Returns `Ok(None)` if there is no such ingress.
+pub async fn get_ingress(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<Ingress>, Error> {
+    let api: Api<Ingress> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(ingress) => Ok(Some(ingress)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Creates the Ingress of a `FoxService` if it has HTTP ingress points using TCP and the ingress
+/// doesn't exist yet, and patches an existing ingress in place if it drifted from the
+/// specification. The ingress is deleted once there are no such HTTP ingress points anymore.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the ingress with.
+/// - `fox_svc` - The `FoxService` resource owning the ingress
+/// - `namespace` - Namespace the ingress resides in.
+///
+/// Returns the ingress if it was created.
+pub async fn apply_ingress(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<Ingress>, Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let routed = is_routed(fs);
+    let api: Api<Ingress> = Api::namespaced(client.clone(), namespace);
+    match get_ingress(client.clone(), &fs.name, namespace).await? {
+        None if routed => {
+            let ingress = build_ingress(fox_svc, namespace);
+            Ok(Some(api.create(&PostParams::default(), &ingress).await?))
+        }
+        None => Ok(None),
+        Some(live) if routed => {
+            let desired = build_ingress(fox_svc, namespace);
+            if needs_update(&desired, &live) || metadata_differs(fox_svc, &live.metadata) {
+                let mut annotations = dns::patch_annotations(&desired.metadata.annotations);
+                annotations[CLUSTER_ISSUER_ANNOTATION] = json!(cluster_issuer(fs));
+                let spec = desired.spec.unwrap_or_default();
+                // A `null` strips the TLS section and the annotations once no longer configured.
+                let mut patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": annotations
+                    },
+                    "spec": {
+                        "rules": spec.rules,
+                        "tls": spec.tls
+                    }
+                });
+                // Without a class, the live one is kept, it may have been set by the cluster.
+                if let Some(class_name) = spec.ingress_class_name {
+                    patch["spec"]["ingressClassName"] = json!(class_name);
+                }
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+            Ok(None)
+        }
+        // An ingress of the same name not owned by the `FoxService` is left alone
+        Some(live) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_ingress(client, &fs.name, namespace).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Decides whether a live ingress has drifted from the desired state. A class set by the cluster
+/// for an ingress without one is tolerated.
+///
+/// # Arguments
+/// - `desired` - Ingress as built by `build_ingress`
+/// - `live` - Ingress as currently present in the cluster
+pub fn needs_update(desired: &Ingress, live: &Ingress) -> bool {
+    let cluster_issuer = |ingress: &Ingress| {
+        ingress
+            .metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(CLUSTER_ISSUER_ANNOTATION).cloned())
+    };
+    if cluster_issuer(desired) != cluster_issuer(live)
+        || dns::differs(&desired.metadata, &live.metadata)
+    {
+        return true;
+    }
+    let desired = desired.spec.clone().unwrap_or_default();
+    let live = live.spec.clone().unwrap_or_default();
+    desired.rules != live.rules
+        || desired.tls != live.tls
+        || (desired.ingress_class_name.is_some()
+            && desired.ingress_class_name != live.ingress_class_name)
+}
+
+/// IP addresses and hostnames assigned to an ingress by the ingress controller, empty until the
+/// controller picked the ingress up.
+///
+/// # Arguments
+/// - `ingress` - Ingress as currently present in the cluster
+pub fn addresses(ingress: &Ingress) -> Vec<String> {
+    ingress
+        .status
+        .as_ref()
+        .and_then(|status| status.load_balancer.as_ref())
+        .and_then(|load_balancer| load_balancer.ingress.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|ingress| ingress.ip.clone().or_else(|| ingress.hostname.clone()))
+        .collect()
+}
+
+/// Deletes an existing ingress.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the Ingress with
+/// - `name` - Name of the ingress to delete
+/// - `namespace` - Namespace the existing ingress resides in
+///
+/// Note: An ingress that doesn't exist is considered deleted.
+pub async fn delete_ingress(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<Ingress> = Api::namespaced(client, namespace);
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,478 @@
+use fox_k8s_crds::fox_service::{validation, FoxService, FoxServicePropagation, FoxServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use kube::api::{DeleteParams, PatchParams, PropagationPolicy};
+use kube::error::ErrorResponse;
+use kube::{Client, Error, Resource};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub mod certificate;
+pub mod config_map;
+pub mod cron_job;
+pub mod daemon_set;
+pub mod deployment;
+pub mod dns;
+pub mod hook;
+pub mod horizontal_pod_autoscaler;
+pub mod http_route;
+pub mod ingress;
+pub mod network_policy;
+pub mod persistent_volume_claim;
+pub mod pod_disruption_budget;
+pub mod service;
+pub mod service_account;
+pub mod service_monitor;
+pub mod stateful_set;
+pub mod vertical_pod_autoscaler;
+pub mod workload;
+
+/// Field manager the operator applies subresources and the `FoxService` status with. Fields
+/// applied before but left out of the desired object are removed by the API server.
+pub const FIELD_MANAGER: &str = "fox-operator";
+
+/// Annotation pausing the reconciliation of a `FoxService` when set to `true`, e.g., while its
+/// workload is patched by hand during an incident. Deletion is handled regardless.
+pub const PAUSED_ANNOTATION: &str = "fox.cbopt.com/paused";
+
+/// Checks whether the reconciliation of a `FoxService` is paused by `PAUSED_ANNOTATION`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource being reconciled
+pub fn is_paused(fox_svc: &FoxService) -> bool {
+    fox_svc
+        .meta()
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(PAUSED_ANNOTATION))
+        .is_some_and(|paused| paused == "true")
+}
+
+/// Annotation overriding the interval a `FoxService` is checked on again after a successful
+/// reconciliation, in seconds, e.g., `60` for a rarely changing service in a large cluster.
+pub const REQUEUE_INTERVAL_ANNOTATION: &str = "fox.cbopt.com/requeue-interval";
+
+/// Reads the interval a `FoxService` is checked on again after a successful reconciliation from
+/// `REQUEUE_INTERVAL_ANNOTATION`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource being reconciled
+///
+/// Returns `Ok(None)` if the annotation isn't set, or a message if it isn't a positive number of
+/// seconds.
+pub fn requeue_interval(fox_svc: &FoxService) -> Result<Option<Duration>, String> {
+    let interval = fox_svc
+        .meta()
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(REQUEUE_INTERVAL_ANNOTATION));
+    match interval.map(|seconds| seconds.trim().parse::<u64>()) {
+        None => Ok(None),
+        Some(Ok(seconds)) if seconds > 0 => Ok(Some(Duration::from_secs(seconds))),
+        Some(_) => Err(format!(
+            "{} must be a positive number of seconds, not {}",
+            REQUEUE_INTERVAL_ANNOTATION,
+            interval.map_or("", String::as_str)
+        )),
+    }
+}
+
+/// Labels identifying the pods of a `FoxService`. Applied to the pod template of the workload and
+/// used as the selector of both the workload and the service, so they are always derived from
+/// the same values.
+///
+/// As the labels are derived from the name of the service, renaming it results in a new set of
+/// labels. Pods created under the old name are not selected anymore.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the pods belong to
+pub fn selector_labels(fox_svc: &FoxService) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    labels.insert(
+        "app.kubernetes.io/name".to_string(),
+        fox_svc.spec.name.to_owned(),
+    );
+    labels.insert(
+        "app.kubernetes.io/instance".to_string(),
+        fox_svc.meta().name.clone().unwrap_or_default(),
+    );
+    labels
+}
+
+/// Standard labels of a `FoxService` object, set by the mutating admission webhook: the selector
+/// labels of its pods and `app.kubernetes.io/managed-by`. The instance label is left out while the
+/// name of the object is still to be generated.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource to label
+pub fn standard_labels(fox_svc: &FoxService) -> BTreeMap<String, String> {
+    let mut labels = selector_labels(fox_svc);
+    labels.retain(|_, value| !value.is_empty());
+    labels.insert(
+        "app.kubernetes.io/managed-by".to_string(),
+        "fox-operator".to_string(),
+    );
+    labels
+}
+
+/// Labels of the subresources of a `FoxService`: the labels propagated from the `FoxService`
+/// object and the selector labels, which win on conflict.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the subresources
+pub fn labels(fox_svc: &FoxService) -> BTreeMap<String, String> {
+    let mut labels = propagated(fox_svc, &fox_svc.meta().labels, |propagate| {
+        propagate.labels
+    });
+    labels.extend(selector_labels(fox_svc));
+    labels
+}
+
+/// Annotations of a subresource of a `FoxService`: the annotations propagated from the
+/// `FoxService` object and the ones set by the operator, which win on conflict.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the subresource
+/// - `annotations` - Annotations set by the operator, if any
+///
+/// Returns `None` if there are no annotations at all.
+pub fn annotations(
+    fox_svc: &FoxService,
+    annotations: Option<BTreeMap<String, String>>,
+) -> Option<BTreeMap<String, String>> {
+    let mut propagated = propagated(fox_svc, &fox_svc.meta().annotations, |propagate| {
+        propagate.annotations
+    });
+    propagated.extend(annotations.into_iter().flatten());
+    Some(propagated).filter(|annotations| !annotations.is_empty())
+}
+
+/// Checks whether a live subresource lacks any of the labels of `labels` or the propagated
+/// annotations, or has a different value for one of them. Used to bring the metadata of
+/// subresources up-to-date with the `FoxService` object, which doesn't change its generation.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the subresource
+/// - `live` - Metadata of the subresource as currently present in the cluster
+pub fn metadata_differs(fox_svc: &FoxService, live: &ObjectMeta) -> bool {
+    !contains_all(&live.labels, &Some(labels(fox_svc)))
+        || !contains_all(&live.annotations, &annotations(fox_svc, None))
+}
+
+/// Labels or annotations of the `FoxService` object to copy onto its subresources, as configured
+/// by `propagate`. Keys in the domains of kubectl and of the operator are never copied.
+fn propagated(
+    fox_svc: &FoxService,
+    map: &Option<BTreeMap<String, String>>,
+    enabled: impl Fn(&FoxServicePropagation) -> Option<bool>,
+) -> BTreeMap<String, String> {
+    let propagate = match &fox_svc.spec.propagate {
+        Some(propagate) if enabled(propagate).unwrap_or(true) => propagate,
+        _ => return BTreeMap::new(),
+    };
+    let allowed = |key: &str| match &propagate.prefixes {
+        Some(prefixes) => prefixes
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str())),
+        None => true,
+    };
+    map.iter()
+        .flatten()
+        .filter(|(key, _)| !key.starts_with("kubectl.kubernetes.io/"))
+        .filter(|(key, _)| !validation::is_reserved(key) && allowed(key))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// The selector labels of a `FoxService` as a serialized label selector, e.g.,
+/// `app.kubernetes.io/instance=foo,app.kubernetes.io/name=foo`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the pods belong to
+pub fn label_selector(fox_svc: &FoxService) -> String {
+    selector_labels(fox_svc)
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Labels of the pods of a `FoxService` run to completion by Jobs: the propagated labels, the
+/// additional pod labels of the specification, the instance label and a component label telling
+/// them apart. They lack the
+/// name label, the pods would be selected by the workload, the service and the pod disruption
+/// budget otherwise.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the pods belong to
+/// - `component` - What the pods run, e.g., `batch`
+pub fn job_labels(fox_svc: &FoxService, component: &str) -> BTreeMap<String, String> {
+    let mut labels = propagated(fox_svc, &fox_svc.meta().labels, |propagate| {
+        propagate.labels
+    });
+    labels.extend(fox_svc.spec.pod_labels.clone().unwrap_or_default());
+    labels.remove("app.kubernetes.io/name");
+    labels.insert(
+        "app.kubernetes.io/instance".to_string(),
+        fox_svc.meta().name.clone().unwrap_or_default(),
+    );
+    labels.insert(
+        "app.kubernetes.io/component".to_string(),
+        component.to_string(),
+    );
+    labels
+}
+
+/// Builds a reference to the `FoxService` controlling a subresource. Kubernetes deletes the
+/// subresource once the `FoxService` is gone, even if the operator failed to do so.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the subresource
+pub fn owner_reference(fox_svc: &FoxService) -> OwnerReference {
+    OwnerReference {
+        api_version: FoxService::api_version(&()).to_string(),
+        kind: FoxService::kind(&()).to_string(),
+        name: fox_svc.meta().name.clone().unwrap_or_default(),
+        uid: fox_svc.meta().uid.clone().unwrap_or_default(),
+        controller: Some(true),
+        block_owner_deletion: Some(true),
+    }
+}
+
+/// Checks whether a subresource found in the cluster belongs to a `FoxService`, so it may be
+/// adopted when it is to be created. It belongs to the `FoxService` if it
+/// is owned by it, or if it has no controller but carries its selector labels, as subresources
+/// created by earlier versions of the operator do.
+///
+/// # Arguments
+/// - `metadata` - Metadata of the subresource present in the cluster
+/// - `fox_svc` - The `FoxService` resource to create the subresource for
+pub fn is_managed(metadata: &ObjectMeta, fox_svc: &FoxService) -> bool {
+    match controller_uid(metadata) {
+        Some(uid) => Some(uid) == fox_svc.meta().uid.as_deref(),
+        None => contains_all(&metadata.labels, &Some(selector_labels(fox_svc))),
+    }
+}
+
+/// UID of the controller of an object, i.e., of its owner reference with `controller: true`.
+/// Owner references added by others, e.g., by backup tools, are not taken into account.
+///
+/// # Arguments
+/// - `metadata` - Metadata of the object
+pub fn controller_uid(metadata: &ObjectMeta) -> Option<&str> {
+    let mut owners = metadata.owner_references.iter().flatten();
+    owners
+        .find(|owner| owner.controller == Some(true))
+        .map(|controller| controller.uid.as_str())
+}
+
+/// Checks whether creating a subresource failed because an object of the same name exists.
+///
+/// # Arguments
+/// - `error` - Error the creation failed with
+pub fn already_exists(error: &Error) -> bool {
+    matches!(error, Error::Api(response) if response.code == 409 && response.reason == "AlreadyExists")
+}
+
+/// Parameters deleting the dependents of a subresource, e.g., the pods of a workload, before the
+/// subresource itself.
+pub fn foreground_deletion() -> DeleteParams {
+    DeleteParams {
+        propagation_policy: Some(PropagationPolicy::Foreground),
+        ..DeleteParams::default()
+    }
+}
+
+/// Parameters applying the desired state of an object server-side as `FIELD_MANAGER`. Fields
+/// managed by others, e.g., replicas set by hand, are taken over on conflict.
+pub fn apply_params() -> PatchParams {
+    PatchParams::apply(FIELD_MANAGER).force()
+}
+
+/// Error reported for a subresource to be created that exists already but doesn't belong to the
+/// `FoxService`, as the API server reports it on creating an object that exists.
+///
+/// # Arguments
+/// - `resource` - Resource of the subresource, e.g., `deployments.apps`
+/// - `name` - Name of the subresource
+pub fn already_exists_error(resource: &str, name: &str) -> Error {
+    Error::Api(ErrorResponse {
+        status: "Failure".to_string(),
+        message: format!("{} \"{}\" already exists", resource, name),
+        reason: "AlreadyExists".to_string(),
+        code: 409,
+    })
+}
+
+/// Checks whether all entries of `expected` are present in `map` with the same values. Used to
+/// compare labels and annotations, which others may add to subresources as well.
+///
+/// # Arguments
+/// - `map` - Labels or annotations of a live subresource
+/// - `expected` - Labels or annotations set by the operator
+pub fn contains_all(
+    map: &Option<BTreeMap<String, String>>,
+    expected: &Option<BTreeMap<String, String>>,
+) -> bool {
+    expected
+        .iter()
+        .flatten()
+        .all(|(key, value)| map.as_ref().and_then(|map| map.get(key)) == Some(value))
+}
+
+/// Checks whether all fields of `expected` are present in `value` with the same values. Lists
+/// have to be of the same length, their items are compared pairwise. Used to compare subresources
+/// managed as dynamic objects, which the API server fills in defaults for.
+///
+/// # Arguments
+/// - `expected` - Desired state of (a part of) a subresource
+/// - `value` - Live state of the same part
+pub fn is_subset(expected: &Value, value: &Value) -> bool {
+    match (expected, value) {
+        (Value::Object(expected), Value::Object(value)) => {
+            expected.iter().all(|(key, expected)| {
+                value
+                    .get(key)
+                    .is_some_and(|value| is_subset(expected, value))
+            })
+        }
+        (Value::Array(expected), Value::Array(value)) => {
+            expected.len() == value.len()
+                && expected
+                    .iter()
+                    .zip(value)
+                    .all(|(expected, value)| is_subset(expected, value))
+        }
+        (expected, value) => expected == value,
+    }
+}
+
+/// Creates or updates the subresources accompanying the workload of a `FoxService`: the ConfigMap
+/// of the config files, the persistent volume claims and the service account it depends on, the
+/// headless service governing a StatefulSet, the network policy, the horizontal pod autoscaler,
+/// the pod disruption budget and the CronJob running the batch containers. They are not part of
+/// the workload, so they are applied on every reconciliation instead of relying on the drift
+/// detection of the workload.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the subresources with.
+/// - `fox_svc` - The `FoxService` resource owning the subresources
+/// - `namespace` - Namespace the subresources reside in.
+pub async fn apply_dependencies(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    config_map::apply_config_map(client.clone(), fox_svc, namespace).await?;
+    persistent_volume_claim::apply_persistent_volume_claims(client.clone(), fox_svc, namespace)
+        .await?;
+    service_account::apply_service_account(client.clone(), fox_svc, namespace).await?;
+    stateful_set::apply_governing_service(client.clone(), fox_svc, namespace).await?;
+    network_policy::apply_network_policy(client.clone(), fox_svc, namespace).await?;
+    horizontal_pod_autoscaler::apply_horizontal_pod_autoscaler(client.clone(), fox_svc, namespace)
+        .await?;
+    pod_disruption_budget::apply_pod_disruption_budget(client.clone(), fox_svc, namespace).await?;
+    cron_job::apply_cron_job(client, fox_svc, namespace).await
+}
+
+/// Creates the workload of a `FoxService` along with the subresources it depends on: the
+/// persistent volume claims and the service account. The service, the ingress and the HTTP route
+/// exposing the workload are applied separately, see `service::apply_service`,
+/// `ingress::apply_ingress` and `http_route::apply_http_route`.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to create the subresources with.
+/// - `fox_svc` - The `FoxService` resource owning the subresources
+/// - `namespace` - Namespace to create the subresources in.
+///
+/// Returns the created workload, if there are long-running containers to run.
+///
+/// Note: An existing deployment of the same name is adopted if it belongs to the `FoxService`, see
+/// `is_managed`. Returns an `Error` if it doesn't, or if a workload of another kind exists.
+pub async fn deploy(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<workload::Workload>, Error> {
+    apply_dependencies(client.clone(), fox_svc, namespace).await?;
+    if !workload::is_required(&fox_svc.spec) {
+        workload::delete_previous_workloads(client, fox_svc, namespace).await?;
+        return Ok(None);
+    }
+    workload::create_workload(client, fox_svc, namespace)
+        .await
+        .map(Some)
+}
+
+/// Deletes all subresources created by `deploy`.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to delete the subresources with.
+/// - `fs` - Fox service specification
+/// - `namespace` - Namespace the subresources reside in.
+pub async fn delete(client: Client, fs: &FoxServiceSpec, namespace: &str) -> Result<(), Error> {
+    workload::delete_workloads(client.clone(), fs, namespace).await?;
+    // The service may not exist even so, e.g., if the resource is deleted before the first
+    // reconciliation. One left behind after removing the ingress points is garbage collected.
+    if service::is_exposed(fs) {
+        service::delete_service(client.clone(), &fs.name, namespace).await?;
+    }
+    if ingress::is_routed(fs) {
+        ingress::delete_ingress(client.clone(), &fs.name, namespace).await?;
+    }
+    if certificate::issuer_ref(fs).is_some() {
+        certificate::delete_certificate(client.clone(), &fs.name, namespace).await?;
+    }
+    if http_route::is_routed(fs) {
+        http_route::delete_http_route(client.clone(), &fs.name, namespace).await?;
+    }
+    if fs.network_policy.is_some() {
+        network_policy::delete_network_policy(client.clone(), &fs.name, namespace).await?;
+    }
+    if fs.autoscaling.is_some() {
+        horizontal_pod_autoscaler::delete_horizontal_pod_autoscaler(
+            client.clone(),
+            &fs.name,
+            namespace,
+        )
+        .await?;
+    }
+    if fs.disruption_budget.is_some() {
+        pod_disruption_budget::delete_pod_disruption_budget(client.clone(), &fs.name, namespace)
+            .await?;
+    }
+    if fs.cron.is_some() {
+        cron_job::delete_cron_job(client.clone(), &fs.name, namespace).await?;
+    }
+    if fs.metrics.is_some() {
+        service_monitor::delete_service_monitor(client.clone(), &fs.name, namespace).await?;
+    }
+    if fs.vertical_autoscaling.is_some() {
+        vertical_pod_autoscaler::delete_vertical_pod_autoscaler(
+            client.clone(),
+            &fs.name,
+            namespace,
+        )
+        .await?;
+    }
+    if fs.config_files.is_some() {
+        config_map::delete_config_map(client.clone(), fs, namespace).await?;
+    }
+    persistent_volume_claim::delete_persistent_volume_claims(client.clone(), fs, namespace).await?;
+    service_account::delete_service_account(client, fs, namespace).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_params_force_the_field_manager_of_the_operator() {
+        let params = apply_params();
+
+        assert_eq!(params.field_manager.as_deref(), Some("fox-operator"));
+        assert!(params.force);
+        assert!(!params.dry_run);
+    }
+}
@@ -0,0 +1,199 @@
+use super::{metadata_differs, owner_reference, selector_labels};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::networking::v1::{
+    NetworkPolicy, NetworkPolicyIngressRule, NetworkPolicyPeer, NetworkPolicyPort,
+    NetworkPolicySpec,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Ports of the pods of a `FoxService` as `(port, protocol)`: the ports declared by the containers
+/// and the ports targeted by the HTTP ingress points, which containers don't have to declare.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+fn allowed_ports(fs: &FoxServiceSpec) -> BTreeSet<(i32, &'static str)> {
+    let declared = fs
+        .containers
+        .iter()
+        .flat_map(|container| container.ports.iter().flatten())
+        .map(|port| {
+            let protocol = port.protocol.unwrap_or(PortProtocol::Tcp);
+            (port.container_port, protocol.as_str())
+        });
+    let targeted = fs.http_ingress.iter().flatten().filter_map(|ingress| {
+        let port = match (&ingress.target_port, fs.ingress_container_port(ingress)) {
+            (_, Some(port)) => port.container_port,
+            (Some(IntOrString::Int(target_port)), None) => *target_port,
+            (Some(IntOrString::String(_)), None) => return None,
+            (None, None) => ingress.port,
+        };
+        Some((port, fs.ingress_protocol(ingress).as_str()))
+    });
+    declared.chain(targeted).collect()
+}
+
+/// Builds the desired state of the NetworkPolicy of a `FoxService`, allowing traffic to the
+/// ports of its pods only. Without any ports, all incoming traffic is denied.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the policy
+/// - `policy` - NetworkPolicy configuration of the service
+/// - `namespace` - Namespace the NetworkPolicy is going to reside in.
+pub fn build_network_policy(
+    fox_svc: &FoxService,
+    policy: &FoxServiceNetworkPolicy,
+    namespace: &str,
+) -> NetworkPolicy {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let ports: Vec<NetworkPolicyPort> = allowed_ports(fs)
+        .into_iter()
+        .map(|(port, protocol)| NetworkPolicyPort {
+            port: Some(IntOrString::Int(port)),
+            protocol: Some(protocol.to_string()),
+        })
+        .collect();
+    let from = policy.from.as_ref().map(|from| {
+        from.iter()
+            .map(|peer| NetworkPolicyPeer {
+                ip_block: None,
+                namespace_selector: peer.namespace_labels.as_ref().map(label_selector),
+                pod_selector: peer.pod_labels.as_ref().map(label_selector),
+            })
+            .collect()
+    });
+    // A rule without ports would allow all of them
+    let ingress = if ports.is_empty() {
+        vec![]
+    } else {
+        vec![NetworkPolicyIngressRule {
+            from,
+            ports: Some(ports),
+        }]
+    };
+
+    NetworkPolicy {
+        metadata: ObjectMeta {
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(NetworkPolicySpec {
+            egress: None,
+            ingress: Some(ingress),
+            pod_selector: LabelSelector {
+                match_expressions: None,
+                match_labels: Some(selector_labels(fox_svc)),
+            },
+            policy_types: Some(vec!["Ingress".to_string()]),
+        }),
+    }
+}
+
+/// Builds a selector matching the given labels. Empty labels result in an empty selector, which
+/// matches everything and is returned by the API server without labels.
+fn label_selector(labels: &BTreeMap<String, String>) -> LabelSelector {
+    LabelSelector {
+        match_expressions: None,
+        match_labels: Some(labels.clone()).filter(|labels| !labels.is_empty()),
+    }
+}
+
+/// Decides whether a live NetworkPolicy has drifted from the desired state. An empty list of rules
+/// is omitted by the API server.
+///
+/// # Arguments
+/// - `desired` - NetworkPolicy as built by `build_network_policy`
+/// - `live` - NetworkPolicy as currently present in the cluster
+pub fn needs_update(desired: &NetworkPolicy, live: &NetworkPolicy) -> bool {
+    let desired = desired.spec.clone().unwrap_or_default();
+    let live = live.spec.clone().unwrap_or_default();
+    desired.pod_selector != live.pod_selector
+        || desired.policy_types != live.policy_types
+        || desired.ingress.unwrap_or_default() != live.ingress.unwrap_or_default()
+}
+
+/// Creates the NetworkPolicy of a `FoxService` if it is enabled and doesn't exist yet, and patches
+/// an existing policy if the ports or the allowed sources changed. The policy is deleted once it
+/// is disabled.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the NetworkPolicy with.
+/// - `fox_svc` - The `FoxService` resource owning the policy
+/// - `namespace` - Namespace the NetworkPolicy resides in.
+pub async fn apply_network_policy(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<NetworkPolicy> = Api::namespaced(client.clone(), namespace);
+    let live = match api.get(&fs.name).await {
+        Ok(live) => Some(live),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    match (&fs.network_policy, live) {
+        (Some(policy), None) => {
+            let network_policy = build_network_policy(fox_svc, policy, namespace);
+            api.create(&PostParams::default(), &network_policy).await?;
+        }
+        (Some(policy), Some(live)) => {
+            let desired = build_network_policy(fox_svc, policy, namespace);
+            if needs_update(&desired, &live) || metadata_differs(fox_svc, &live.metadata) {
+                // Lists are replaced as a whole by a merge patch, which keeps the policy exact.
+                let patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": desired.metadata.annotations
+                    },
+                    "spec": desired.spec
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+        }
+        (None, None) => {}
+        // A policy of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_network_policy(client, &fs.name, namespace).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes an existing NetworkPolicy.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the NetworkPolicy with
+/// - `name` - Name of the NetworkPolicy to delete
+/// - `namespace` - Namespace the existing NetworkPolicy resides in
+///
+/// Note: A NetworkPolicy that doesn't exist is considered deleted.
+pub async fn delete_network_policy(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<NetworkPolicy> = Api::namespaced(client, namespace);
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
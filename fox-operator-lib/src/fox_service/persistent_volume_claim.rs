@@ -0,0 +1,205 @@
+use super::{metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::core::v1::{
+    PersistentVolumeClaim, PersistentVolumeClaimSpec, ResourceRequirements,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::{DeleteParams, ListParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Name of the PersistentVolumeClaim backing a volume of a `FoxService`.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+/// - `volume` - The volume backed by the claim
+pub fn claim_name(fs: &FoxServiceSpec, volume: &FoxServiceVolume) -> String {
+    format!("{}-{}", fs.name, volume.name)
+}
+
+/// Builds the desired state of the PersistentVolumeClaim backing a volume of a `FoxService`.
+/// Unless the claim is retained, it is owned by the `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the volume belongs to
+/// - `volume` - The volume backed by the claim
+/// - `persistence` - Persistence configuration of the volume
+/// - `namespace` - Namespace the PersistentVolumeClaim is going to reside in.
+pub fn build_persistent_volume_claim(
+    fox_svc: &FoxService,
+    volume: &FoxServiceVolume,
+    persistence: &PersistenceVolume,
+    namespace: &str,
+) -> PersistentVolumeClaim {
+    // An owned claim would be garbage collected along with the `FoxService`, retained or not.
+    let owner_references = match persistence.retain {
+        Some(true) => None,
+        _ => Some(vec![owner_reference(fox_svc)]),
+    };
+    PersistentVolumeClaim {
+        metadata: ObjectMeta {
+            name: Some(claim_name(&fox_svc.spec, volume)),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references,
+            ..ObjectMeta::default()
+        },
+        spec: Some(build_claim_spec(persistence)),
+        ..PersistentVolumeClaim::default()
+    }
+}
+
+/// Translates the persistence configuration of a volume into the specification of a claim. Shared
+/// by the standalone claims and the volume claim templates of a StatefulSet.
+///
+/// # Arguments
+/// - `persistence` - Persistence configuration of the volume
+pub fn build_claim_spec(persistence: &PersistenceVolume) -> PersistentVolumeClaimSpec {
+    let access_modes = match &persistence.access_modes {
+        Some(access_modes) => access_modes
+            .iter()
+            .map(|mode| format!("{:?}", mode))
+            .collect(),
+        None => vec![format!("{:?}", AccessMode::ReadWriteOnce)],
+    };
+    let mut requests = BTreeMap::new();
+    requests.insert("storage".to_string(), Quantity(persistence.size.to_owned()));
+
+    PersistentVolumeClaimSpec {
+        access_modes: Some(access_modes),
+        storage_class_name: persistence.storage_class.clone(),
+        resources: Some(ResourceRequirements {
+            requests: Some(requests),
+            limits: None,
+        }),
+        ..PersistentVolumeClaimSpec::default()
+    }
+}
+
+/// Creates the PersistentVolumeClaims of all persistent volumes of a `FoxService` which don't exist
+/// yet, and resizes the existing ones whose requested size changed. The labels and annotations
+/// propagated from the `FoxService` are kept up-to-date.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the claims with.
+/// - `fox_svc` - The `FoxService` resource the volumes belong to
+/// - `namespace` - Namespace the claims reside in.
+///
+/// Note: Only the size and the metadata of an existing claim are updated, the storage class and
+/// access modes of a claim can't be changed. A `statefulset` workload creates the claims of its pods on its own.
+pub async fn apply_persistent_volume_claims(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    if super::stateful_set::is_stateful(&fox_svc.spec) {
+        return Ok(());
+    }
+    let api: Api<PersistentVolumeClaim> = Api::namespaced(client, namespace);
+    for volume in fox_svc.spec.volumes.iter().flatten() {
+        let persistence = match &volume.persistence {
+            Some(persistence) => persistence,
+            None => continue,
+        };
+        let name = claim_name(&fox_svc.spec, volume);
+        let existing = match api.get(&name).await {
+            Ok(claim) => claim,
+            Err(Error::Api(response)) if response.code == 404 => {
+                let claim = build_persistent_volume_claim(fox_svc, volume, persistence, namespace);
+                api.create(&PostParams::default(), &claim).await?;
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+
+        let size = existing
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.resources.as_ref())
+            .and_then(|resources| resources.requests.as_ref())
+            .and_then(|requests| requests.get("storage"));
+        if size.map(|size| size.0.as_str()) != Some(persistence.size.as_str()) {
+            let resize = json!({
+                "spec": {
+                    "resources": {
+                        "requests": {
+                            "storage": persistence.size
+                        }
+                    }
+                }
+            });
+            api.patch(&name, &PatchParams::default(), &Patch::Merge(&resize))
+                .await?;
+        }
+        if metadata_differs(fox_svc, &existing.metadata) {
+            let claim = build_persistent_volume_claim(fox_svc, volume, persistence, namespace);
+            let relabel = json!({
+                "metadata": {
+                    "labels": claim.metadata.labels,
+                    "annotations": claim.metadata.annotations
+                }
+            });
+            api.patch(&name, &PatchParams::default(), &Patch::Merge(&relabel))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the PersistentVolumeClaims of all persistent volumes of a `FoxService`, except for the
+/// retained ones. Claims which don't exist are skipped. Claims created from the volume claim
+/// templates of a StatefulSet are not garbage collected by Kubernetes, they are deleted as well.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to delete the claims with.
+/// - `fs` - Fox service specification
+/// - `namespace` - Namespace the claims reside in.
+pub async fn delete_persistent_volume_claims(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<PersistentVolumeClaim> = Api::namespaced(client, namespace);
+    for volume in fs.volumes.iter().flatten() {
+        match &volume.persistence {
+            Some(persistence) if persistence.retain != Some(true) => {}
+            _ => continue,
+        }
+        match api
+            .delete(&claim_name(fs, volume), &DeleteParams::default())
+            .await
+        {
+            Ok(_) => {}
+            Err(Error::Api(response)) if response.code == 404 => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    // Claims of a StatefulSet are named `<template>-<statefulset>-<ordinal>`.
+    let params = ListParams::default().labels(&format!("app.kubernetes.io/name={}", fs.name));
+    let claims = api.list(&params).await?;
+    for volume in fs.volumes.iter().flatten() {
+        match &volume.persistence {
+            Some(persistence) if persistence.retain != Some(true) => {}
+            _ => continue,
+        }
+        let prefix = format!("{}-{}-", volume.name, fs.name);
+        let names = claims
+            .iter()
+            .filter_map(|claim| claim.metadata.name.as_ref())
+            .filter(|name| {
+                name.strip_prefix(&prefix)
+                    .is_some_and(|ordinal| ordinal.parse::<u32>().is_ok())
+            });
+        for name in names {
+            match api.delete(name, &DeleteParams::default()).await {
+                Ok(_) => {}
+                Err(Error::Api(response)) if response.code == 404 => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    Ok(())
+}
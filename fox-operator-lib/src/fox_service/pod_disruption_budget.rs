@@ -0,0 +1,133 @@
+use super::{metadata_differs, owner_reference, selector_labels};
+use crate::validation;
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::policy::v1beta1::{PodDisruptionBudget, PodDisruptionBudgetSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+
+/// Builds the desired state of the PodDisruptionBudget of a `FoxService`, selecting its pods.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the budget
+/// - `budget` - Disruption budget configuration of the service
+/// - `namespace` - Namespace the PodDisruptionBudget is going to reside in.
+pub fn build_pod_disruption_budget(
+    fox_svc: &FoxService,
+    budget: &FoxServiceDisruptionBudget,
+    namespace: &str,
+) -> PodDisruptionBudget {
+    PodDisruptionBudget {
+        metadata: ObjectMeta {
+            name: Some(fox_svc.spec.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(PodDisruptionBudgetSpec {
+            max_unavailable: budget.max_unavailable.clone(),
+            min_available: budget.min_available.clone(),
+            selector: Some(LabelSelector {
+                match_expressions: None,
+                match_labels: Some(selector_labels(fox_svc)),
+            }),
+        }),
+        ..PodDisruptionBudget::default()
+    }
+}
+
+/// Creates the PodDisruptionBudget of a `FoxService` if one is configured and the budget doesn't
+/// exist yet, and patches an existing budget if it changed. The budget is deleted once it is no
+/// longer configured, or no longer allows any pod to be evicted, see
+/// `validation::blocks_eviction`.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the PodDisruptionBudget with.
+/// - `fox_svc` - The `FoxService` resource owning the budget
+/// - `namespace` - Namespace the PodDisruptionBudget resides in.
+pub async fn apply_pod_disruption_budget(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<PodDisruptionBudget> = Api::namespaced(client.clone(), namespace);
+    let live = match api.get(&fs.name).await {
+        Ok(live) => Some(live),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    let budget = fs
+        .disruption_budget
+        .as_ref()
+        .filter(|_| !validation::blocks_eviction(fs));
+    match (budget, live) {
+        (Some(budget), None) => {
+            let pod_disruption_budget = build_pod_disruption_budget(fox_svc, budget, namespace);
+            api.create(&PostParams::default(), &pod_disruption_budget)
+                .await?;
+        }
+        (Some(budget), Some(live)) => {
+            let desired = build_pod_disruption_budget(fox_svc, budget, namespace);
+            let spec = desired.spec.unwrap_or_default();
+            let outdated = metadata_differs(fox_svc, &live.metadata);
+            let live = live.spec.unwrap_or_default();
+            if spec.min_available != live.min_available
+                || spec.max_unavailable != live.max_unavailable
+                || outdated
+            {
+                // `null` removes the other bound, only one of them may be set.
+                let patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": desired.metadata.annotations
+                    },
+                    "spec": {
+                        "minAvailable": spec.min_available,
+                        "maxUnavailable": spec.max_unavailable
+                    }
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+        }
+        (None, None) => {}
+        // A budget of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_pod_disruption_budget(client, &fs.name, namespace).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes an existing PodDisruptionBudget.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the PodDisruptionBudget with
+/// - `name` - Name of the PodDisruptionBudget to delete
+/// - `namespace` - Namespace the existing PodDisruptionBudget resides in
+///
+/// Note: A PodDisruptionBudget that doesn't exist is considered deleted.
+pub async fn delete_pod_disruption_budget(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<PodDisruptionBudget> = Api::namespaced(client, namespace);
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
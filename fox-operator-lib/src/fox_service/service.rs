@@ -0,0 +1,441 @@
+use super::{already_exists_error, apply_params, contains_all, dns, is_managed};
+use super::{owner_reference, selector_labels};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::core::v1::{
+    ClientIPConfig, Service, ServicePort, ServiceSpec, SessionAffinityConfig,
+};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{DeleteParams, ObjectMeta, Patch};
+use kube::{Api, Client, Error, Resource};
+
+/// Timeout of `ClientIP` session affinity applied by the API server if none is set.
+const DEFAULT_SESSION_AFFINITY_TIMEOUT_SECONDS: i32 = 10800;
+
+/// Builds the desired state of the Service exposing the HTTP ingress points of a `FoxService`.
+/// The service is owned by the `FoxService`, so Kubernetes garbage collects it should the
+/// explicit deletion be skipped.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the service
+/// - `namespace` - Namespace the Service is going to reside in.
+pub fn build_service(fox_svc: &FoxService, namespace: &str) -> Service {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let ports = fs.http_ingress.as_ref().map(|ingress| {
+        ingress
+            .iter()
+            .map(|ingress| {
+                let container_port = fs.ingress_container_port(ingress);
+                // Named ports are targeted by their name, so the container port number may change.
+                let target_port = match (&ingress.target_port, container_port) {
+                    (Some(target_port), _) => target_port.clone(),
+                    (
+                        None,
+                        Some(FoxServicePort {
+                            name: Some(name), ..
+                        }),
+                    ) => IntOrString::String(name.to_owned()),
+                    (None, _) => IntOrString::Int(ingress.port),
+                };
+                ServicePort {
+                    name: fs.ingress_port_name(ingress),
+                    port: ingress.port,
+                    protocol: Some(fs.ingress_protocol(ingress).as_str().to_string()),
+                    target_port: Some(target_port),
+                    ..ServicePort::default()
+                }
+            })
+            .collect()
+    });
+    let session_affinity = fs
+        .service
+        .as_ref()
+        .and_then(|service| service.session_affinity.as_ref());
+    let mut annotations = fs
+        .service
+        .as_ref()
+        .and_then(|service| service.annotations.clone())
+        .unwrap_or_default();
+    annotations.extend(dns::annotations(fs, dns::Target::Service));
+    Service {
+        metadata: ObjectMeta {
+            annotations: super::annotations(fox_svc, Some(annotations)),
+            // Allows to select the service, e.g., by the ServiceMonitor scraping its metrics
+            labels: Some(super::labels(fox_svc)),
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(ServiceSpec {
+            type_: Some(format!("{:?}", service_type(fs))),
+            external_traffic_policy: fs
+                .service
+                .as_ref()
+                .and_then(|service| service.external_traffic_policy)
+                .map(|policy| format!("{:?}", policy)),
+            ports,
+            selector: Some(selector_labels(fox_svc)),
+            session_affinity: session_affinity.map(|affinity| format!("{:?}", affinity.type_)),
+            session_affinity_config: session_affinity
+                .filter(|affinity| affinity.type_ == SessionAffinityType::ClientIP)
+                .and_then(|affinity| affinity.timeout_seconds)
+                .map(|timeout_seconds| SessionAffinityConfig {
+                    client_ip: Some(ClientIPConfig {
+                        timeout_seconds: Some(timeout_seconds),
+                    }),
+                }),
+            ..ServiceSpec::default()
+        }),
+        ..Service::default()
+    }
+}
+
+/// Type of the Service of a `FoxService`, `ClusterIP` unless configured otherwise.
+fn service_type(fs: &FoxServiceSpec) -> ServiceType {
+    fs.service
+        .as_ref()
+        .and_then(|service| service.type_)
+        .unwrap_or(ServiceType::ClusterIP)
+}
+
+/// Creates a new service for the contianers that expose ports
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to create the service with.
+/// - `fox_svc` - The `FoxService` resource owning the service
+/// - `namespace` - Namespace to create the Kubernetes Service in.
+///
+/// Note: A service of the same name created since it was looked up is adopted and brought in line
+/// with the specification if it belongs to the `FoxService`, see `is_managed`. Returns an
+/// `AlreadyExists` error if it doesn't.
+pub async fn create_service(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Service, Error> {
+    let name = &fox_svc.spec.name;
+    match get_service(client.clone(), name, namespace).await? {
+        Some(live) if !is_managed(&live.metadata, fox_svc) => {
+            Err(already_exists_error("services", name))
+        }
+        live => update_service(client, fox_svc, live.as_ref(), namespace).await,
+    }
+}
+
+/// Fetches the service with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the Service with
+/// - `name` - Name of the service to fetch
+/// - `namespace` - Namespace the service resides in
+///
+/// Returns `Ok(None)` if there is no such service.
+pub async fn get_service(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<Service>, Error> {
+    let api: Api<Service> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(service) => Ok(Some(service)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Whether a `FoxService` has HTTP ingress points, and therefore a Service exposing them.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn is_exposed(fs: &FoxServiceSpec) -> bool {
+    fs.http_ingress
+        .iter()
+        .any(|http_ingress| !http_ingress.is_empty())
+}
+
+/// Creates the Service of a `FoxService` if it has HTTP ingress points and the service doesn't
+/// exist yet, and patches an existing service in place if it drifted from the specification.
+/// Recreating the service instead would lose its cluster IP and its cloud load balancer. The
+/// service is deleted once all HTTP ingress points are removed.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the service with.
+/// - `fox_svc` - The `FoxService` resource owning the service
+/// - `namespace` - Namespace the service resides in.
+///
+/// Returns the service if it was created, or an `AlreadyExists` error if a service of the same
+/// name doesn't belong to the `FoxService`, see `is_managed`.
+pub async fn apply_service(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<Service>, Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let exposed = is_exposed(fs);
+    match get_service(client.clone(), &fs.name, namespace).await? {
+        None if exposed => Ok(Some(create_service(client, fox_svc, namespace).await?)),
+        None => Ok(None),
+        Some(live) if exposed && !is_managed(&live.metadata, fox_svc) => {
+            Err(already_exists_error("services", &fs.name))
+        }
+        Some(live) if exposed => {
+            if needs_update(&build_service(fox_svc, namespace), &live) {
+                update_service(client, fox_svc, Some(&live), namespace).await?;
+            }
+            Ok(None)
+        }
+        // A service of the same name not owned by the `FoxService` is left alone
+        Some(live) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_service(client, &fs.name, namespace).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Decides whether a live service has drifted from the desired state. Only the fields set by
+/// `build_service` which can be changed in place are compared. Labels and annotations added by
+/// others, e.g., by cloud controllers, are tolerated.
+///
+/// # Arguments
+/// - `desired` - Service as built by `build_service`
+/// - `live` - Service as currently present in the cluster
+pub fn needs_update(desired: &Service, live: &Service) -> bool {
+    let type_ = |service: &Service| {
+        service
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.type_.clone())
+            .unwrap_or_else(|| format!("{:?}", ServiceType::ClusterIP))
+    };
+    let selector = |service: &Service| service.spec.as_ref().and_then(|spec| spec.selector.clone());
+    type_(desired) != type_(live)
+        || sorted_ports(desired) != sorted_ports(live)
+        || selector(desired) != selector(live)
+        || session_affinity(desired) != session_affinity(live)
+        || external_traffic_policy(desired) != external_traffic_policy(live)
+        || !contains_all(&live.metadata.annotations, &desired.metadata.annotations)
+        || dns::differs(&desired.metadata, &live.metadata)
+        || !contains_all(&live.metadata.labels, &desired.metadata.labels)
+}
+
+/// Ports of a service as `(port, protocol, name, target port)`, ordered by port and protocol.
+/// Node ports are left out, as they are allocated by the API server. A missing protocol and
+/// target port are treated as the defaults applied by the API server, `TCP` and the port itself.
+fn sorted_ports(service: &Service) -> Vec<(i32, String, Option<String>, String)> {
+    let mut ports: Vec<_> = service
+        .spec
+        .iter()
+        .flat_map(|spec| spec.ports.iter().flatten())
+        .map(|port| {
+            let target_port = match &port.target_port {
+                Some(IntOrString::Int(target_port)) => target_port.to_string(),
+                Some(IntOrString::String(target_port)) => target_port.to_owned(),
+                None => port.port.to_string(),
+            };
+            (
+                port.port,
+                port.protocol.clone().unwrap_or_else(|| "TCP".to_string()),
+                port.name.clone(),
+                target_port,
+            )
+        })
+        .collect();
+    ports.sort();
+    ports
+}
+
+/// Session affinity of a service and the timeout of `ClientIP` affinity, with the defaults
+/// applied by the API server.
+fn session_affinity(service: &Service) -> (String, Option<i32>) {
+    let spec = service.spec.clone().unwrap_or_default();
+    let type_ = spec
+        .session_affinity
+        .unwrap_or_else(|| format!("{:?}", SessionAffinityType::None));
+    let timeout_seconds = if type_ == format!("{:?}", SessionAffinityType::ClientIP) {
+        spec.session_affinity_config
+            .and_then(|config| config.client_ip)
+            .and_then(|client_ip| client_ip.timeout_seconds)
+            .or(Some(DEFAULT_SESSION_AFFINITY_TIMEOUT_SECONDS))
+    } else {
+        None
+    };
+    (type_, timeout_seconds)
+}
+
+/// External traffic policy of a service, with the default applied by the API server to services
+/// of type `NodePort` and `LoadBalancer`.
+fn external_traffic_policy(service: &Service) -> Option<String> {
+    let spec = service.spec.clone().unwrap_or_default();
+    match spec.type_ {
+        None => None,
+        Some(type_) if type_ == format!("{:?}", ServiceType::ClusterIP) => None,
+        Some(_) => spec
+            .external_traffic_policy
+            .or_else(|| Some(format!("{:?}", ExternalTrafficPolicy::Cluster))),
+    }
+}
+
+/// Applies the service built from the given Fox service specification server-side, creating it
+/// if it doesn't exist yet. The cluster IP is immutable and left untouched, node ports allocated
+/// to the live service are kept.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to apply the service with.
+/// - `fox_svc` - The `FoxService` resource owning the service
+/// - `live` - Service as currently present in the cluster, if any
+/// - `namespace` - Namespace the service resides in.
+///
+/// Note: The full desired service is applied as `FIELD_MANAGER`, so fields removed from the
+/// specification, e.g., selector labels or external-dns annotations, are removed from the service
+/// as well, while labels and annotations added by others are kept.
+pub async fn update_service(
+    client: Client,
+    fox_svc: &FoxService,
+    live: Option<&Service>,
+    namespace: &str,
+) -> Result<Service, Error> {
+    let mut service = build_service(fox_svc, namespace);
+    let spec = service.spec.get_or_insert_with(Default::default);
+    let live_spec = live.and_then(|live| live.spec.clone()).unwrap_or_default();
+
+    // Node ports allocated by the API server aren't part of the desired service, the ones of
+    // ports that remain are carried over so they don't change. Node ports are rejected for
+    // `ClusterIP` services, though, so they are dropped on changing to that type.
+    if service_type(&fox_svc.spec) != ServiceType::ClusterIP {
+        for port in spec.ports.iter_mut().flatten() {
+            port.node_port = live_spec
+                .ports
+                .iter()
+                .flatten()
+                .find(|live_port| {
+                    live_port.port == port.port && live_port.protocol == port.protocol
+                })
+                .and_then(|live_port| live_port.node_port);
+        }
+    }
+
+    let api: Api<Service> = Api::namespaced(client, namespace);
+    api.patch(&fox_svc.spec.name, &apply_params(), &Patch::Apply(&service))
+        .await
+}
+
+/// Cluster IP address assigned to a service, if any.
+///
+/// # Arguments
+/// - `service` - Service as currently present in the cluster
+pub fn cluster_ip(service: &Service) -> Option<String> {
+    service
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.cluster_ip.clone())
+        .filter(|cluster_ip| !cluster_ip.is_empty() && cluster_ip != "None")
+}
+
+/// IP addresses and hostnames assigned to a service of type `LoadBalancer`, empty until the load
+/// balancer is provisioned.
+///
+/// # Arguments
+/// - `service` - Service as currently present in the cluster
+pub fn load_balancer_addresses(service: &Service) -> Vec<String> {
+    service
+        .status
+        .as_ref()
+        .and_then(|status| status.load_balancer.as_ref())
+        .and_then(|load_balancer| load_balancer.ingress.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|ingress| ingress.ip.clone().or_else(|| ingress.hostname.clone()))
+        .collect()
+}
+
+/// Deletes an existing service.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the Service with
+/// - `name` - Name of the service to delete
+/// - `namespace` - Namespace the existing service resides in
+///
+/// Note: A service that doesn't exist is considered deleted, as it is removed along with the last
+/// HTTP ingress point.
+pub async fn delete_service(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<Service> = Api::namespaced(client, namespace);
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use hyper::{Method, StatusCode};
+    use serde_json::json;
+
+    #[test]
+    fn build_service_is_owned_by_the_fox_service() {
+        let spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|container| container.image("nginx:1.21").port(80))
+            .http_ingress("web", 80, "example.com", "/")
+            .build()
+            .unwrap();
+        let mut fox_svc = FoxService::new("web", spec);
+        fox_svc.meta_mut().uid = Some("uid-web".to_string());
+
+        let service = build_service(&fox_svc, "default");
+
+        let owners = service.metadata.owner_references.unwrap();
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].kind, "FoxService");
+        assert_eq!(owners[0].api_version, "cbopt.com/v1");
+        assert_eq!(owners[0].name, "web");
+        assert_eq!(owners[0].uid, "uid-web");
+        assert_eq!(owners[0].controller, Some(true));
+    }
+
+    #[tokio::test]
+    async fn apply_service_leaves_a_foreign_service_alone() {
+        let spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|container| container.image("nginx:1.21").port(80))
+            .http_ingress("web", 80, "example.com", "/")
+            .build()
+            .unwrap();
+        let mut fox_svc = FoxService::new("web", spec);
+        fox_svc.meta_mut().namespace = Some("default".to_string());
+        fox_svc.meta_mut().uid = Some("uid-web".to_string());
+        let (client, requests) = mock::client(|method, _, _| match *method {
+            // A Service of the same name, created by someone else
+            Method::GET => {
+                let foreign = json!({
+                    "apiVersion": "v1",
+                    "kind": "Service",
+                    "metadata": { "name": "web", "namespace": "default", "labels": { "app": "legacy" } },
+                    "spec": { "selector": { "app": "legacy" }, "ports": [{ "port": 8080 }] }
+                });
+                (StatusCode::OK, foreign)
+            }
+            _ => mock::status(500, "InternalError"),
+        });
+
+        let error = apply_service(client, &fox_svc, "default")
+            .await
+            .unwrap_err();
+
+        assert!(crate::fox_service::already_exists(&error));
+        assert_eq!(
+            requests.lines(),
+            ["GET /api/v1/namespaces/default/services/web"]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use super::{contains_all, metadata_differs, owner_reference};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::core::v1::ServiceAccount;
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error};
+use serde_json::json;
+
+/// Builds the desired state of the ServiceAccount created for a `FoxService`, owned by the
+/// `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource the pods of which run as the ServiceAccount
+/// - `account` - ServiceAccount configuration of the service
+/// - `namespace` - Namespace the ServiceAccount is going to reside in.
+pub fn build_service_account(
+    fox_svc: &FoxService,
+    account: &FoxServiceAccount,
+    namespace: &str,
+) -> ServiceAccount {
+    ServiceAccount {
+        metadata: ObjectMeta {
+            name: Some(account.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, account.annotations.clone()),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        ..ServiceAccount::default()
+    }
+}
+
+/// Creates the ServiceAccount of a `FoxService` if the operator is meant to create it and it
+/// doesn't exist yet, and updates the labels and annotations of an existing one if they changed.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the ServiceAccount with.
+/// - `fox_svc` - The `FoxService` resource the pods of which run as the ServiceAccount
+/// - `namespace` - Namespace the ServiceAccount resides in.
+///
+/// Note: Annotations removed from the specification are left on the ServiceAccount, as they can't
+/// be told apart from annotations added by others.
+pub async fn apply_service_account(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let account = match &fox_svc.spec.service_account {
+        Some(account) if account.create == Some(true) => account,
+        _ => return Ok(()),
+    };
+    let api: Api<ServiceAccount> = Api::namespaced(client, namespace);
+    let existing = match api.get(&account.name).await {
+        Ok(existing) => existing,
+        Err(Error::Api(response)) if response.code == 404 => {
+            let service_account = build_service_account(fox_svc, account, namespace);
+            api.create(&PostParams::default(), &service_account).await?;
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    };
+
+    let desired = build_service_account(fox_svc, account, namespace);
+    if !contains_all(
+        &existing.metadata.annotations,
+        &desired.metadata.annotations,
+    ) || metadata_differs(fox_svc, &existing.metadata)
+    {
+        let annotate = json!({
+            "metadata": {
+                "labels": desired.metadata.labels,
+                "annotations": desired.metadata.annotations
+            }
+        });
+        api.patch(
+            &account.name,
+            &PatchParams::default(),
+            &Patch::Merge(&annotate),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Deletes the ServiceAccount of a `FoxService` if it was created by the operator. A
+/// ServiceAccount which doesn't exist is skipped.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to delete the ServiceAccount with.
+/// - `fs` - Fox service specification
+/// - `namespace` - Namespace the ServiceAccount resides in.
+pub async fn delete_service_account(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<(), Error> {
+    let account = match &fs.service_account {
+        Some(account) if account.create == Some(true) => account,
+        _ => return Ok(()),
+    };
+    let api: Api<ServiceAccount> = Api::namespaced(client, namespace);
+    match api.delete(&account.name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
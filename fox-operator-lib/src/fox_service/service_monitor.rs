@@ -0,0 +1,133 @@
+use super::{is_subset, metadata_differs, owner_reference, selector_labels};
+use fox_k8s_crds::fox_service::*;
+use kube::api::{
+    ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams, PostParams,
+};
+use kube::{Api, Client, Error, Resource};
+use serde_json::json;
+
+/// Path Prometheus scrapes if none is configured.
+const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+/// The ServiceMonitor resource of the Prometheus operator. The operator is optional, so monitors
+/// are managed as dynamic objects rather than depending on its types.
+fn api_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk("monitoring.coreos.com", "v1", "ServiceMonitor");
+    ApiResource::from_gvk_with_plural(&gvk, "servicemonitors")
+}
+
+/// Builds the desired state of the ServiceMonitor making Prometheus scrape the metrics of a
+/// `FoxService` through its Service.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the monitor
+/// - `metrics` - Metrics endpoint of the service
+/// - `namespace` - Namespace the ServiceMonitor is going to reside in.
+pub fn build_service_monitor(
+    fox_svc: &FoxService,
+    metrics: &FoxServiceMetrics,
+    namespace: &str,
+) -> DynamicObject {
+    let mut endpoint = json!({
+        "port": metrics.port,
+        "path": metrics.path.as_deref().unwrap_or(DEFAULT_METRICS_PATH)
+    });
+    if let Some(interval) = &metrics.interval {
+        endpoint["interval"] = json!(interval);
+    }
+
+    let mut monitor = DynamicObject::new(&fox_svc.spec.name, &api_resource())
+        .within(namespace)
+        .data(json!({
+            "spec": {
+                "selector": { "matchLabels": selector_labels(fox_svc) },
+                "endpoints": [endpoint]
+            }
+        }));
+    monitor.metadata.labels = Some(super::labels(fox_svc));
+    monitor.metadata.annotations = super::annotations(fox_svc, None);
+    monitor.metadata.owner_references = Some(vec![owner_reference(fox_svc)]);
+    monitor
+}
+
+/// Creates the ServiceMonitor of a `FoxService` if metrics are declared and the monitor doesn't
+/// exist yet, and patches an existing monitor in place if it drifted from the specification. The
+/// monitor is deleted once the metrics are removed.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the monitor with.
+/// - `fox_svc` - The `FoxService` resource owning the monitor
+/// - `namespace` - Namespace the monitor resides in.
+///
+/// Returns the monitor if it was created. Fails with a 404 if the Prometheus operator isn't
+/// installed.
+pub async fn apply_service_monitor(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<DynamicObject>, Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &api_resource());
+    let live = match api.get(&fs.name).await {
+        Ok(monitor) => Some(monitor),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    match (&fs.metrics, live) {
+        (Some(metrics), None) => {
+            let monitor = build_service_monitor(fox_svc, metrics, namespace);
+            Ok(Some(api.create(&PostParams::default(), &monitor).await?))
+        }
+        (Some(metrics), Some(live)) => {
+            let desired = build_service_monitor(fox_svc, metrics, namespace);
+            if !is_subset(&desired.data["spec"], &live.data["spec"])
+                || metadata_differs(fox_svc, &live.metadata)
+            {
+                let mut patch = desired.data.clone();
+                patch["metadata"] = json!({
+                    "labels": desired.metadata.labels,
+                    "annotations": desired.metadata.annotations
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+            Ok(None)
+        }
+        (None, None) => Ok(None),
+        // A monitor of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_service_monitor(client, &fs.name, namespace).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Deletes an existing ServiceMonitor.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the ServiceMonitor with
+/// - `name` - Name of the ServiceMonitor to delete
+/// - `namespace` - Namespace the existing ServiceMonitor resides in
+///
+/// Note: A ServiceMonitor that doesn't exist is considered deleted, as is one of a cluster without
+/// the Prometheus operator.
+pub async fn delete_service_monitor(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client, namespace, &api_resource());
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,325 @@
+use super::deployment::{build_pod_template, pod_template_differs};
+use super::hook::generation_annotation;
+use super::persistent_volume_claim::build_claim_spec;
+use super::{controller_uid, foreground_deletion, metadata_differs};
+use super::{owner_reference, selector_labels};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::apps::v1::{StatefulSet, StatefulSetSpec};
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Service, ServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Error, Resource};
+use serde_json::{json, Value};
+
+/// Number of old revisions the API server defaults StatefulSets to keep.
+const DEFAULT_REVISION_HISTORY_LIMIT: i32 = 10;
+
+/// Name of the headless Service governing the StatefulSet of a `FoxService`. It provides the
+/// stable network identities of the pods, `<pod>.<service>.<namespace>.svc`.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn governing_service_name(fs: &FoxServiceSpec) -> String {
+    format!("{}-headless", fs.name)
+}
+
+/// Checks whether the pods of a `FoxService` are run by a StatefulSet.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn is_stateful(fs: &FoxServiceSpec) -> bool {
+    fs.workload == Some(WorkloadKind::StatefulSet)
+}
+
+/// Builds the desired state of the StatefulSet backing a `FoxService`. Each persistent volume is
+/// turned into a volume claim template, giving every pod a claim of its own.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the StatefulSet
+/// - `namespace` - Namespace the StatefulSet is going to reside in.
+pub fn build_stateful_set(fox_svc: &FoxService, namespace: &str) -> StatefulSet {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let claim_templates: Vec<PersistentVolumeClaim> = fs
+        .volumes
+        .iter()
+        .flatten()
+        .filter_map(|volume| {
+            let persistence = volume.persistence.as_ref()?;
+            Some(PersistentVolumeClaim {
+                metadata: ObjectMeta {
+                    name: Some(volume.name.to_owned()),
+                    labels: Some(selector_labels(fox_svc)),
+                    ..ObjectMeta::default()
+                },
+                spec: Some(build_claim_spec(persistence)),
+                ..PersistentVolumeClaim::default()
+            })
+        })
+        .collect();
+    StatefulSet {
+        metadata: ObjectMeta {
+            name: Some(fs.name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, generation_annotation(fox_svc)),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(StatefulSetSpec {
+            replicas: super::workload::replicas(fox_svc),
+            revision_history_limit: fs.revision_history_limit,
+            service_name: governing_service_name(fs),
+            selector: LabelSelector {
+                match_labels: Some(selector_labels(fox_svc)),
+                match_expressions: None,
+            },
+            template: build_pod_template(fox_svc, false),
+            volume_claim_templates: Some(claim_templates).filter(|claims| !claims.is_empty()),
+            ..StatefulSetSpec::default()
+        }),
+        ..StatefulSet::default()
+    }
+}
+
+/// Creates a new StatefulSet backing a `FoxService`.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to create the StatefulSet with.
+/// - `fox_svc` - The `FoxService` resource owning the StatefulSet
+/// - `namespace` - Namespace to create the StatefulSet in.
+///
+/// Note: It is assumed the resource does not already exists for simplicity. Returns an `Error` if it does.
+pub async fn create_stateful_set(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<StatefulSet, Error> {
+    let stateful_set: StatefulSet = build_stateful_set(fox_svc, namespace);
+    let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+    api.create(&PostParams::default(), &stateful_set).await
+}
+
+/// Fetches the StatefulSet with given name, if it exists.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the StatefulSet with
+/// - `name` - Name of the StatefulSet to fetch
+/// - `namespace` - Namespace the StatefulSet resides in
+///
+/// Returns `Ok(None)` if there is no such StatefulSet.
+pub async fn get_stateful_set(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<StatefulSet>, Error> {
+    let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(stateful_set) => Ok(Some(stateful_set)),
+        Err(Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Patches an existing StatefulSet to match the given Fox service specification.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to patch the StatefulSet with.
+/// - `fox_svc` - The `FoxService` resource owning the StatefulSet
+/// - `namespace` - Namespace the existing StatefulSet resides in.
+///
+/// Note: The selector, the governing service and the volume claim templates of a StatefulSet
+/// can't be changed, they are left out of the patch. Claims of existing pods are resized by
+/// editing them directly.
+pub async fn update_stateful_set(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<StatefulSet, Error> {
+    let stateful_set = build_stateful_set(fox_svc, namespace);
+    let spec = stateful_set.spec.unwrap_or_default();
+    let mut patch: Value = json!({
+        "metadata": {
+            "labels": stateful_set.metadata.labels,
+            "ownerReferences": [owner_reference(fox_svc)]
+        },
+        "spec": {
+            "replicas": spec.replicas,
+            "revisionHistoryLimit": spec.revision_history_limit,
+            "template": spec.template
+        }
+    });
+    // A `null` would reset the replicas managed by an autoscaler, an omitted field is left
+    // untouched by a merge patch.
+    if let (None, Some(spec)) = (spec.replicas, patch["spec"].as_object_mut()) {
+        spec.remove("replicas");
+    }
+    // Records the generation the pre-deploy hooks completed for and the propagated annotations,
+    // annotations of others are kept.
+    if let Some(annotations) = stateful_set.metadata.annotations {
+        patch["metadata"]["annotations"] = json!(annotations);
+    }
+
+    let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+    api.patch(
+        &fox_svc.spec.name,
+        &PatchParams::default(),
+        &Patch::Merge(&patch),
+    )
+    .await
+}
+
+/// Decides whether a live StatefulSet has drifted from the desired state. Only the fields which
+/// can be changed in place are compared.
+///
+/// # Arguments
+/// - `desired` - StatefulSet as built by `build_stateful_set`
+/// - `live` - StatefulSet as currently present in the cluster
+pub fn needs_update(desired: &StatefulSet, live: &StatefulSet) -> bool {
+    let (desired_metadata, live_metadata) = (&desired.metadata, &live.metadata);
+    let (desired, live) = match (desired.spec.as_ref(), live.spec.as_ref()) {
+        (Some(desired), Some(live)) => (desired, live),
+        (desired, live) => return desired.is_some() != live.is_some(),
+    };
+    if desired.replicas.is_some() && desired.replicas != live.replicas {
+        return true;
+    }
+    if desired
+        .revision_history_limit
+        .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT)
+        != live
+            .revision_history_limit
+            .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT)
+    {
+        return true;
+    }
+    if controller_uid(desired_metadata) != controller_uid(live_metadata) {
+        return true;
+    }
+    pod_template_differs(&desired.template, &live.template)
+}
+
+/// Deletes an existing StatefulSet. Its pods are deleted before the StatefulSet itself is gone,
+/// so a workload replacing it doesn't run alongside them for long.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the StatefulSet with
+/// - `name` - Name of the StatefulSet to delete
+/// - `namespace` - Namespace the existing StatefulSet resides in
+///
+/// Note: A StatefulSet that doesn't exist is considered deleted. The claims of its pods are kept.
+pub async fn delete_stateful_set(client: Client, name: &str, namespace: &str) -> Result<(), Error> {
+    let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+    match api.delete(name, &foreground_deletion()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Builds the desired state of the headless Service governing the StatefulSet of a `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the Service
+/// - `namespace` - Namespace the Service is going to reside in.
+pub fn build_governing_service(fox_svc: &FoxService, namespace: &str) -> Service {
+    Service {
+        metadata: ObjectMeta {
+            name: Some(governing_service_name(&fox_svc.spec)),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(super::labels(fox_svc)),
+            annotations: super::annotations(fox_svc, None),
+            owner_references: Some(vec![owner_reference(fox_svc)]),
+            ..ObjectMeta::default()
+        },
+        spec: Some(ServiceSpec {
+            cluster_ip: Some("None".to_string()),
+            selector: Some(selector_labels(fox_svc)),
+            ..ServiceSpec::default()
+        }),
+        ..Service::default()
+    }
+}
+
+/// Creates the headless Service governing the StatefulSet of a `FoxService` if it doesn't exist
+/// yet, or restores its selector. It is deleted if owned by the `FoxService` and the pods are no
+/// longer run by a StatefulSet.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the Service with.
+/// - `fox_svc` - The `FoxService` resource owning the Service
+/// - `namespace` - Namespace the Service resides in.
+pub async fn apply_governing_service(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let name = governing_service_name(fs);
+    let api: Api<Service> = Api::namespaced(client.clone(), namespace);
+    let live = match api.get(&name).await {
+        Ok(service) => Some(service),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    let desired = build_governing_service(fox_svc, namespace);
+    match live {
+        None if is_stateful(fs) => {
+            api.create(&PostParams::default(), &desired).await?;
+        }
+        None => {}
+        Some(live) if is_stateful(fs) => {
+            let selector =
+                |service: &Service| service.spec.as_ref().and_then(|spec| spec.selector.clone());
+            if selector(&desired) != selector(&live) || metadata_differs(fox_svc, &live.metadata) {
+                let patch = json!({
+                    "metadata": {
+                        "labels": desired.metadata.labels,
+                        "annotations": desired.metadata.annotations
+                    },
+                    "spec": {
+                        "selector": selector(&desired)
+                    }
+                });
+                api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+        }
+        // A service of the same name not owned by the `FoxService` is left alone
+        Some(live) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_governing_service(client, fs, namespace).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the headless Service governing the StatefulSet of a `FoxService`.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the Service with
+/// - `fs` - Fox service specification
+/// - `namespace` - Namespace the existing Service resides in
+///
+/// Note: A Service that doesn't exist is considered deleted.
+pub async fn delete_governing_service(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<Service> = Api::namespaced(client, namespace);
+    match api
+        .delete(&governing_service_name(fs), &DeleteParams::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
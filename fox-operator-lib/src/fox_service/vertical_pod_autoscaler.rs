@@ -0,0 +1,164 @@
+use super::{is_subset, metadata_differs, owner_reference, workload};
+use fox_k8s_crds::fox_service::*;
+use kube::api::{
+    ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams, PostParams,
+};
+use kube::{Api, Client, Error, Resource};
+use serde_json::{json, Value};
+
+/// The VerticalPodAutoscaler resource of the Kubernetes autoscaler. It is optional, so vertical
+/// autoscalers are managed as dynamic objects rather than depending on its types.
+fn api_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk("autoscaling.k8s.io", "v1", "VerticalPodAutoscaler");
+    ApiResource::from_gvk_with_plural(&gvk, "verticalpodautoscalers")
+}
+
+/// Translates bounds of resource requests into a resource list, leaving out unset resources.
+fn resource_list(bounds: &FoxServiceResourceBounds) -> Value {
+    let mut list = json!({});
+    if let Some(cpu) = &bounds.cpu {
+        list["cpu"] = json!(cpu);
+    }
+    if let Some(memory) = &bounds.memory {
+        list["memory"] = json!(memory);
+    }
+    list
+}
+
+/// Builds the desired state of the VerticalPodAutoscaler setting the resource requests of the
+/// containers of a `FoxService`.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the autoscaler
+/// - `vertical_autoscaling` - Vertical autoscaling configuration of the service
+/// - `namespace` - Namespace the VerticalPodAutoscaler is going to reside in.
+pub fn build_vertical_pod_autoscaler(
+    fox_svc: &FoxService,
+    vertical_autoscaling: &FoxServiceVerticalAutoscaling,
+    namespace: &str,
+) -> DynamicObject {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let update_mode = vertical_autoscaling
+        .update_mode
+        .unwrap_or(VerticalUpdateMode::Auto);
+    let policies: Vec<Value> = vertical_autoscaling
+        .container_policies
+        .iter()
+        .flatten()
+        .map(|policy| {
+            let mut container_policy = json!({ "containerName": policy.container_name });
+            if let Some(min_allowed) = &policy.min_allowed {
+                container_policy["minAllowed"] = resource_list(min_allowed);
+            }
+            if let Some(max_allowed) = &policy.max_allowed {
+                container_policy["maxAllowed"] = resource_list(max_allowed);
+            }
+            container_policy
+        })
+        .collect();
+
+    let mut spec = json!({
+        "targetRef": workload::target_ref(fs),
+        "updatePolicy": { "updateMode": format!("{:?}", update_mode) }
+    });
+    if !policies.is_empty() {
+        spec["resourcePolicy"] = json!({ "containerPolicies": policies });
+    }
+    let mut autoscaler = DynamicObject::new(&fs.name, &api_resource())
+        .within(namespace)
+        .data(json!({ "spec": spec }));
+    autoscaler.metadata.labels = Some(super::labels(fox_svc));
+    autoscaler.metadata.annotations = super::annotations(fox_svc, None);
+    autoscaler.metadata.owner_references = Some(vec![owner_reference(fox_svc)]);
+    autoscaler
+}
+
+/// Creates the VerticalPodAutoscaler of a `FoxService` if vertical autoscaling is enabled and the
+/// autoscaler doesn't exist yet, and patches an existing autoscaler if it drifted from the
+/// specification. The autoscaler is deleted once vertical autoscaling is disabled.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the autoscaler with.
+/// - `fox_svc` - The `FoxService` resource owning the autoscaler
+/// - `namespace` - Namespace the autoscaler resides in.
+///
+/// Returns the autoscaler if it was created. Fails with a 404 if the VerticalPodAutoscaler CRD
+/// isn't installed.
+pub async fn apply_vertical_pod_autoscaler(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<DynamicObject>, Error> {
+    let fs: &FoxServiceSpec = &fox_svc.spec;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &api_resource());
+    let live = match api.get(&fs.name).await {
+        Ok(autoscaler) => Some(autoscaler),
+        Err(Error::Api(response)) if response.code == 404 => None,
+        Err(error) => return Err(error),
+    };
+    match (&fs.vertical_autoscaling, live) {
+        (Some(vertical_autoscaling), None) => {
+            let autoscaler =
+                build_vertical_pod_autoscaler(fox_svc, vertical_autoscaling, namespace);
+            Ok(Some(api.create(&PostParams::default(), &autoscaler).await?))
+        }
+        (Some(vertical_autoscaling), Some(live)) => {
+            let desired = build_vertical_pod_autoscaler(fox_svc, vertical_autoscaling, namespace);
+            // The resource policy is replaced as a whole, removed bounds are dropped with it.
+            let removed = desired.data["spec"].get("resourcePolicy").is_none()
+                && live.data["spec"].get("resourcePolicy").is_some();
+            if removed
+                || !is_subset(&desired.data["spec"], &live.data["spec"])
+                || metadata_differs(fox_svc, &live.metadata)
+            {
+                let mut patch = desired.data.clone();
+                if patch["spec"].get("resourcePolicy").is_none() {
+                    patch["spec"]["resourcePolicy"] = Value::Null;
+                }
+                patch["metadata"] = json!({
+                    "labels": desired.metadata.labels,
+                    "annotations": desired.metadata.annotations
+                });
+                api.patch(&fs.name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+            }
+            Ok(None)
+        }
+        (None, None) => Ok(None),
+        // An autoscaler of the same name not owned by the `FoxService` is left alone
+        (None, Some(live)) => {
+            let owned = live
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref());
+            if owned {
+                delete_vertical_pod_autoscaler(client, &fs.name, namespace).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Deletes an existing VerticalPodAutoscaler.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the VerticalPodAutoscaler with
+/// - `name` - Name of the VerticalPodAutoscaler to delete
+/// - `namespace` - Namespace the existing VerticalPodAutoscaler resides in
+///
+/// Note: A VerticalPodAutoscaler that doesn't exist is considered deleted, as is one of a cluster
+/// without the VerticalPodAutoscaler CRD.
+pub async fn delete_vertical_pod_autoscaler(
+    client: Client,
+    name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client, namespace, &api_resource());
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(error) => Err(error),
+    }
+}
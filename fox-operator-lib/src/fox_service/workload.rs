@@ -0,0 +1,330 @@
+use super::{daemon_set, deployment, stateful_set};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::autoscaling::v2beta2::CrossVersionObjectReference;
+use kube::api::ObjectMeta;
+use kube::{Client, Error, Resource};
+
+/// The workload running the pods of a `FoxService`, either a Deployment, a StatefulSet or a
+/// DaemonSet.
+pub enum Workload {
+    Deployment(Deployment),
+    StatefulSet(StatefulSet),
+    DaemonSet(DaemonSet),
+}
+
+impl Workload {
+    /// Kind of the workload.
+    pub fn kind(&self) -> WorkloadKind {
+        match self {
+            Workload::Deployment(_) => WorkloadKind::Deployment,
+            Workload::StatefulSet(_) => WorkloadKind::StatefulSet,
+            Workload::DaemonSet(_) => WorkloadKind::DaemonSet,
+        }
+    }
+
+    /// Metadata of the workload.
+    pub fn metadata(&self) -> &ObjectMeta {
+        match self {
+            Workload::Deployment(deployment) => deployment.meta(),
+            Workload::StatefulSet(stateful_set) => stateful_set.meta(),
+            Workload::DaemonSet(daemon_set) => daemon_set.meta(),
+        }
+    }
+
+    /// Name of the workload, as used in events, e.g., `deployment my-service`.
+    pub fn describe(&self) -> String {
+        let kind = match self {
+            Workload::Deployment(_) => Deployment::kind(&()),
+            Workload::StatefulSet(_) => StatefulSet::kind(&()),
+            Workload::DaemonSet(_) => DaemonSet::kind(&()),
+        };
+        format!(
+            "{} {}",
+            kind.to_lowercase(),
+            self.metadata().name.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// Kind of the workload selected by a Fox service specification.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn kind(fs: &FoxServiceSpec) -> WorkloadKind {
+    fs.workload_kind()
+}
+
+/// Checks whether the workload of a `FoxService` is meant to be scaled down to zero pods.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn is_suspended(fs: &FoxServiceSpec) -> bool {
+    fs.suspend == Some(true)
+}
+
+/// Number of pods of the Deployment or StatefulSet of a `FoxService`. Zero while suspended,
+/// `None` if left to an autoscaler or to others.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the workload
+pub fn replicas(fox_svc: &FoxService) -> Option<i32> {
+    let fs = &fox_svc.spec;
+    if is_suspended(fs) {
+        return Some(0);
+    }
+    match &fs.autoscaling {
+        // A HorizontalPodAutoscaler doesn't scale a workload up from zero pods, a resumed workload
+        // starts at the minimum. It is left to the autoscaler from the next reconciliation on.
+        Some(autoscaling) if was_suspended(fox_svc) => Some(autoscaling.min_replicas.unwrap_or(1)),
+        // Left to the HorizontalPodAutoscaler, it would be reset on every update otherwise.
+        Some(_) => None,
+        None => fs.replicas,
+    }
+}
+
+/// Checks whether the last reconciliation pass reported the `FoxService` as suspended.
+fn was_suspended(fox_svc: &FoxService) -> bool {
+    fox_svc
+        .status
+        .iter()
+        .flat_map(|status| status.conditions.iter())
+        .any(|condition| {
+            condition.type_ == FoxServiceCondition::SUSPENDED && condition.status == "True"
+        })
+}
+
+/// Reference to the workload of the kind selected by a Fox service specification, as targeted by
+/// autoscalers.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn target_ref(fs: &FoxServiceSpec) -> CrossVersionObjectReference {
+    let (api_version, kind) = match kind(fs) {
+        WorkloadKind::Deployment => (Deployment::api_version(&()), Deployment::kind(&())),
+        WorkloadKind::StatefulSet => (StatefulSet::api_version(&()), StatefulSet::kind(&())),
+        WorkloadKind::DaemonSet => (DaemonSet::api_version(&()), DaemonSet::kind(&())),
+    };
+    CrossVersionObjectReference {
+        api_version: Some(api_version.to_string()),
+        kind: kind.to_string(),
+        name: fs.name.to_owned(),
+    }
+}
+
+/// Checks whether a `FoxService` has long-running containers, i.e., containers not marked as
+/// `batch`, and therefore a workload.
+///
+/// # Arguments
+/// - `fs` - Fox service specification
+pub fn is_required(fs: &FoxServiceSpec) -> bool {
+    fs.containers
+        .iter()
+        .any(|container| !container.batch.unwrap_or(false))
+}
+
+/// Fetches the workload of the kind selected by the specification of a `FoxService`, if it exists.
+/// A workload of another kind left behind by switching kinds is not returned, neither is a
+/// workload left behind by marking all containers as `batch`.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to fetch the workload with
+/// - `fox_svc` - The `FoxService` resource owning the workload
+/// - `namespace` - Namespace the workload resides in
+pub async fn get_workload(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Option<Workload>, Error> {
+    if !is_required(&fox_svc.spec) {
+        return Ok(None);
+    }
+    let name = &fox_svc.spec.name;
+    Ok(match kind(&fox_svc.spec) {
+        WorkloadKind::Deployment => deployment::get_deployment(client, name, namespace)
+            .await?
+            .map(Workload::Deployment),
+        WorkloadKind::StatefulSet => stateful_set::get_stateful_set(client, name, namespace)
+            .await?
+            .map(Workload::StatefulSet),
+        WorkloadKind::DaemonSet => daemon_set::get_daemon_set(client, name, namespace)
+            .await?
+            .map(Workload::DaemonSet),
+    })
+}
+
+/// Decides whether a live workload no longer reflects the specification of a `FoxService`. A
+/// workload of another kind than the selected one always does.
+///
+/// # Arguments
+/// - `fox_svc` - The `FoxService` resource owning the workload
+/// - `workload` - Workload as currently present in the cluster
+/// - `namespace` - Namespace the workload resides in
+pub fn needs_update(fox_svc: &FoxService, workload: &Workload, namespace: &str) -> bool {
+    // Labels and annotations of the `FoxService` object don't change its generation.
+    if super::metadata_differs(fox_svc, workload.metadata()) {
+        return true;
+    }
+    match (kind(&fox_svc.spec), workload) {
+        (WorkloadKind::Deployment, Workload::Deployment(live)) => {
+            deployment::needs_update(&deployment::build_deployment(fox_svc, namespace), live)
+        }
+        (WorkloadKind::StatefulSet, Workload::StatefulSet(live)) => {
+            stateful_set::needs_update(&stateful_set::build_stateful_set(fox_svc, namespace), live)
+        }
+        (WorkloadKind::DaemonSet, Workload::DaemonSet(live)) => {
+            daemon_set::needs_update(&daemon_set::build_daemon_set(fox_svc, namespace), live)
+        }
+        _ => true,
+    }
+}
+
+/// Creates the workload of the kind selected by the specification of a `FoxService`. Workloads of
+/// other kinds owned by the `FoxService`, left behind by switching kinds, are deleted first.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to manage the workloads with.
+/// - `fox_svc` - The `FoxService` resource owning the workload
+/// - `namespace` - Namespace to create the workload in.
+///
+/// Note: An existing deployment of the same name is adopted if it belongs to the `FoxService`, see
+/// `deployment::create_deployment`. Returns an `Error` if a workload of another kind exists.
+pub async fn create_workload(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Workload, Error> {
+    delete_previous_workloads(client.clone(), fox_svc, namespace).await?;
+    match kind(&fox_svc.spec) {
+        WorkloadKind::Deployment => deployment::create_deployment(client, fox_svc, namespace)
+            .await
+            .map(Workload::Deployment),
+        WorkloadKind::StatefulSet => stateful_set::create_stateful_set(client, fox_svc, namespace)
+            .await
+            .map(Workload::StatefulSet),
+        WorkloadKind::DaemonSet => daemon_set::create_daemon_set(client, fox_svc, namespace)
+            .await
+            .map(Workload::DaemonSet),
+    }
+}
+
+/// Deletes the workloads of other kinds than the selected one owned by a `FoxService`, or all of
+/// them if the `FoxService` has no long-running containers. Workloads of the same name not owned
+/// by the `FoxService` are left alone.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to delete the workloads with.
+/// - `fox_svc` - The `FoxService` resource owning the workloads
+/// - `namespace` - Namespace the workloads reside in.
+pub async fn delete_previous_workloads(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let name = &fox_svc.spec.name;
+    let selected = Some(kind(&fox_svc.spec)).filter(|_| is_required(&fox_svc.spec));
+    let owned = |metadata: &ObjectMeta| {
+        metadata
+            .owner_references
+            .iter()
+            .flatten()
+            .any(|owner| Some(&owner.uid) == fox_svc.meta().uid.as_ref())
+    };
+    if selected != Some(WorkloadKind::Deployment) {
+        let previous = deployment::get_deployment(client.clone(), name, namespace).await?;
+        if previous.is_some_and(|previous| owned(previous.meta())) {
+            deployment::delete_deployment(client.clone(), name, namespace).await?;
+        }
+    }
+    if selected != Some(WorkloadKind::StatefulSet) {
+        let previous = stateful_set::get_stateful_set(client.clone(), name, namespace).await?;
+        if previous.is_some_and(|previous| owned(previous.meta())) {
+            stateful_set::delete_stateful_set(client.clone(), name, namespace).await?;
+        }
+    }
+    if selected != Some(WorkloadKind::DaemonSet) {
+        let previous = daemon_set::get_daemon_set(client.clone(), name, namespace).await?;
+        if previous.is_some_and(|previous| owned(previous.meta())) {
+            daemon_set::delete_daemon_set(client, name, namespace).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Patches the workload of the kind selected by the specification of a `FoxService` to match it.
+///
+/// # Arguments
+/// - `client` - A Kubernetes client to patch the workload with.
+/// - `fox_svc` - The `FoxService` resource owning the workload
+/// - `namespace` - Namespace the existing workload resides in.
+pub async fn update_workload(
+    client: Client,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<Workload, Error> {
+    match kind(&fox_svc.spec) {
+        WorkloadKind::Deployment => deployment::apply_deployment(client, fox_svc, namespace)
+            .await
+            .map(Workload::Deployment),
+        WorkloadKind::StatefulSet => stateful_set::update_stateful_set(client, fox_svc, namespace)
+            .await
+            .map(Workload::StatefulSet),
+        WorkloadKind::DaemonSet => daemon_set::update_daemon_set(client, fox_svc, namespace)
+            .await
+            .map(Workload::DaemonSet),
+    }
+}
+
+/// Deletes the workloads of all kinds backing a `FoxService`, as the kind may have been switched
+/// since they were created.
+///
+/// # Arguments:
+/// - `client` - A Kubernetes client to delete the workloads with
+/// - `fs` - Fox service specification
+/// - `namespace` - Namespace the workloads reside in
+///
+/// Note: Workloads which don't exist are skipped.
+pub async fn delete_workloads(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<(), Error> {
+    deployment::delete_deployment(client.clone(), &fs.name, namespace).await?;
+    stateful_set::delete_stateful_set(client.clone(), &fs.name, namespace).await?;
+    daemon_set::delete_daemon_set(client.clone(), &fs.name, namespace).await?;
+    stateful_set::delete_governing_service(client, fs, namespace).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `FoxService` running a single container, with given number of pods.
+    fn with_replicas(replicas: Option<i32>) -> FoxService {
+        let mut spec = FoxServiceSpec::builder()
+            .name("web")
+            .container(|container| container.image("nginx:1.21").port(80))
+            .build()
+            .unwrap();
+        spec.replicas = replicas;
+        FoxService::new("web", spec)
+    }
+
+    #[test]
+    fn replicas_are_left_alone_when_unset() {
+        let fox_svc = with_replicas(None);
+
+        assert_eq!(replicas(&fox_svc), None);
+        let body = deployment::apply_body(&fox_svc, "default").unwrap();
+        assert!(body["spec"].get("replicas").is_none());
+    }
+
+    #[test]
+    fn replicas_are_applied_when_set() {
+        let fox_svc = with_replicas(Some(3));
+
+        assert_eq!(replicas(&fox_svc), Some(3));
+        let body = deployment::apply_body(&fox_svc, "default").unwrap();
+        assert_eq!(body["spec"]["replicas"], 3);
+    }
+}
@@ -0,0 +1,1371 @@
+use futures::stream::StreamExt;
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::networking::v1::Ingress;
+use kube::api::{DynamicObject, ListParams, ObjectMeta};
+use kube::{client::Client, Api};
+use kube::{Resource, ResourceExt};
+use kube_runtime::controller::{Context, ReconcilerAction};
+use kube_runtime::Controller;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use tokio::time::{Duration, Instant};
+
+use fox_k8s_crds::fox_service::*;
+
+use events::{EventType, Recorder};
+use fox_service::hook::HookState;
+use fox_service::workload::Workload;
+use metrics::Metrics;
+
+pub mod events;
+pub mod finalizer;
+pub mod fox_service;
+pub mod metrics;
+#[cfg(test)]
+mod mock;
+pub mod rbac;
+pub mod retry;
+pub mod status;
+pub mod validation;
+pub mod webhook;
+
+/// Delay before checking again on a successfully reconciled `FoxService`, either to verify the
+/// changes made converged or to detect new ones, unless configured otherwise.
+pub const DEFAULT_REQUEUE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Delay before checking again on a `FoxService` whose reconciliation is paused.
+const PAUSED_REQUEUE: Duration = Duration::from_secs(300);
+
+/// Environment variable holding the namespaces to watch `FoxService` resources in, separated by
+/// commas. All namespaces are watched if it is not set or empty.
+const WATCH_NAMESPACE_VAR: &str = "WATCH_NAMESPACE";
+
+/// Namespaces the operator watches `FoxService` resources in.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Scope {
+    /// All namespaces of the cluster, permissions are granted by a ClusterRole
+    Cluster,
+    /// The listed namespaces only, permissions are granted by a Role in each of them
+    Namespaces(Vec<String>),
+}
+
+impl Scope {
+    /// Lists the namespaces to watch, separated by commas, e.g., `team-a,team-b`.
+    ///
+    /// Returns the whole cluster if no namespace is listed.
+    pub fn parse(namespaces: &str) -> Self {
+        let mut namespaces: Vec<String> = namespaces
+            .split(',')
+            .map(str::trim)
+            .filter(|namespace| !namespace.is_empty())
+            .map(str::to_string)
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        if namespaces.is_empty() {
+            Scope::Cluster
+        } else {
+            Scope::Namespaces(namespaces)
+        }
+    }
+
+    /// Reads the namespaces to watch from `WATCH_NAMESPACE`, see `Scope::parse`.
+    pub fn from_env() -> Self {
+        Scope::parse(&std::env::var(WATCH_NAMESPACE_VAR).unwrap_or_default())
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Cluster => write!(f, "all namespaces"),
+            Scope::Namespaces(namespaces) => write!(f, "namespaces {}", namespaces.join(", ")),
+        }
+    }
+}
+
+/// Delay before retrying a reconciliation failed by a mistake in the `FoxService`, doubled on each
+/// consecutive failure.
+const USER_ERROR_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Longest delay before retrying a reconciliation failed by a mistake in the `FoxService`.
+const MAX_USER_ERROR_BACKOFF: Duration = Duration::from_secs(600);
+
+/// Delay before retrying a reconciliation failed by a conflict or throttling, doubled on each
+/// consecutive failure.
+const CONFLICT_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Longest delay before retrying a reconciliation failed by a conflict or throttling.
+const MAX_CONFLICT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Longest random delay added to retries of conflicts and throttling.
+const CONFLICT_JITTER: Duration = Duration::from_millis(500);
+
+/// Delay before retrying a reconciliation failed otherwise, doubled on each consecutive failure.
+const ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay before retrying a reconciliation failed otherwise.
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Interval at which the reconciliations in progress are checked on while shutting down.
+const DRAIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs the controller reconciling the `FoxService` resources in scope, until the watch of the
+/// resources ends or `shutdown` completes.
+///
+/// # Arguments
+/// - `context` - Shared state of the controller, its client watches the `FoxService` resources
+///   and manages their subresources.
+/// - `scope` - Namespaces to watch `FoxService` resources in. Subresources are only ever looked
+///   up in the namespace of their `FoxService`.
+/// - `selector` - Label selector of the `FoxService` resources to reconcile, checked by
+///   `validation::validate_selector`. Resources no longer matching it are left as they are.
+/// - `shutdown` - Completes once the operator is to shut down, e.g., on `SIGTERM`. No reconciliation
+///   is started afterwards, the ones in progress are given `drain_timeout` to finish, so they don't
+///   leave subresources half created.
+/// - `drain_timeout` - Time the reconciliations in progress are given to finish on shutdown.
+///
+/// Note: Each listed namespace is watched by a controller of its own, so resources in other
+/// namespaces are never seen. The controllers share the context.
+pub async fn run(
+    context: ContextData,
+    scope: &Scope,
+    selector: Option<&str>,
+    shutdown: impl Future<Output = ()>,
+    drain_timeout: Duration,
+) {
+    // Preparation of resources used by the `kube_runtime::Controller`
+    let crd_apis: Vec<Api<FoxService>> = match scope {
+        Scope::Cluster => vec![Api::all(context.client.clone())],
+        Scope::Namespaces(namespaces) => namespaces
+            .iter()
+            .map(|namespace| Api::namespaced(context.client.clone(), namespace))
+            .collect(),
+    };
+    let context: Context<ContextData> = Context::new(context);
+    let list_params = match selector {
+        Some(selector) => ListParams::default().labels(selector),
+        None => ListParams::default(),
+    };
+    tracing::info!(
+        %scope,
+        selector = selector.unwrap_or_default(),
+        "Watching FoxService resources"
+    );
+
+    // The controller comes from the `kube_runtime` crate and manages the reconciliation process.
+    // It requires the following information:
+    // - `kube::Api<T>` this controller "owns". In this case, `T = FoxService`, as this controller owns the `FoxService` resource,
+    // - `kube::api::ListParams` to select the `FoxService` resources with. Can be used for FoxService filtering `FoxService` resources before reconciliation,
+    // - `reconcile` function with reconciliation logic to be called each time a resource of `FoxService` kind is created/updated/deleted,
+    // - `on_error` function to call whenever reconciliation fails.
+    let controllers = crd_apis.into_iter().map(|crd_api| {
+        Controller::new(crd_api, list_params.clone())
+            .run(reconcile, on_error, context.clone())
+            .boxed()
+    });
+    let reconciliations =
+        futures::stream::select_all(controllers).for_each(|reconciliation_result| async move {
+            match reconciliation_result {
+                Ok((fox_serv_res, _)) => {
+                    tracing::debug!(
+                        name = %fox_serv_res.name,
+                        namespace = ?fox_serv_res.namespace,
+                        "Reconciliation successful"
+                    );
+                }
+                Err(reconciliation_err) => {
+                    tracing::warn!(error = %reconciliation_err, "Reconciliation error");
+                }
+            }
+        });
+    tokio::pin!(reconciliations, shutdown);
+    tokio::select! {
+        _ = &mut reconciliations => return,
+        _ = &mut shutdown => {}
+    }
+
+    // The controllers keep being driven until the reconciliations in progress finished, they are
+    // dropped along with the watches afterwards.
+    let state = context.get_ref();
+    state.draining.store(true, Ordering::SeqCst);
+    tracing::info!(
+        in_progress = state.in_progress.load(Ordering::SeqCst),
+        "Shutting down, waiting for reconciliations in progress"
+    );
+    let drained = async {
+        while state.in_progress.load(Ordering::SeqCst) > 0 {
+            tokio::time::sleep(DRAIN_INTERVAL).await;
+        }
+    };
+    let drain = async {
+        tokio::select! {
+            _ = &mut reconciliations => {}
+            _ = drained => {}
+        }
+    };
+    match tokio::time::timeout(drain_timeout, drain).await {
+        Ok(()) => tracing::info!("Reconciliations in progress finished"),
+        Err(_) => tracing::warn!(
+            in_progress = state.in_progress.load(Ordering::SeqCst),
+            "Reconciliations in progress did not finish in time, abandoning them"
+        ),
+    }
+}
+
+/// Context injected with each `reconcile` and `on_error` method invocation.
+///
+/// Note: All requests of a reconciliation go through the client, which may be built with
+/// `Client::new` on any `tower::Service`, e.g., a mocked API server.
+pub struct ContextData {
+    /// Kubernetes client to make Kubernetes API requests with. Required for K8S resource management.
+    client: Client,
+    /// Publishes Kubernetes events regarding reconciled `FoxService` resources.
+    recorder: Recorder,
+    /// Counts and times the reconciliations.
+    metrics: Metrics,
+    /// Whether the operator is shutting down, no reconciliation is started then.
+    draining: AtomicBool,
+    /// Number of reconciliations in progress, waited for on shutdown.
+    in_progress: AtomicUsize,
+    /// Consecutive failed reconciliations by namespace and name of the `FoxService`, reset on
+    /// success.
+    failures: Mutex<HashMap<(String, String), u32>>,
+    /// Delay before checking again on a successfully reconciled `FoxService`, unless overridden by
+    /// its `fox_service::REQUEUE_INTERVAL_ANNOTATION`.
+    requeue_interval: Duration,
+}
+
+impl ContextData {
+    /// Constructs a new instance of ContextData.
+    ///
+    /// # Arguments:
+    /// - `client`: A Kubernetes client to make Kubernetes REST API requests with. Resources
+    ///   will be created and deleted with this client.
+    pub fn new(client: Client) -> Self {
+        ContextData::with_recorder(client.clone(), Recorder::new(client))
+    }
+
+    /// Constructs a new instance of ContextData publishing events with given recorder.
+    ///
+    /// # Arguments:
+    /// - `client`: A Kubernetes client to make Kubernetes REST API requests with.
+    /// - `recorder`: Publishes Kubernetes events regarding reconciled `FoxService` resources.
+    pub fn with_recorder(client: Client, recorder: Recorder) -> Self {
+        ContextData {
+            client,
+            recorder,
+            metrics: Metrics::new(),
+            draining: AtomicBool::new(false),
+            in_progress: AtomicUsize::new(0),
+            failures: Mutex::new(HashMap::new()),
+            requeue_interval: DEFAULT_REQUEUE_INTERVAL,
+        }
+    }
+
+    /// Checks on successfully reconciled `FoxService` resources again after given interval, rather
+    /// than `DEFAULT_REQUEUE_INTERVAL`. Failed reconciliations are retried after `backoff`.
+    ///
+    /// # Arguments:
+    /// - `requeue_interval`: Delay before checking again, a `FoxService` may override it with
+    ///   `fox_service::REQUEUE_INTERVAL_ANNOTATION`.
+    pub fn with_requeue_interval(mut self, requeue_interval: Duration) -> Self {
+        self.requeue_interval = requeue_interval;
+        self
+    }
+
+    /// Metrics updated by the reconciliations, e.g., to serve them with `metrics::serve`.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+}
+
+/// Action to be taken upon an `FoxService` resource during reconciliation
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Action {
+    /// Create the subresources, this includes spawning `n` pods with FoxService service
+    Create,
+    /// Patch the existing subresources, as they no longer match the `FoxService` specification
+    Update,
+    /// Delete all subresources created in the `Create` phase
+    Delete,
+    /// This `FoxService` resource is in desired state and requires no actions to be taken
+    NoOp,
+    /// Leave the subresources untouched, reconciliation is paused by an annotation
+    Pause,
+}
+
+/// Brings the subresources of a `FoxService` resource in line with its specification, as decided
+/// by `determine_action`, and reports the outcome in its status.
+///
+/// # Arguments
+/// - `fox_svc`: The `FoxService` resource to reconcile.
+/// - `context`: Shared state of the controller, see `ContextData`.
+///
+/// Returns when to check on the resource again.
+///
+/// Note: Runs in an `info` span carrying the name, namespace and generation of the resource, the
+/// requests made to the Kubernetes API are logged within it at the `debug` level.
+#[tracing::instrument(
+    name = "reconcile",
+    skip(fox_svc, context),
+    fields(
+        name = %fox_svc.name(),
+        namespace = fox_svc.namespace().as_deref().unwrap_or_default(),
+        generation = fox_svc.meta().generation.unwrap_or_default(),
+    )
+)]
+pub async fn reconcile(
+    fox_svc: FoxService,
+    context: Context<ContextData>,
+) -> Result<ReconcilerAction, Failure> {
+    let state = context.get_ref();
+    // The resource is reconciled by the next instance of the operator instead.
+    if state.draining.load(Ordering::SeqCst) {
+        return Ok(ReconcilerAction {
+            requeue_after: None,
+        });
+    }
+    let started = Instant::now();
+    let namespace = fox_svc.namespace().unwrap_or_default();
+    let key = (namespace.clone(), fox_svc.name());
+    let mut action: Option<Action> = None;
+    state.in_progress.fetch_add(1, Ordering::SeqCst);
+    let result = reconcile_resource(fox_svc, state, &mut action).await;
+    state.in_progress.fetch_sub(1, Ordering::SeqCst);
+    state
+        .metrics
+        .reconciled(&namespace, action, result.as_ref().err(), started.elapsed());
+
+    // Consecutive failures are counted to back off further on each, see `on_error`.
+    let mut failures = state
+        .failures
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match result {
+        Ok(reconciler_action) => {
+            failures.remove(&key);
+            Ok(reconciler_action)
+        }
+        Err(error) => {
+            let attempts = failures.entry(key).or_insert(0);
+            *attempts += 1;
+            Err(Failure {
+                error,
+                attempts: *attempts,
+            })
+        }
+    }
+}
+
+/// Reconciles a `FoxService` resource, see `reconcile`.
+///
+/// # Arguments
+/// - `fox_svc`: The `FoxService` resource to reconcile.
+/// - `context`: Shared state of the controller, see `ContextData`.
+/// - `taken`: Set to the action taken once decided, for the metrics.
+async fn reconcile_resource(
+    fox_svc: FoxService,
+    context: &ContextData,
+    taken: &mut Option<Action>,
+) -> Result<ReconcilerAction, Error> {
+    let client: Client = context.client.clone(); // The `Client` is shared -> a clone from the reference is obtained
+    let recorder: &Recorder = &context.recorder;
+
+    // The resource of `FoxService` kind is required to have a namespace set. However, it is not guaranteed
+    // the resource will have a `namespace` set. Therefore, the `namespace` field on object's metadata
+    // is optional and Rust forces the programmer to check for it's existence first.
+    let namespace: String = match fox_svc.namespace() {
+        None => {
+            // If there is no namespace to deploy to defined, reconciliation ends with an error immediately.
+            return Err(Error::UserInputError(
+                "Expected FoxService resource to be namespaced. Can't deploy to an unknown namespace."
+                    .to_owned(),
+            ));
+        }
+        // If namespace is known, proceed. In a more advanced version of the operator, perhaps
+        // the namespace could be checked for existence first.
+        Some(namespace) => namespace,
+    };
+
+    // The workload currently present in the cluster, if any. It is compared against the desired
+    // state to detect changes made to the `FoxService` specification.
+    let workload: Option<Workload> =
+        fox_service::workload::get_workload(client.clone(), &fox_svc, &namespace).await?;
+
+    // The service currently present in the cluster, if any. Addresses of its load balancer are
+    // reported in the status.
+    let service: Option<Service> = if fox_service::service::is_exposed(&fox_svc.spec) {
+        fox_service::service::get_service(client.clone(), &fox_svc.spec.name, &namespace).await?
+    } else {
+        None
+    };
+
+    // The ingress currently present in the cluster, if any. Its addresses are reported in the
+    // status as well.
+    let ingress: Option<Ingress> = if fox_service::ingress::is_routed(&fox_svc.spec) {
+        fox_service::ingress::get_ingress(client.clone(), &fox_svc.spec.name, &namespace).await?
+    } else {
+        None
+    };
+
+    // The certificate requested for the ingress, if any. Whether it was issued is reported in the
+    // status.
+    let certificate: Option<DynamicObject> = if fox_service::certificate::issuer_ref(&fox_svc.spec)
+        .is_some()
+    {
+        fox_service::certificate::get_certificate(client.clone(), &fox_svc.spec.name, &namespace)
+            .await?
+    } else {
+        None
+    };
+
+    // Performs action as decided by the `determine_action` function. The outcome of creating or
+    // updating the subresources is reported in the status of the `FoxService` resource first.
+    let action = determine_action(&fox_svc, workload.as_ref());
+    *taken = Some(action);
+    // The specification is checked before anything is applied, a resource being deleted is cleaned
+    // up regardless.
+    let problems = fox_svc.spec.validate();
+    let invalid = !problems.is_empty() && !matches!(action, Action::Delete | Action::Pause);
+    // Unknown fields are most likely misspelled ones, which would be ignored silently otherwise.
+    // Reported once per generation, rather than on every pass.
+    let observed = fox_svc
+        .status
+        .as_ref()
+        .and_then(|status| status.observed_generation);
+    let unknown_fields = if observed != fox_svc.meta().generation {
+        fox_svc.spec.unknown_field_paths()
+    } else {
+        Vec::new()
+    };
+    if !unknown_fields.is_empty() {
+        let fields: Vec<String> = unknown_fields
+            .iter()
+            .map(|field| format!("spec.{}", field))
+            .collect();
+        let note = format!("{} not recognized, ignored", fields.join(", "));
+        recorder
+            .publish(
+                &fox_svc,
+                EventType::Warning,
+                "UnknownFields",
+                "Validate",
+                Some(note),
+            )
+            .await;
+    }
+    let outcome: Result<(), Error> = match action {
+        _ if invalid => {
+            let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+            let note = problems.join("; ");
+            recorder
+                .publish(
+                    &fox_svc,
+                    EventType::Warning,
+                    "InvalidSpec",
+                    "Validate",
+                    Some(note.clone()),
+                )
+                .await;
+            Err(Error::InvalidSpec(note))
+        }
+        Action::Create => create(client.clone(), recorder, &fox_svc, &namespace).await,
+        Action::Update => {
+            update(
+                client.clone(),
+                recorder,
+                &fox_svc,
+                &namespace,
+                workload.as_ref(),
+            )
+            .await
+        }
+        Action::Delete => {
+            delete(client, recorder, &fox_svc, &namespace).await?;
+            return Ok(ReconcilerAction {
+                requeue_after: None, // Makes no sense to delete after a successful delete, as the resource is gone
+            });
+        }
+        Action::NoOp => converge(client.clone(), recorder, &fox_svc, &namespace).await,
+        Action::Pause => Ok(()),
+    };
+
+    // Subresources modified in this pass can't be rolled out yet, the status reflects that.
+    let changed = !matches!(action, Action::NoOp | Action::Pause);
+    let status = status::build_status(
+        &fox_svc,
+        workload.as_ref(),
+        service.as_ref(),
+        ingress.as_ref(),
+        certificate.as_ref(),
+        changed,
+        &outcome,
+    );
+    status::update(client, &fox_svc, status).await?;
+
+    // Retrying doesn't help until the missing Secret or ConfigMap is created, the checks are
+    // spaced out the longer it is missing.
+    if let Err(Error::MissingReference(_)) = outcome {
+        return Ok(ReconcilerAction {
+            requeue_after: Some(status::degraded_backoff(&fox_svc)),
+        });
+    }
+    // Only a change of the specification fixes it, which triggers a reconciliation by itself.
+    if let Err(Error::InvalidSpec(_)) = outcome {
+        return Ok(ReconcilerAction {
+            requeue_after: None,
+        });
+    }
+    // Removing the annotation triggers a reconciliation by itself, this is merely a safety net.
+    if let Action::Pause = action {
+        return Ok(ReconcilerAction {
+            requeue_after: Some(PAUSED_REQUEUE),
+        });
+    }
+    outcome?;
+    // Re-check later, either to verify changes made converged or to detect new ones.
+    let requeue_interval = match fox_service::requeue_interval(&fox_svc) {
+        Ok(interval) => interval.unwrap_or(context.requeue_interval),
+        Err(message) => {
+            tracing::warn!(%message, "Ignoring the requeue interval annotation");
+            context.requeue_interval
+        }
+    };
+    Ok(ReconcilerAction {
+        requeue_after: Some(requeue_interval),
+    })
+}
+
+/// Creates the subresources of a `FoxService` resource.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to create the subresources with.
+/// - `recorder`: Publishes events about created subresources and failures.
+/// - `fox_svc`: The `FoxService` resource to create the subresources for.
+/// - `namespace`: Namespace of the `FoxService` resource.
+async fn create(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    // Creates a workload with `n` FoxService service pods, but applies a finalizer first.
+    // Finalizer is applied first, as the operator might be shut down and restarted
+    // at any time, leaving subresources in intermediate state. This prevents leaks on
+    // the `FoxService` resource deletion.
+    let name = fox_svc.name(); // Name of the FoxService resource is used to name the subresources as well.
+
+    // Verify the references of the specification and apply the finalizer first. If that fails,
+    // the `?` operator invokes automatic conversion of `kube::Error` to the `Error` defined in
+    // this crate. Each failure is published as a warning event as well.
+    let verified = validation::verify_references(client.clone(), &fox_svc.spec, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "MissingReference", "Validate", verified)
+        .await?;
+    check_references(client.clone(), recorder, fox_svc, namespace).await?;
+    publish_warnings(recorder, fox_svc).await;
+    let added = finalizer::add(client.clone(), &name, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "FinalizerFailed", "AddFinalizer", added)
+        .await?;
+    // The workload is created once its pre-deploy hooks completed, a later pass checks on them.
+    if fox_service::workload::is_required(&fox_svc.spec)
+        && !pre_deploy(client.clone(), recorder, fox_svc, namespace, None, "Deploy").await?
+    {
+        return Ok(());
+    }
+    // Invoke creation of the workload running the fox service pods, a deployment by default.
+    let deployed = fox_service::deploy(client.clone(), fox_svc, namespace)
+        .await
+        .map_err(not_managed);
+    let workload = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", "Deploy", deployed)
+        .await?;
+
+    // A `FoxService` running only batch containers has no workload.
+    if let Some(workload) = workload {
+        let reason = format!("{:?}Created", workload.kind());
+        let note = format!("Created {}", workload.describe());
+        recorder
+            .publish(fox_svc, EventType::Normal, &reason, "Deploy", Some(note))
+            .await;
+    }
+    expose(client, recorder, fox_svc, namespace, "Deploy").await
+}
+
+/// Updates the subresources of a `FoxService` resource whose specification was changed since they
+/// were created or last updated.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to update the subresources with.
+/// - `recorder`: Publishes events about updated subresources and failures.
+/// - `fox_svc`: The `FoxService` resource to update the subresources of.
+/// - `namespace`: Namespace of the `FoxService` resource.
+/// - `workload`: The workload currently present in the cluster.
+async fn update(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+    workload: Option<&Workload>,
+) -> Result<(), Error> {
+    let verified = validation::verify_references(client.clone(), &fox_svc.spec, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "MissingReference", "Validate", verified)
+        .await?;
+    check_references(client.clone(), recorder, fox_svc, namespace).await?;
+    publish_warnings(recorder, fox_svc).await;
+    // Newly added persistent volumes need their claims before the pods can mount them, a newly
+    // added service account has to exist before the pods can run as it.
+    let applied = fox_service::apply_dependencies(client.clone(), fox_svc, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "DeployFailed", "Update", applied)
+        .await?;
+    let live = workload.map(Workload::metadata);
+    if !pre_deploy(client.clone(), recorder, fox_svc, namespace, live, "Update").await? {
+        return Ok(());
+    }
+    // Patch the workload to match the specification again.
+    let updated = fox_service::workload::update_workload(client.clone(), fox_svc, namespace).await;
+    let workload = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", "Update", updated)
+        .await?;
+
+    let reason = format!("{:?}Updated", workload.kind());
+    let note = format!("Updated {}", workload.describe());
+    recorder
+        .publish(fox_svc, EventType::Normal, &reason, "Update", Some(note))
+        .await;
+    expose(client, recorder, fox_svc, namespace, "Update").await
+}
+
+/// Brings the subresources of a `FoxService` resource other than the workload in line with the
+/// specification, while the workload itself is in the desired state already. Persistent volume
+/// claims may have to be resized, the service may have to be patched, for instance.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to manage the subresources with.
+/// - `recorder`: Publishes events about created subresources and failures.
+/// - `fox_svc`: The `FoxService` resource the subresources belong to.
+/// - `namespace`: Namespace of the `FoxService` resource.
+async fn converge(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let applied = fox_service::apply_dependencies(client.clone(), fox_svc, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "DeployFailed", "Update", applied)
+        .await?;
+    // The workload is left behind once all containers are marked as `batch`.
+    if !fox_service::workload::is_required(&fox_svc.spec) {
+        let deleted =
+            fox_service::workload::delete_previous_workloads(client.clone(), fox_svc, namespace)
+                .await;
+        recorder
+            .warn_if_failed(fox_svc, "DeployFailed", "Update", deleted)
+            .await?;
+    }
+    expose(client, recorder, fox_svc, namespace, "Update").await
+}
+
+/// Runs the pre-deploy hooks of a `FoxService` resource for its current generation, unless the
+/// live workload records they completed already. The hooks may rely on the persistent volume
+/// claims and the service account, these are applied first.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to manage the hook Jobs with.
+/// - `recorder`: Publishes events about started and failed hooks.
+/// - `fox_svc`: The `FoxService` resource owning the hooks.
+/// - `namespace`: Namespace of the `FoxService` resource.
+/// - `workload`: Metadata of the live workload, if any.
+/// - `action`: What the operator is doing, `Deploy` or `Update`.
+///
+/// Returns whether the workload may be rolled out. A hook still running is checked on again by
+/// the next reconciliation, a failed hook fails the reconciliation.
+async fn pre_deploy(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+    workload: Option<&ObjectMeta>,
+    action: &str,
+) -> Result<bool, Error> {
+    // A suspended service runs no pods, its hooks wait for it to be resumed.
+    if fox_service::workload::is_suspended(&fox_svc.spec)
+        || fox_service::hook::is_done(fox_svc, workload)
+    {
+        return Ok(true);
+    }
+    let applied = fox_service::apply_dependencies(client.clone(), fox_svc, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    let ran = fox_service::hook::run_pre_deploy_hooks(client, fox_svc, namespace).await;
+    let state = recorder
+        .warn_if_failed(fox_svc, "PreDeployHookFailed", action, ran)
+        .await?;
+    match state {
+        HookState::Completed => Ok(true),
+        HookState::Started(job) => {
+            let note = format!("Started job {}", job);
+            recorder
+                .publish(
+                    fox_svc,
+                    EventType::Normal,
+                    "PreDeployHookStarted",
+                    action,
+                    Some(note),
+                )
+                .await;
+            Ok(false)
+        }
+        HookState::Running => Ok(false),
+        HookState::Failed(job) => {
+            let failed = Err(Error::HookFailed(format!("job {} failed", job)));
+            recorder
+                .warn_if_failed(fox_svc, "PreDeployHookFailed", action, failed)
+                .await
+        }
+    }
+}
+
+/// Creates or patches the service, the ingress, its certificate, the HTTP route, the service
+/// monitor and the vertical pod autoscaler of a `FoxService` resource, publishing an event for
+/// each of them created.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to manage the subresources with.
+/// - `recorder`: Publishes events about the created subresources and failures.
+/// - `fox_svc`: The `FoxService` resource owning the subresources.
+/// - `namespace`: Namespace of the `FoxService` resource.
+/// - `action`: What the operator is doing, `Deploy` or `Update`.
+async fn expose(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+    action: &str,
+) -> Result<(), Error> {
+    let applied = fox_service::service::apply_service(client.clone(), fox_svc, namespace)
+        .await
+        .map_err(not_managed);
+    let created = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    if let Some(service) = created {
+        let note = format!("Created service {}", service.name());
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Normal,
+                "ServiceCreated",
+                action,
+                Some(note),
+            )
+            .await;
+    }
+
+    let applied = fox_service::ingress::apply_ingress(client.clone(), fox_svc, namespace).await;
+    let created = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    if let Some(ingress) = created {
+        let note = format!("Created ingress {}", ingress.name());
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Normal,
+                "IngressCreated",
+                action,
+                Some(note),
+            )
+            .await;
+    }
+
+    let applied =
+        fox_service::certificate::apply_certificate(client.clone(), fox_svc, namespace).await;
+    let applied = applied.map_err(|error| match error {
+        kube::Error::Api(response) if response.code == 404 => Error::UserInputError(
+            "ingress.issuerRef: cert-manager is not installed, the Certificate CRD is missing"
+                .to_owned(),
+        ),
+        error => error.into(),
+    });
+    let created = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    if let Some(certificate) = created {
+        let note = format!("Created certificate {}", certificate.name());
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Normal,
+                "CertificateCreated",
+                action,
+                Some(note),
+            )
+            .await;
+    }
+
+    let applied =
+        fox_service::http_route::apply_http_route(client.clone(), fox_svc, namespace).await;
+    let created = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    if let Some(route) = created {
+        let note = format!("Created HTTP route {}", route.name());
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Normal,
+                "HTTPRouteCreated",
+                action,
+                Some(note),
+            )
+            .await;
+    }
+
+    // The Prometheus operator is optional, a cluster without it merely isn't scraped.
+    let applied =
+        fox_service::service_monitor::apply_service_monitor(client.clone(), fox_svc, namespace)
+            .await;
+    let applied = match applied {
+        Err(kube::Error::Api(response)) if response.code == 404 => {
+            let note = "ServiceMonitor CRD is not installed, metrics are not scraped".to_string();
+            recorder
+                .publish(
+                    fox_svc,
+                    EventType::Warning,
+                    "ServiceMonitorUnavailable",
+                    action,
+                    Some(note),
+                )
+                .await;
+            Ok(None)
+        }
+        applied => applied,
+    };
+    let created = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    if let Some(monitor) = created {
+        let note = format!("Created service monitor {}", monitor.name());
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Normal,
+                "ServiceMonitorCreated",
+                action,
+                Some(note),
+            )
+            .await;
+    }
+
+    // The VerticalPodAutoscaler is optional as well, without it the requests are left as they are.
+    let applied = fox_service::vertical_pod_autoscaler::apply_vertical_pod_autoscaler(
+        client, fox_svc, namespace,
+    )
+    .await;
+    let applied = match applied {
+        Err(kube::Error::Api(response)) if response.code == 404 => {
+            let note =
+                "VerticalPodAutoscaler CRD is not installed, resource requests are not adjusted"
+                    .to_string();
+            recorder
+                .publish(
+                    fox_svc,
+                    EventType::Warning,
+                    "VerticalPodAutoscalerUnavailable",
+                    action,
+                    Some(note),
+                )
+                .await;
+            Ok(None)
+        }
+        applied => applied,
+    };
+    let created = recorder
+        .warn_if_failed(fox_svc, "DeployFailed", action, applied)
+        .await?;
+    if let Some(autoscaler) = created {
+        let note = format!("Created vertical pod autoscaler {}", autoscaler.name());
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Normal,
+                "VerticalPodAutoscalerCreated",
+                action,
+                Some(note),
+            )
+            .await;
+    }
+    Ok(())
+}
+
+/// Publishes a warning event for each image pull secret of a `FoxService` resource that is missing
+/// or doesn't hold registry credentials, and for a missing PriorityClass. The reconciliation
+/// carries on regardless, as the referenced objects may still be created.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to look up the referenced objects with.
+/// - `recorder`: Publishes the warning events.
+/// - `fox_svc`: The `FoxService` resource to check the references of.
+/// - `namespace`: Namespace of the `FoxService` resource.
+async fn check_references(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    let secrets =
+        validation::check_image_pull_secrets(client.clone(), &fox_svc.spec, namespace).await?;
+    let secrets = secrets
+        .into_iter()
+        .map(|problem| ("InvalidImagePullSecret", problem));
+    let priority_class = validation::check_priority_class(client.clone(), &fox_svc.spec).await?;
+    let priority_class = priority_class
+        .into_iter()
+        .map(|problem| ("MissingPriorityClass", problem));
+    let tls_secrets = validation::check_tls_secrets(client, &fox_svc.spec, namespace).await?;
+    let tls_secrets = tls_secrets
+        .into_iter()
+        .map(|problem| ("MissingTlsSecret", problem));
+    for (reason, problem) in secrets.chain(priority_class).chain(tls_secrets) {
+        recorder
+            .publish(
+                fox_svc,
+                EventType::Warning,
+                reason,
+                "Validate",
+                Some(problem),
+            )
+            .await;
+    }
+    Ok(())
+}
+
+/// Publishes a warning event for each setting of a `FoxService` resource that is valid but
+/// risky, e.g., privileged containers, so they are auditable.
+///
+/// # Arguments
+/// - `recorder`: Publishes the warning events.
+/// - `fox_svc`: The `FoxService` resource to check the specification of.
+async fn publish_warnings(recorder: &Recorder, fox_svc: &FoxService) {
+    for (reason, note) in validation::warnings(&fox_svc.spec) {
+        recorder
+            .publish(fox_svc, EventType::Warning, reason, "Validate", Some(note))
+            .await;
+    }
+}
+
+/// Deletes any subresources related to a `FoxService` resource. If and only if all subresources
+/// are deleted, the finalizer is removed and Kubernetes is free to remove the `FoxService` resource.
+///
+/// # Arguments
+/// - `client`: Kubernetes client to delete the subresources with.
+/// - `recorder`: Publishes events about deleted subresources and failures.
+/// - `fox_svc`: The `FoxService` resource to delete the subresources of.
+/// - `namespace`: Namespace of the `FoxService` resource.
+async fn delete(
+    client: Client,
+    recorder: &Recorder,
+    fox_svc: &FoxService,
+    namespace: &str,
+) -> Result<(), Error> {
+    //First, delete the subresources. If there is any error deleting them, it is
+    // automatically converted into `Error` defined in this crate and the reconciliation is ended
+    // with that error. Subresources that don't exist, e.g., because they were deleted by hand or
+    // never created, count as deleted, so the finalizer is removed regardless.
+    let deleted = fox_service::delete(client.clone(), &fox_svc.spec, namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "DeleteFailed", "Delete", deleted)
+        .await?;
+    recorder
+        .publish(
+            fox_svc,
+            EventType::Normal,
+            "ResourcesDeleted",
+            "Delete",
+            None,
+        )
+        .await;
+
+    // Once the subresources are successfully removed, remove the finalizer to make it possible
+    // for Kubernetes to delete the `FoxService` resource.
+    let removed = finalizer::delete(client, &fox_svc.name(), namespace).await;
+    recorder
+        .warn_if_failed(fox_svc, "FinalizerFailed", "RemoveFinalizer", removed)
+        .await?;
+    Ok(())
+}
+
+/// Resources arrives into reconciliation queue in a certain state. This function looks at
+/// the state of given `FoxService` resource and decides which actions needs to be performed.
+/// The finite set of possible actions is represented by the `Action` enum.
+///
+/// # Arguments
+/// - `fox_svc`: A reference to `FoxService` being reconciled to decide next action upon.
+/// - `workload`: The workload belonging to `fox_svc` as currently present in the cluster, if any.
+pub fn determine_action(fox_svc: &FoxService, workload: Option<&Workload>) -> Action {
+    if fox_svc.meta().deletion_timestamp.is_some() {
+        Action::Delete
+    } else if fox_service::is_paused(fox_svc) {
+        // Checked after deletion, so a paused resource being deleted doesn't keep its finalizer.
+        Action::Pause
+    } else if !finalizer::is_present(fox_svc) {
+        // Finalizers of other controllers don't matter, only ours marks the resource as created.
+        Action::Create
+    } else {
+        match workload {
+            // Batch containers are run by a CronJob, which is not a workload.
+            None if !fox_service::workload::is_required(&fox_svc.spec) => Action::NoOp,
+            // The workload was removed by someone else or the workload kind was switched, it has
+            // to be created again.
+            None => Action::Create,
+            Some(workload) if is_outdated(fox_svc, workload) => Action::Update,
+            Some(_) => Action::NoOp,
+        }
+    }
+}
+
+/// Checks whether the workload no longer reflects the specification of given `FoxService`.
+fn is_outdated(fox_svc: &FoxService, workload: &Workload) -> bool {
+    let namespace = fox_svc.namespace().unwrap_or_default();
+    fox_service::workload::needs_update(fox_svc, workload, &namespace)
+}
+
+/// Actions to be taken when a reconciliation fails - for whatever reason.
+/// Logs the error with its chain of causes and requeues the resource for another reconciliation
+/// after a delay depending on the error, see `backoff`.
+///
+/// # Arguments
+/// - `failure`: The error that occurred during reconciliation, with the number of consecutive
+///   failures of the resource.
+/// - `_context`: Unused argument. Context Data "injected" automatically by kube-rs.
+pub fn on_error(failure: &Failure, _context: Context<ContextData>) -> ReconcilerAction {
+    let error = &failure.error;
+    let mut causes: Vec<String> = Vec::new();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    let delay = backoff(error, failure.attempts);
+    tracing::error!(
+        error = %error,
+        causes = ?causes,
+        attempts = failure.attempts,
+        retry_in = ?delay,
+        "Reconciliation failed"
+    );
+    ReconcilerAction {
+        requeue_after: Some(delay),
+    }
+}
+
+/// Delay before retrying a failed reconciliation.
+///
+/// # Arguments
+/// - `error`: The error the reconciliation failed with. Mistakes in the `FoxService` are only
+///   fixed by the user, the retries back off up to `MAX_USER_ERROR_BACKOFF`. Conflicts and
+///   throttling by the API server are transient, they are retried quickly, with jitter so the
+///   retries of many resources are spread out. Other errors back off up to `MAX_ERROR_BACKOFF`.
+/// - `attempts`: Number of consecutive failures of the resource, starting at 1.
+pub fn backoff(error: &Error, attempts: u32) -> Duration {
+    let exponential = |base: Duration, max: Duration| {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        base.checked_mul(factor).map_or(max, |delay| delay.min(max))
+    };
+    match error {
+        Error::UserInputError(_) | Error::InvalidSpec(_) | Error::NotManaged(_) => {
+            exponential(USER_ERROR_BACKOFF, MAX_USER_ERROR_BACKOFF)
+        }
+        Error::KubeError {
+            source: kube::Error::Api(response),
+        } if response.code == 409 || response.code == 429 => {
+            // The sub-second part of the clock is random enough to spread out retries.
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |now| now.subsec_nanos());
+            let jitter = CONFLICT_JITTER.mul_f64(f64::from(nanos) / 1e9);
+            exponential(CONFLICT_BACKOFF, MAX_CONFLICT_BACKOFF) + jitter
+        }
+        _ => exponential(ERROR_BACKOFF, MAX_ERROR_BACKOFF),
+    }
+}
+
+/// Converts an error creating a subresource, telling a subresource of the same name not adopted
+/// by `fox_service::is_managed` apart from other errors.
+///
+/// # Arguments
+/// - `error` - Error the creation failed with
+fn not_managed(error: kube::Error) -> Error {
+    match &error {
+        kube::Error::Api(response) if fox_service::already_exists(&error) => {
+            Error::NotManaged(response.message.clone())
+        }
+        _ => Error::from(error),
+    }
+}
+
+/// A failed reconciliation, as handed to `on_error`.
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct Failure {
+    /// The error the reconciliation failed with
+    pub error: Error,
+    /// Number of consecutive failures of the resource, including this one
+    pub attempts: u32,
+}
+
+/// All errors possible to occur during reconciliation
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Any error originating from the `kube-rs` crate
+    #[error("Kubernetes reported error: {source}")]
+    KubeError {
+        #[from]
+        source: kube::Error,
+    },
+    /// Error in user input or FoxService resource definition, typically missing fields.
+    #[error("Invalid FoxService CRD: {0}")]
+    UserInputError(String),
+    /// The FoxService specification has problems found by `FoxServiceSpec::validate`, listed in
+    /// the message.
+    #[error("Invalid FoxService specification: {0}")]
+    InvalidSpec(String),
+    /// A Secret or ConfigMap referenced by the FoxService doesn't exist (yet).
+    #[error("Missing reference: {0}")]
+    MissingReference(String),
+    /// A pre-deploy hook of the FoxService failed, the workload is not rolled out.
+    #[error("Pre-deploy hook failed: {0}")]
+    HookFailed(String),
+    /// A subresource to be created exists already and doesn't belong to the FoxService, see
+    /// `fox_service::is_managed`. It is left alone until it is removed or the FoxService renamed.
+    #[error("Subresource not managed by the FoxService: {0}")]
+    NotManaged(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{Method, StatusCode};
+    use mock::fox_service;
+    use serde_json::{json, Value};
+
+    /// Answers like an API server without any objects: reads are not found, applied objects are
+    /// returned as applied and created events are returned as created.
+    fn respond(
+        fox_svc: &FoxService,
+        method: &Method,
+        path: &str,
+        body: &Value,
+    ) -> (StatusCode, Value) {
+        match *method {
+            // Lists are requested with a query, e.g., a label selector.
+            Method::GET if path.contains('?') => mock::list(),
+            Method::GET | Method::DELETE => mock::status(404, "NotFound"),
+            Method::PATCH if path.contains("/foxservices/") => {
+                (StatusCode::OK, serde_json::to_value(fox_svc).unwrap())
+            }
+            Method::PATCH => (StatusCode::OK, body.clone()),
+            _ => (StatusCode::CREATED, body.clone()),
+        }
+    }
+
+    /// Context of a reconciliation against the mocked API server answering with `respond`.
+    fn context<F>(respond: F) -> (Context<ContextData>, mock::Requests)
+    where
+        F: Fn(&Method, &str, &Value) -> (StatusCode, Value) + Send + Sync + 'static,
+    {
+        let (client, requests) = mock::client(respond);
+        let recorder = Recorder::with_reporting_instance(client.clone(), "fox-operator-test");
+        (
+            Context::new(ContextData::with_recorder(client, recorder)),
+            requests,
+        )
+    }
+
+    /// A `FoxService` being deleted, still carrying the finalizer of the operator.
+    fn deleted_fox_service() -> FoxService {
+        let mut fox_svc = fox_service();
+        fox_svc.meta_mut().resource_version = Some("7".to_string());
+        fox_svc.meta_mut().finalizers = Some(vec![finalizer::FINALIZER.to_string()]);
+        let now = k8s_openapi::chrono::Utc::now();
+        fox_svc.meta_mut().deletion_timestamp =
+            Some(k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(now));
+        fox_svc
+    }
+
+    /// Reasons of the events published, in order.
+    fn event_reasons(requests: &mock::Requests) -> Vec<String> {
+        requests
+            .bodies(
+                Method::POST,
+                "/apis/events.k8s.io/v1/namespaces/default/events",
+            )
+            .iter()
+            .map(|event| event["reason"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn determine_action_ignores_foreign_finalizers() {
+        let mut fox_svc = fox_service();
+        let deployment = fox_service::deployment::build_deployment(&fox_svc, "default");
+        let workload = Workload::Deployment(deployment);
+
+        fox_svc.meta_mut().finalizers = Some(vec!["other.example".to_string()]);
+        assert_eq!(determine_action(&fox_svc, Some(&workload)), Action::Create);
+
+        let finalizers = vec![
+            "other.example".to_string(),
+            finalizer::FINALIZER.to_string(),
+        ];
+        fox_svc.meta_mut().finalizers = Some(finalizers);
+        assert_eq!(determine_action(&fox_svc, Some(&workload)), Action::NoOp);
+    }
+
+    #[tokio::test]
+    async fn reconcile_creates_finalizer_then_deployment_then_status() {
+        let fox_svc = fox_service();
+        let answered = fox_svc.clone();
+        let (context, requests) =
+            context(move |method, path, body| respond(&answered, method, path, body));
+
+        let action = reconcile(fox_svc, context).await.unwrap();
+
+        assert_eq!(action.requeue_after, Some(DEFAULT_REQUEUE_INTERVAL));
+        let apply = "?&force=true&fieldManager=fox-operator";
+        let foxservices = "/apis/cbopt.com/v1/namespaces/default/foxservices/web";
+        let deployments = "/apis/apps/v1/namespaces/default/deployments/web";
+        let expected = vec![
+            format!("GET {}", deployments),
+            format!("PATCH {}{}", foxservices, apply),
+            "GET /api/v1/namespaces/default/configmaps/web-config".to_string(),
+            "GET /api/v1/namespaces/default/services/web-headless".to_string(),
+            "GET /apis/networking.k8s.io/v1/namespaces/default/networkpolicies/web".to_string(),
+            "GET /apis/autoscaling/v2beta2/namespaces/default/horizontalpodautoscalers/web"
+                .to_string(),
+            "GET /apis/policy/v1beta1/namespaces/default/poddisruptionbudgets/web".to_string(),
+            "GET /apis/batch/v1beta1/namespaces/default/cronjobs/web".to_string(),
+            "GET /apis/apps/v1/namespaces/default/statefulsets/web".to_string(),
+            "GET /apis/apps/v1/namespaces/default/daemonsets/web".to_string(),
+            format!("GET {}", deployments),
+            format!("PATCH {}{}", deployments, apply),
+            "POST /apis/events.k8s.io/v1/namespaces/default/events?".to_string(),
+            "GET /api/v1/namespaces/default/services/web".to_string(),
+            "GET /apis/networking.k8s.io/v1/namespaces/default/ingresses/web".to_string(),
+            "GET /apis/cert-manager.io/v1/namespaces/default/certificates/web".to_string(),
+            "GET /apis/gateway.networking.k8s.io/v1/namespaces/default/httproutes/web".to_string(),
+            "GET /apis/monitoring.coreos.com/v1/namespaces/default/servicemonitors/web".to_string(),
+            "GET /apis/autoscaling.k8s.io/v1/namespaces/default/verticalpodautoscalers/web"
+                .to_string(),
+            format!("PATCH {}/status{}", foxservices, apply),
+        ];
+        assert_eq!(requests.lines(), expected);
+
+        let finalizer = &requests.bodies(Method::PATCH, foxservices)[0];
+        assert_eq!(
+            finalizer,
+            &json!({
+                "apiVersion": "cbopt.com/v1",
+                "kind": "FoxService",
+                "metadata": { "name": "web", "finalizers": [finalizer::FINALIZER] }
+            })
+        );
+        let deployment = &requests.bodies(Method::PATCH, deployments)[0];
+        assert_eq!(deployment["kind"], "Deployment");
+        assert_eq!(
+            deployment["metadata"]["ownerReferences"][0]["uid"],
+            "uid-web"
+        );
+        let container = &deployment["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(container["image"], "nginx:1.21");
+        assert_eq!(container["ports"][0]["containerPort"], 80);
+        let status = &requests.bodies(Method::PATCH, &format!("{}/status", foxservices))[0];
+        assert_eq!(status["metadata"], json!({ "name": "web" }));
+        assert_eq!(status["status"]["phase"], "Deploying");
+        assert_eq!(status["status"]["observedGeneration"], 1);
+        assert_eq!(event_reasons(&requests), vec!["DeploymentCreated"]);
+    }
+
+    #[tokio::test]
+    async fn reconcile_deletes_subresources_then_finalizer() {
+        let fox_svc = deleted_fox_service();
+        let answered = fox_svc.clone();
+        let (context, requests) = context(move |method, path, body| match *method {
+            Method::DELETE => mock::deleted(),
+            Method::GET if path.ends_with("/foxservices/web") => {
+                (StatusCode::OK, serde_json::to_value(&answered).unwrap())
+            }
+            _ => respond(&answered, method, path, body),
+        });
+
+        let action = reconcile(fox_svc, context).await.unwrap();
+
+        assert_eq!(action.requeue_after, None);
+        let foxservices = "/apis/cbopt.com/v1/namespaces/default/foxservices/web";
+        let expected = vec![
+            "GET /apis/apps/v1/namespaces/default/deployments/web",
+            "DELETE /apis/apps/v1/namespaces/default/deployments/web?",
+            "DELETE /apis/apps/v1/namespaces/default/statefulsets/web?",
+            "DELETE /apis/apps/v1/namespaces/default/daemonsets/web?",
+            "DELETE /api/v1/namespaces/default/services/web-headless?",
+            "GET /api/v1/namespaces/default/persistentvolumeclaims?&labelSelector=app.kubernetes.io%2Fname%3Dweb",
+            "POST /apis/events.k8s.io/v1/namespaces/default/events?",
+            "GET /apis/cbopt.com/v1/namespaces/default/foxservices/web",
+            "PATCH /apis/cbopt.com/v1/namespaces/default/foxservices/web?",
+        ];
+        assert_eq!(requests.lines(), expected);
+        let propagation = json!({ "propagationPolicy": "Foreground" });
+        let deleted = requests.bodies(Method::DELETE, "/apis/apps/v1/");
+        assert!(deleted.iter().all(|body| *body == propagation));
+        assert_eq!(
+            requests.bodies(Method::PATCH, foxservices),
+            vec![json!({ "metadata": { "resourceVersion": "7", "finalizers": [] } })]
+        );
+        assert_eq!(event_reasons(&requests), vec!["ResourcesDeleted"]);
+    }
+
+    #[tokio::test]
+    async fn reconcile_deletes_without_subresources() {
+        // The subresources were never created, deleting them is answered with 404.
+        let fox_svc = deleted_fox_service();
+        let answered = fox_svc.clone();
+        let (context, requests) = context(move |method, path, body| match *method {
+            Method::GET if path.ends_with("/foxservices/web") => {
+                (StatusCode::OK, serde_json::to_value(&answered).unwrap())
+            }
+            _ => respond(&answered, method, path, body),
+        });
+
+        let action = reconcile(fox_svc, context).await.unwrap();
+
+        assert_eq!(action.requeue_after, None);
+        let foxservices = "/apis/cbopt.com/v1/namespaces/default/foxservices/web";
+        assert_eq!(requests.bodies(Method::PATCH, foxservices).len(), 1);
+        assert_eq!(event_reasons(&requests), vec!["ResourcesDeleted"]);
+    }
+
+    #[tokio::test]
+    async fn reconcile_deletes_a_resource_already_gone() {
+        // The finalizer was removed by hand in between, so the resource is gone as well.
+        let fox_svc = deleted_fox_service();
+        let answered = fox_svc.clone();
+        let (context, requests) =
+            context(move |method, path, body| respond(&answered, method, path, body));
+
+        let action = reconcile(fox_svc, context).await.unwrap();
+
+        assert_eq!(action.requeue_after, None);
+        let foxservices = "/apis/cbopt.com/v1/namespaces/default/foxservices/web";
+        assert!(requests.bodies(Method::PATCH, foxservices).is_empty());
+        assert_eq!(event_reasons(&requests), vec!["ResourcesDeleted"]);
+    }
+}
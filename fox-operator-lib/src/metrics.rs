@@ -0,0 +1,185 @@
+use crate::{Action, Error};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Environment variable holding the port the metrics server listens on.
+const PORT_VAR: &str = "FOX_METRICS_PORT";
+
+/// Port the metrics server listens on, unless `FOX_METRICS_PORT` is set.
+const DEFAULT_PORT: u16 = 8080;
+
+/// Path the metrics are served at, in the Prometheus text format.
+const METRICS_PATH: &str = "/metrics";
+
+/// Metrics of the reconciliations, labeled by the namespace of the reconciled `FoxService`.
+/// Cloning is cheap, the clones update and serve the same metrics.
+#[derive(Clone)]
+pub struct Metrics {
+    /// Registry the metrics are gathered from when served
+    registry: Registry,
+    /// Reconciliations by namespace, action and outcome
+    reconciliations: IntCounterVec,
+    /// Durations of reconciliations by namespace and action, in seconds
+    durations: HistogramVec,
+    /// Errors reported by the Kubernetes API by namespace and status code
+    api_errors: IntCounterVec,
+}
+
+impl Metrics {
+    /// Registers the metrics of the operator with a new registry.
+    pub fn new() -> Self {
+        let reconciliations = IntCounterVec::new(
+            Opts::new(
+                "fox_operator_reconciliations_total",
+                "Reconciliations of FoxService resources by action and outcome",
+            ),
+            &["namespace", "action", "outcome"],
+        )
+        .expect("Expected valid reconciliation counter options.");
+        let durations = HistogramVec::new(
+            HistogramOpts::new(
+                "fox_operator_reconcile_duration_seconds",
+                "Durations of reconciliations of FoxService resources",
+            ),
+            &["namespace", "action"],
+        )
+        .expect("Expected valid reconciliation duration histogram options.");
+        let api_errors = IntCounterVec::new(
+            Opts::new(
+                "fox_operator_kube_api_errors_total",
+                "Errors reported by the Kubernetes API while reconciling, by status code",
+            ),
+            &["namespace", "code"],
+        )
+        .expect("Expected valid Kubernetes API error counter options.");
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(reconciliations.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(durations.clone()),
+            Box::new(api_errors.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Expected metrics to be registered once.");
+        }
+        Metrics {
+            registry,
+            reconciliations,
+            durations,
+            api_errors,
+        }
+    }
+
+    /// Records a finished reconciliation.
+    ///
+    /// # Arguments
+    /// - `namespace` - Namespace of the reconciled `FoxService`.
+    /// - `action` - Action taken, `None` if the reconciliation failed before it was decided.
+    /// - `error` - Error the reconciliation failed with, if any.
+    /// - `duration` - Time the reconciliation took.
+    pub fn reconciled(
+        &self,
+        namespace: &str,
+        action: Option<Action>,
+        error: Option<&Error>,
+        duration: Duration,
+    ) {
+        let action = match action {
+            Some(Action::Create) => "create",
+            Some(Action::Update) => "update",
+            Some(Action::Delete) => "delete",
+            Some(Action::NoOp) => "noop",
+            Some(Action::Pause) => "pause",
+            None => "unknown",
+        };
+        let outcome = if error.is_some() { "error" } else { "success" };
+        self.reconciliations
+            .with_label_values(&[namespace, action, outcome])
+            .inc();
+        self.durations
+            .with_label_values(&[namespace, action])
+            .observe(duration.as_secs_f64());
+        // Failures to reach the API server at all have no status code.
+        if let Some(Error::KubeError { source }) = error {
+            let code = match source {
+                kube::Error::Api(response) => response.code.to_string(),
+                _ => "none".to_string(),
+            };
+            self.api_errors
+                .with_label_values(&[namespace, code.as_str()])
+                .inc();
+        }
+    }
+
+    /// Encodes the current values of the metrics in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(error) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!(%error, "Could not encode the metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Reads the port the metrics server listens on from `FOX_METRICS_PORT`, 8080 if not set.
+pub fn port_from_env() -> Result<u16, String> {
+    match std::env::var(PORT_VAR) {
+        Ok(port) => port
+            .parse()
+            .map_err(|_| format!("{} is not a port: {}", PORT_VAR, port)),
+        Err(_) => Ok(DEFAULT_PORT),
+    }
+}
+
+/// Serves the metrics over HTTP at `/metrics` until an error occurs accepting connections.
+/// Failures of single connections are only logged.
+///
+/// # Arguments
+/// - `port` - Port to listen on.
+/// - `metrics` - Metrics updated by the reconciliations.
+pub async fn serve(port: u16, metrics: Metrics) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .map_err(|error| format!("Could not listen on port {}: {}", port, error))?;
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|error| format!("Could not accept a connection: {}", error))?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|request| handle(request, metrics.clone()));
+            if let Err(error) = Http::new().serve_connection(stream, service).await {
+                tracing::warn!(%error, "Metrics connection error");
+            }
+        });
+    }
+}
+
+/// Answers a request to the metrics server, only `GET /metrics` is served.
+async fn handle(request: Request<Body>, metrics: Metrics) -> Result<Response<Body>, hyper::Error> {
+    if request.method() != Method::GET || request.uri().path() != METRICS_PATH {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return Ok(response);
+    }
+    let mut response = Response::new(Body::from(metrics.encode()));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    Ok(response)
+}
@@ -0,0 +1,125 @@
+use fox_k8s_crds::fox_service::{FoxService, FoxServiceSpec};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use kube::{Client, Resource};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// A `FoxService` named `web` in the `default` namespace, running a single nginx container.
+pub fn fox_service() -> FoxService {
+    let spec = FoxServiceSpec::builder()
+        .name("web")
+        .container(|container| container.image("nginx:1.21").port(80))
+        .build()
+        .unwrap();
+    let mut fox_svc = FoxService::new("web", spec);
+    fox_svc.meta_mut().namespace = Some("default".to_string());
+    fox_svc.meta_mut().uid = Some("uid-web".to_string());
+    fox_svc.meta_mut().generation = Some(1);
+    fox_svc
+}
+
+/// A request made to the mocked API server: the verb, the path with the query and the body, `null`
+/// if there is none.
+pub type Recorded = (Method, String, Value);
+
+/// Requests made to the mocked API server, in the order they were made.
+#[derive(Clone, Default)]
+pub struct Requests(Arc<Mutex<Vec<Recorded>>>);
+
+impl Requests {
+    /// The requests made so far.
+    pub fn all(&self) -> Vec<Recorded> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// The verbs and paths of the requests made so far, e.g., `PATCH /apis/...?fieldManager=...`.
+    pub fn lines(&self) -> Vec<String> {
+        self.all()
+            .iter()
+            .map(|(method, path, _)| format!("{} {}", method, path))
+            .collect()
+    }
+
+    /// The bodies of the requests made so far with given verb to paths starting with `prefix`.
+    pub fn bodies(&self, method: Method, prefix: &str) -> Vec<Value> {
+        self.all()
+            .into_iter()
+            .filter(|(verb, path, _)| *verb == method && path.starts_with(prefix))
+            .map(|(_, _, body)| body)
+            .collect()
+    }
+}
+
+/// Builds a client whose requests are answered by `respond` rather than an API server. Each
+/// request is recorded before it is answered.
+///
+/// # Arguments
+/// - `respond` - Answers a request with a status code and a JSON body, given its verb, the path
+///   with the query and the body.
+pub fn client<F>(respond: F) -> (Client, Requests)
+where
+    F: Fn(&Method, &str, &Value) -> (StatusCode, Value) + Send + Sync + 'static,
+{
+    let requests = Requests::default();
+    let recorded = requests.clone();
+    let respond = Arc::new(respond);
+    let service = tower::service_fn(move |request: Request<Body>| {
+        let (recorded, respond) = (recorded.clone(), respond.clone());
+        async move {
+            let method = request.method().clone();
+            let path = request
+                .uri()
+                .path_and_query()
+                .map_or_else(String::new, ToString::to_string);
+            let bytes = hyper::body::to_bytes(request.into_body()).await?;
+            let body: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+            let (status, response) = respond(&method, &path, &body);
+            recorded.0.lock().unwrap().push((method, path, body));
+            let mut response = Response::new(Body::from(response.to_string()));
+            *response.status_mut() = status;
+            Ok::<_, hyper::Error>(response)
+        }
+    });
+    (Client::new(service), requests)
+}
+
+/// A `Status` as returned by the API server for a failed request.
+///
+/// # Arguments
+/// - `code` - HTTP status code, e.g., 404
+/// - `reason` - Machine-readable reason, e.g., `NotFound`
+pub fn status(code: u16, reason: &str) -> (StatusCode, Value) {
+    let body = json!({
+        "kind": "Status",
+        "apiVersion": "v1",
+        "metadata": {},
+        "status": "Failure",
+        "message": format!("{} ({})", reason, code),
+        "reason": reason,
+        "code": code
+    });
+    (StatusCode::from_u16(code).unwrap(), body)
+}
+
+/// An empty list of objects, as returned by the API server if none matches.
+pub fn list() -> (StatusCode, Value) {
+    let body = json!({
+        "kind": "List",
+        "apiVersion": "v1",
+        "metadata": {},
+        "items": []
+    });
+    (StatusCode::OK, body)
+}
+
+/// A `Status` as returned by the API server for a successful deletion.
+pub fn deleted() -> (StatusCode, Value) {
+    let body = json!({
+        "kind": "Status",
+        "apiVersion": "v1",
+        "metadata": {},
+        "status": "Success",
+        "code": 200
+    });
+    (StatusCode::OK, body)
+}
@@ -0,0 +1,226 @@
+use crate::Scope;
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{
+    ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject,
+};
+use kube::api::ObjectMeta;
+use serde_json::{json, Value};
+
+/// Name of the service account, the roles and the bindings of the operator.
+const NAME: &str = "fox-operator";
+
+/// Verbs needed on the subresources of a `FoxService`, which are created, patched and deleted.
+const MANAGE: &[&str] = &[
+    "get", "list", "watch", "create", "update", "patch", "delete",
+];
+
+/// Verbs needed on objects merely looked up by the operator.
+const READ: &[&str] = &["get", "list", "watch"];
+
+/// Kubernetes API permission of the operator.
+struct Permission {
+    /// API group of the resources, empty for the core group
+    group: &'static str,
+    /// Resources of the group, e.g., `deployments` or `foxservices/status`
+    resources: &'static [&'static str],
+    /// Verbs allowed on the resources
+    verbs: &'static [&'static str],
+    /// Whether the resources are cluster-scoped, these can't be granted by a Role
+    cluster_scoped: bool,
+}
+
+/// Every permission the operator needs. A new kind of subresource has to be added here, so the
+/// manifests printed by `fox-operator rbac` grant access to it.
+const PERMISSIONS: &[Permission] = &[
+    Permission {
+        group: "cbopt.com",
+        resources: &["foxservices"],
+        verbs: &["get", "list", "watch", "update", "patch"],
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "cbopt.com",
+        resources: &["foxservices/status"],
+        verbs: &["get", "update", "patch"],
+        cluster_scoped: false,
+    },
+    // Owner references blocking the deletion of the owner need access to its finalizers.
+    Permission {
+        group: "cbopt.com",
+        resources: &["foxservices/finalizers"],
+        verbs: &["update"],
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "",
+        resources: &[
+            "services",
+            "configmaps",
+            "persistentvolumeclaims",
+            "serviceaccounts",
+        ],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    // Secrets referenced by the containers and image pull secrets are checked for existence.
+    Permission {
+        group: "",
+        resources: &["secrets"],
+        verbs: READ,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "",
+        resources: &["events"],
+        verbs: &["create", "patch"],
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "apps",
+        resources: &["deployments", "statefulsets", "daemonsets"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "batch",
+        resources: &["jobs", "cronjobs"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "autoscaling",
+        resources: &["horizontalpodautoscalers"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "autoscaling.k8s.io",
+        resources: &["verticalpodautoscalers"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "policy",
+        resources: &["poddisruptionbudgets"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "networking.k8s.io",
+        resources: &["ingresses", "networkpolicies"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "gateway.networking.k8s.io",
+        resources: &["httproutes"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "monitoring.coreos.com",
+        resources: &["servicemonitors"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    Permission {
+        group: "cert-manager.io",
+        resources: &["certificates"],
+        verbs: MANAGE,
+        cluster_scoped: false,
+    },
+    // The PriorityClass of the pods is checked for existence.
+    Permission {
+        group: "scheduling.k8s.io",
+        resources: &["priorityclasses"],
+        verbs: READ,
+        cluster_scoped: true,
+    },
+];
+
+/// Builds the manifests granting the operator its permissions: a service account, and roles bound
+/// to it.
+///
+/// # Arguments
+/// - `namespace` - Namespace the operator runs in, its service account is created there.
+/// - `scope` - Namespaces the operator manages `FoxService` resources in. Unless it is the whole
+///   cluster, its permissions are granted by a Role in each watched namespace rather than a
+///   ClusterRole. Permissions on cluster-scoped resources are granted by a ClusterRole either way.
+pub fn manifests(namespace: &str, scope: &Scope) -> Vec<Value> {
+    let service_account = ServiceAccount {
+        metadata: metadata(Some(namespace)),
+        ..ServiceAccount::default()
+    };
+    let subjects = vec![Subject {
+        kind: "ServiceAccount".to_string(),
+        name: NAME.to_string(),
+        namespace: Some(namespace.to_string()),
+        api_group: None,
+    }];
+
+    let (cluster_scoped, namespace_scoped): (Vec<&Permission>, Vec<&Permission>) = PERMISSIONS
+        .iter()
+        .partition(|permission| permission.cluster_scoped || *scope == Scope::Cluster);
+    let mut manifests = vec![json!(service_account)];
+    if !cluster_scoped.is_empty() {
+        let role = ClusterRole {
+            metadata: metadata(None),
+            rules: Some(cluster_scoped.into_iter().map(rule).collect()),
+            ..ClusterRole::default()
+        };
+        let binding = ClusterRoleBinding {
+            metadata: metadata(None),
+            role_ref: role_ref("ClusterRole"),
+            subjects: Some(subjects.clone()),
+        };
+        manifests.push(json!(role));
+        manifests.push(json!(binding));
+    }
+    let watched: &[String] = match scope {
+        Scope::Cluster => &[],
+        Scope::Namespaces(namespaces) => namespaces,
+    };
+    for watched in watched.iter().filter(|_| !namespace_scoped.is_empty()) {
+        let role = Role {
+            metadata: metadata(Some(watched)),
+            rules: Some(namespace_scoped.iter().copied().map(rule).collect()),
+        };
+        let binding = RoleBinding {
+            metadata: metadata(Some(watched)),
+            role_ref: role_ref("Role"),
+            subjects: Some(subjects.clone()),
+        };
+        manifests.push(json!(role));
+        manifests.push(json!(binding));
+    }
+    manifests
+}
+
+/// Metadata of the objects granting the operator its permissions.
+fn metadata(namespace: Option<&str>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(NAME.to_string()),
+        namespace: namespace.map(str::to_string),
+        ..ObjectMeta::default()
+    }
+}
+
+/// Reference of a binding to the role of the operator of the given kind.
+fn role_ref(kind: &str) -> RoleRef {
+    RoleRef {
+        api_group: "rbac.authorization.k8s.io".to_string(),
+        kind: kind.to_string(),
+        name: NAME.to_string(),
+    }
+}
+
+/// Converts a permission to the rule of a Role or ClusterRole.
+fn rule(permission: &Permission) -> PolicyRule {
+    let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+    PolicyRule {
+        api_groups: Some(vec![permission.group.to_string()]),
+        resources: Some(strings(permission.resources)),
+        verbs: strings(permission.verbs),
+        ..PolicyRule::default()
+    }
+}
@@ -0,0 +1,93 @@
+use kube::Error;
+use std::future::Future;
+
+/// Attempts made at a change the API server rejects with a conflict, before the conflict is
+/// returned.
+pub const MAX_CONFLICT_ATTEMPTS: u32 = 3;
+
+/// HTTP status code of a change rejected because the object was modified since it was read.
+const CONFLICT: u16 = 409;
+
+/// Runs a change to an object again if the API server rejects it with a conflict, because another
+/// controller or a user modified the object in between. The change must read the object afresh on
+/// each run, e.g., to patch its current `resourceVersion`, rather than reuse a copy read before.
+///
+/// # Arguments
+/// - `change` - Reads and changes the object, run up to `MAX_CONFLICT_ATTEMPTS` times.
+///
+/// Returns the outcome of the last run.
+pub async fn on_conflict<T, F, Fut>(mut change: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        match change().await {
+            Err(Error::Api(response))
+                if response.code == CONFLICT && attempt < MAX_CONFLICT_ATTEMPTS =>
+            {
+                tracing::debug!(attempt, message = %response.message, "Conflict, retrying");
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use fox_k8s_crds::fox_service::FoxService;
+    use hyper::{Method, StatusCode};
+    use kube::api::{Patch, PatchParams};
+    use kube::{Api, Resource};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Answers patches with a conflict until `conflicts` patches were made, and with the
+    /// `FoxService` afterwards.
+    fn conflicting(conflicts: u32) -> impl Fn(&Method, &str, &Value) -> (StatusCode, Value) {
+        let patches = AtomicU32::new(0);
+        move |_, _, _| {
+            if patches.fetch_add(1, Ordering::SeqCst) < conflicts {
+                mock::status(409, "Conflict")
+            } else {
+                (
+                    StatusCode::OK,
+                    serde_json::to_value(mock::fox_service()).unwrap(),
+                )
+            }
+        }
+    }
+
+    /// Patches the labels of the `FoxService` named `web`.
+    async fn label(api: &Api<FoxService>) -> Result<FoxService, Error> {
+        let patch = json!({ "metadata": { "labels": { "tier": "web" } } });
+        api.patch("web", &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+    }
+
+    #[tokio::test]
+    async fn on_conflict_retries_after_a_conflict() {
+        let (client, requests) = mock::client(conflicting(1));
+        let api: Api<FoxService> = Api::namespaced(client, "default");
+
+        let fox_svc = on_conflict(|| label(&api)).await.unwrap();
+
+        assert_eq!(fox_svc.meta().name.as_deref(), Some("web"));
+        assert_eq!(requests.all().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn on_conflict_gives_up_after_max_attempts() {
+        let (client, requests) = mock::client(conflicting(u32::MAX));
+        let api: Api<FoxService> = Api::namespaced(client, "default");
+
+        let error = on_conflict(|| label(&api)).await.unwrap_err();
+
+        assert!(matches!(error, Error::Api(response) if response.code == 409));
+        assert_eq!(requests.all().len(), MAX_CONFLICT_ATTEMPTS as usize);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::fox_service;
+use crate::fox_service::workload::Workload;
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::apps::v1::{Deployment, DeploymentCondition};
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::networking::v1::Ingress;
+use k8s_openapi::chrono::{DateTime, SecondsFormat, Utc};
+use kube::api::{DynamicObject, Patch};
+use kube::{Api, Client, Error, Resource};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Shortest delay before checking again on missing Secrets and ConfigMaps.
+const MIN_DEGRADED_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Longest delay before checking again on missing Secrets and ConfigMaps.
+const MAX_DEGRADED_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Builds the status of a `FoxService` from the outcome of a reconciliation pass.
+///
+/// # Arguments:
+/// - `fox_svc` - The `FoxService` resource being reconciled. Its current status is the base for the new one.
+/// - `workload` - The workload belonging to `fox_svc` as fetched before any action was taken, if any.
+/// - `service` - The service belonging to `fox_svc` as fetched before any action was taken, if any.
+/// - `ingress` - The ingress belonging to `fox_svc` as fetched before any action was taken, if any.
+/// - `certificate` - The cert-manager Certificate belonging to `fox_svc` as fetched before any
+///   action was taken, if any.
+/// - `changed` - Whether subresources were created or modified during the reconciliation pass.
+/// - `outcome` - Result of the actions taken during the reconciliation pass.
+pub fn build_status(
+    fox_svc: &FoxService,
+    workload: Option<&Workload>,
+    service: Option<&Service>,
+    ingress: Option<&Ingress>,
+    certificate: Option<&DynamicObject>,
+    changed: bool,
+    outcome: &Result<(), crate::Error>,
+) -> FoxServiceStatus {
+    let mut status: FoxServiceStatus = fox_svc.status.clone().unwrap_or_default();
+    status.replicas = workload.map(replicas).unwrap_or_default();
+    status.ready_replicas = workload.map(ready_replicas).unwrap_or_default();
+    status.available_replicas = workload.map(available_replicas).unwrap_or_default();
+    status.service_cluster_ip = service.and_then(fox_service::service::cluster_ip);
+    status.selector = Some(fox_service::label_selector(fox_svc));
+    status.load_balancer_addresses = service
+        .map(fox_service::service::load_balancer_addresses)
+        .unwrap_or_default();
+    status.ingress_addresses = ingress
+        .map(fox_service::ingress::addresses)
+        .unwrap_or_default();
+
+    // Nothing was applied, the status describes the subresources as left by the last pass.
+    if fox_service::is_paused(fox_svc) {
+        status.set_condition(condition(
+            FoxServiceCondition::PAUSED,
+            true,
+            Some("Paused"),
+            Some(format!(
+                "Reconciliation paused by the {} annotation",
+                fox_service::PAUSED_ANNOTATION
+            )),
+        ));
+        return status;
+    }
+    status
+        .conditions
+        .retain(|condition| condition.type_ != FoxServiceCondition::PAUSED);
+
+    match outcome {
+        Ok(()) => {
+            let suspended = fox_service::workload::is_suspended(&fox_svc.spec);
+            status.phase = match workload {
+                _ if suspended => FoxServicePhase::Suspended,
+                Some(workload) if !changed && is_rolled_out(workload) => FoxServicePhase::Ready,
+                // Only batch containers, there are no long-running pods to wait for.
+                None if !changed && !fox_service::workload::is_required(&fox_svc.spec) => {
+                    FoxServicePhase::Ready
+                }
+                _ => FoxServicePhase::Deploying,
+            };
+            status.set_condition(condition(FoxServiceCondition::FAILED, false, None, None));
+            status.set_condition(condition(FoxServiceCondition::DEGRADED, false, None, None));
+            // Tells the next pass the workload was scaled down, so it is scaled up again on resume.
+            status.set_condition(condition(
+                FoxServiceCondition::SUSPENDED,
+                suspended,
+                suspended.then_some("Suspended"),
+                None,
+            ));
+            // Subresources reflect the current specification now. On failure, the previous value
+            // is kept so clients can detect the specification wasn't processed yet.
+            status.observed_generation = fox_svc.meta().generation;
+        }
+        Err(error) => {
+            status.phase = FoxServicePhase::Failed;
+            status.set_condition(condition(
+                FoxServiceCondition::FAILED,
+                true,
+                Some("ReconciliationFailed"),
+                Some(error.to_string()),
+            ));
+            // Other failures don't tell whether the service is degraded, the condition is kept.
+            let degraded = match error {
+                crate::Error::MissingReference(missing) => Some(("MissingReference", missing)),
+                crate::Error::InvalidSpec(problems) => Some(("InvalidSpec", problems)),
+                crate::Error::NotManaged(subresource) => Some(("NotManaged", subresource)),
+                _ => None,
+            };
+            if let Some((reason, message)) = degraded {
+                status.set_condition(condition(
+                    FoxServiceCondition::DEGRADED,
+                    true,
+                    Some(reason),
+                    Some(message.to_owned()),
+                ));
+            }
+        }
+    }
+
+    // A rollout exceeding its progress deadline won't recover on its own, so it is reported as a
+    // failure, while the reconciliation itself may have succeeded. Only deployments have one.
+    let progressing = match workload {
+        Some(Workload::Deployment(deployment)) => deployment_condition(deployment, "Progressing"),
+        _ => None,
+    };
+    if let Some(progressing) = progressing {
+        let stalled = progressing.status == "False";
+        if stalled && outcome.is_ok() {
+            status.phase = FoxServicePhase::Failed;
+        }
+        status.set_condition(condition(
+            FoxServiceCondition::PROGRESSING,
+            !stalled,
+            progressing.reason.as_deref(),
+            progressing.message.clone(),
+        ));
+    }
+
+    // Mirrors whether cert-manager issued the certificate, which it may take a while to do.
+    if fox_service::certificate::issuer_ref(&fox_svc.spec).is_some() {
+        let (ready, message) = certificate
+            .and_then(fox_service::certificate::ready_condition)
+            .unwrap_or((false, None));
+        let reason = if certificate.is_some() {
+            "Issuing"
+        } else {
+            "NotFound"
+        };
+        status.set_condition(condition(
+            FoxServiceCondition::CERTIFICATE_READY,
+            ready,
+            Some(if ready { "Issued" } else { reason }),
+            message,
+        ));
+    } else {
+        status
+            .conditions
+            .retain(|condition| condition.type_ != FoxServiceCondition::CERTIFICATE_READY);
+    }
+
+    let ready = status.phase == FoxServicePhase::Ready;
+    let reason = format!("{:?}", status.phase);
+    status.set_condition(condition(
+        FoxServiceCondition::READY,
+        ready,
+        Some(&reason),
+        None,
+    ));
+    status
+}
+
+/// Writes the status of a `FoxService` resource using the `/status` subresource. If the status did
+/// not change, no request is made, so an unchanged resource doesn't trigger another reconciliation.
+///
+/// # Arguments:
+/// - `client` - Kubernetes client to modify the `FoxService` resource with.
+/// - `fox_svc` - The `FoxService` resource to modify.
+/// - `status` - The new status of the resource.
+///
+/// Note: The status is applied server-side as `FIELD_MANAGER`, so fields left out of it, e.g., a
+/// cleared service address, are removed from the stored status.
+pub async fn update(
+    client: Client,
+    fox_svc: &FoxService,
+    status: FoxServiceStatus,
+) -> Result<(), Error> {
+    if fox_svc.status.as_ref() == Some(&status) {
+        return Ok(());
+    }
+
+    let namespace = fox_svc.meta().namespace.as_deref().unwrap_or_default();
+    let name = fox_svc.meta().name.as_deref().unwrap_or_default();
+    let api: Api<FoxService> = Api::namespaced(client, namespace);
+    let status: Value = json!({
+        "apiVersion": FoxService::api_version(&()),
+        "kind": FoxService::kind(&()),
+        "metadata": { "name": name },
+        "status": status
+    });
+
+    let patch: Patch<&Value> = Patch::Apply(&status);
+    api.patch_status(name, &fox_service::apply_params(), &patch)
+        .await?;
+    Ok(())
+}
+
+/// Delay before checking again on the Secrets and ConfigMaps missing for a degraded `FoxService`.
+/// It grows with the time the `FoxService` has been degraded for, roughly doubling with every
+/// check, between five seconds and five minutes.
+///
+/// # Arguments:
+/// - `fox_svc` - The `FoxService` resource as fetched before the reconciliation pass.
+pub fn degraded_backoff(fox_svc: &FoxService) -> Duration {
+    let since = fox_svc
+        .status
+        .iter()
+        .flat_map(|status| status.conditions.iter())
+        .find(|condition| {
+            condition.type_ == FoxServiceCondition::DEGRADED && condition.status == "True"
+        })
+        .and_then(|condition| condition.last_transition_time.as_deref())
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok());
+    let elapsed = since
+        .and_then(|since| (Utc::now() - since.with_timezone(&Utc)).to_std().ok())
+        .unwrap_or_default();
+    elapsed.clamp(MIN_DEGRADED_BACKOFF, MAX_DEGRADED_BACKOFF)
+}
+
+/// Number of pods created by the workload.
+fn replicas(workload: &Workload) -> i32 {
+    match workload {
+        Workload::Deployment(deployment) => deployment
+            .status
+            .as_ref()
+            .and_then(|status| status.replicas)
+            .unwrap_or_default(),
+        Workload::StatefulSet(stateful_set) => stateful_set
+            .status
+            .as_ref()
+            .map(|status| status.replicas)
+            .unwrap_or_default(),
+        Workload::DaemonSet(daemon_set) => daemon_set
+            .status
+            .as_ref()
+            .map(|status| status.current_number_scheduled)
+            .unwrap_or_default(),
+    }
+}
+
+/// Number of pods of the workload that are ready.
+fn ready_replicas(workload: &Workload) -> i32 {
+    match workload {
+        Workload::Deployment(deployment) => deployment
+            .status
+            .as_ref()
+            .and_then(|status| status.ready_replicas)
+            .unwrap_or_default(),
+        Workload::StatefulSet(stateful_set) => stateful_set
+            .status
+            .as_ref()
+            .and_then(|status| status.ready_replicas)
+            .unwrap_or_default(),
+        Workload::DaemonSet(daemon_set) => daemon_set
+            .status
+            .as_ref()
+            .map(|status| status.number_ready)
+            .unwrap_or_default(),
+    }
+}
+
+/// Number of pods of the workload that are available.
+fn available_replicas(workload: &Workload) -> i32 {
+    match workload {
+        Workload::Deployment(deployment) => deployment
+            .status
+            .as_ref()
+            .and_then(|status| status.available_replicas)
+            .unwrap_or_default(),
+        // StatefulSets don't report available pods, their pods are available as soon as ready.
+        Workload::StatefulSet(_) => ready_replicas(workload),
+        Workload::DaemonSet(daemon_set) => daemon_set
+            .status
+            .as_ref()
+            .and_then(|status| status.number_available)
+            .unwrap_or_default(),
+    }
+}
+
+/// Checks whether all pods of the workload are up-to-date and ready.
+fn is_rolled_out(workload: &Workload) -> bool {
+    // (desired replicas, observed generation, updated replicas, ready replicas)
+    let (desired, observed, updated, ready) = match workload {
+        Workload::Deployment(deployment) => {
+            let desired = deployment.spec.as_ref().and_then(|spec| spec.replicas);
+            match deployment.status.as_ref() {
+                Some(status) => (
+                    desired,
+                    status.observed_generation,
+                    status.updated_replicas,
+                    status.ready_replicas,
+                ),
+                None => return false,
+            }
+        }
+        Workload::StatefulSet(stateful_set) => {
+            let desired = stateful_set.spec.as_ref().and_then(|spec| spec.replicas);
+            match stateful_set.status.as_ref() {
+                Some(status) => (
+                    desired,
+                    status.observed_generation,
+                    status.updated_replicas,
+                    status.ready_replicas,
+                ),
+                None => return false,
+            }
+        }
+        // The number of pods follows the number of nodes, rather than the specification.
+        Workload::DaemonSet(daemon_set) => match daemon_set.status.as_ref() {
+            Some(status) => (
+                Some(status.desired_number_scheduled),
+                status.observed_generation,
+                status.updated_number_scheduled,
+                Some(status.number_ready),
+            ),
+            None => return false,
+        },
+    };
+    let desired = desired.unwrap_or(1);
+    observed >= workload.metadata().generation
+        && updated.unwrap_or_default() == desired
+        && ready.unwrap_or_default() == desired
+}
+
+/// Returns the condition of the given type reported by the deployment controller, if any.
+fn deployment_condition<'a>(
+    deployment: &'a Deployment,
+    type_: &str,
+) -> Option<&'a DeploymentCondition> {
+    deployment
+        .status
+        .as_ref()?
+        .conditions
+        .iter()
+        .flatten()
+        .find(|condition| condition.type_ == type_)
+}
+
+/// Constructs a condition with the current time as the last transition time.
+fn condition(
+    type_: &str,
+    status: bool,
+    reason: Option<&str>,
+    message: Option<String>,
+) -> FoxServiceCondition {
+    FoxServiceCondition {
+        type_: type_.to_string(),
+        status: if status { "True" } else { "False" }.to_string(),
+        reason: reason.map(str::to_string),
+        message,
+        last_transition_time: Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+    }
+}
@@ -0,0 +1,421 @@
+use crate::fox_service::{self, hook};
+use crate::Error;
+use fox_k8s_crds::fox_service::validation::{is_dns_label, resolve_int_or_percent};
+use fox_k8s_crds::fox_service::*;
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use k8s_openapi::api::scheduling::v1::PriorityClass;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::{Api, Client};
+
+/// Type of the Secrets holding credentials for container registries.
+const DOCKER_CONFIG_JSON: &str = "kubernetes.io/dockerconfigjson";
+
+/// Lists settings of a `FoxService` specification that are valid, but likely to cause trouble or
+/// worth auditing, as pairs of a machine-readable reason and a description.
+///
+/// # Arguments
+/// - `fs` - Fox service specification to check
+pub fn warnings(fs: &FoxServiceSpec) -> Vec<(&'static str, String)> {
+    let mut warnings = Vec::new();
+    for (field, container) in containers(fs) {
+        let context = container.security_context.as_ref();
+        if context.and_then(|context| context.privileged) == Some(true) {
+            warnings.push((
+                "PrivilegedContainer",
+                format!("Container {} runs privileged", container.name),
+            ));
+        }
+        // A DaemonSet runs a single pod per node anyway.
+        if replicas(fs) <= 1 || fox_service::workload::kind(fs) == WorkloadKind::DaemonSet {
+            continue;
+        }
+        for (index, port) in container.ports.iter().flatten().enumerate() {
+            if let Some(host_port) = port.host_port {
+                warnings.push((
+                    "HostPortConflict",
+                    format!(
+                        "{}.ports[{}]: host port {} can only be bound by one of the {} replicas per node",
+                        field,
+                        index,
+                        host_port,
+                        replicas(fs)
+                    ),
+                ));
+            }
+        }
+    }
+    // One pod is the default, only a number set on purpose is worth a warning
+    if let Some(replicas) = fs
+        .replicas
+        .filter(|r| *r != 1 && fox_service::workload::kind(fs) == WorkloadKind::DaemonSet)
+    {
+        warnings.push((
+            "IgnoredReplicas",
+            format!(
+                "replicas: {} is ignored, the daemonset workload runs one pod per node",
+                replicas
+            ),
+        ));
+    }
+    if blocks_eviction(fs) {
+        warnings.push((
+            "BlockingDisruptionBudget",
+            format!(
+                "disruptionBudget: not created, it would not allow evicting any of the {} replicas and block node drains",
+                min_replicas(fs)
+            ),
+        ));
+    }
+    warnings
+}
+
+/// Checks whether the disruption budget of a specification would not allow evicting any pod, e.g.,
+/// `minAvailable: 1` for a single replica. Such a budget blocks draining nodes forever, so it is
+/// not created.
+///
+/// # Arguments
+/// - `fs` - Fox service specification to check
+pub fn blocks_eviction(fs: &FoxServiceSpec) -> bool {
+    let budget = match &fs.disruption_budget {
+        Some(budget) => budget,
+        None => return false,
+    };
+    let replicas = min_replicas(fs);
+    let resolve = |bound: &IntOrString| resolve_int_or_percent(bound, replicas, true);
+    match (&budget.min_available, &budget.max_unavailable) {
+        (Some(min_available), _) => resolve(min_available).is_some_and(|min| min >= replicas),
+        (None, Some(max_unavailable)) => resolve(max_unavailable) == Some(0),
+        (None, None) => false,
+    }
+}
+
+/// Lowest number of pods a specification may run, the minimum of the autoscaling if enabled.
+fn min_replicas(fs: &FoxServiceSpec) -> i32 {
+    match &fs.autoscaling {
+        Some(autoscaling) => autoscaling.min_replicas.unwrap_or(1),
+        None => replicas(fs),
+    }
+}
+
+/// Number of pods a specification runs without autoscaling. Unset replicas are left to others,
+/// they are assumed to be the single pod a new workload starts with.
+fn replicas(fs: &FoxServiceSpec) -> i32 {
+    fs.replicas.unwrap_or(1)
+}
+
+/// Verifies that all ConfigMaps and Secrets referenced by the containers exist, through
+/// environment variables, imports of environment variables or volumes, unless the reference is
+/// marked optional. Otherwise, the pods would be stuck in `CreateContainerConfigError`. The
+/// ConfigMap holding the `configFiles` is created by the operator and not verified.
+///
+/// # Arguments
+/// - `client` - Kubernetes client to look up the ConfigMaps and Secrets with
+/// - `fs` - Fox service specification to verify
+/// - `namespace` - Namespace of the `FoxService` resource, the references are resolved in
+///
+/// Returns `Error::MissingReference` naming the first missing object or key.
+pub async fn verify_references(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<(), Error> {
+    let config_maps: Api<ConfigMap> = Api::namespaced(client.clone(), namespace);
+    let secrets: Api<Secret> = Api::namespaced(client, namespace);
+    let missing = |field: String, kind: &str, name: &str| {
+        Error::MissingReference(format!(
+            "{}: {} {} does not exist in namespace {}",
+            field, kind, name, namespace
+        ))
+    };
+    for (container_field, container) in containers(fs) {
+        for (index, entry) in container.env.iter().flatten().enumerate() {
+            let source = match &entry.value_from {
+                Some(source) => source,
+                None => continue,
+            };
+            let field = format!("{}.env[{}].valueFrom", container_field, index);
+            if let Some(reference) = &source.secret_key_ref {
+                let secret = match fetch(secrets.get(&reference.name).await)? {
+                    Some(secret) => secret,
+                    None => return Err(missing(field, "Secret", &reference.name)),
+                };
+                let has_key = secret
+                    .data
+                    .iter()
+                    .flatten()
+                    .any(|(key, _)| key == &reference.key)
+                    || secret
+                        .string_data
+                        .iter()
+                        .flatten()
+                        .any(|(key, _)| key == &reference.key);
+                if !has_key {
+                    return Err(Error::MissingReference(format!(
+                        "{}: Secret {} has no key {}",
+                        field, reference.name, reference.key
+                    )));
+                }
+            }
+            if let Some(reference) = &source.config_map_key_ref {
+                let config_map = match fetch(config_maps.get(&reference.name).await)? {
+                    Some(config_map) => config_map,
+                    None => return Err(missing(field, "ConfigMap", &reference.name)),
+                };
+                let has_key = config_map
+                    .data
+                    .iter()
+                    .flatten()
+                    .any(|(key, _)| key == &reference.key)
+                    || config_map
+                        .binary_data
+                        .iter()
+                        .flatten()
+                        .any(|(key, _)| key == &reference.key);
+                if !has_key {
+                    return Err(Error::MissingReference(format!(
+                        "{}: ConfigMap {} has no key {}",
+                        field, reference.name, reference.key
+                    )));
+                }
+            }
+        }
+
+        let imports = container.env_from.iter().flatten().enumerate();
+        for (import, env_from) in imports.filter(|(_, env_from)| env_from.optional != Some(true)) {
+            let field = format!("{}.envFrom[{}]", container_field, import);
+            if let Some(name) = &env_from.config_map {
+                if !exists(config_maps.get(name).await)? {
+                    return Err(missing(field, "ConfigMap", name));
+                }
+            }
+            if let Some(name) = &env_from.secret {
+                if !exists(secrets.get(name).await)? {
+                    return Err(missing(field, "Secret", name));
+                }
+            }
+        }
+    }
+
+    for (index, volume) in fs.volumes.iter().flatten().enumerate() {
+        let field = format!("volumes[{}]", index);
+        if let Some(config_map) = &volume.config_map {
+            if config_map.optional != Some(true)
+                && !exists(config_maps.get(&config_map.name).await)?
+            {
+                return Err(missing(field, "ConfigMap", &config_map.name));
+            }
+        } else if let Some(secret) = &volume.secret {
+            if secret.optional != Some(true) && !exists(secrets.get(&secret.secret_name).await)? {
+                return Err(missing(field, "Secret", &secret.secret_name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all image pull secrets exist and hold registry credentials. A missing or mistyped
+/// secret doesn't prevent the deployment, as the images may be public after all, so the problems
+/// are returned for the caller to report rather than as an error.
+///
+/// # Arguments
+/// - `client` - Kubernetes client to look up the Secrets with
+/// - `fs` - Fox service specification to check
+/// - `namespace` - Namespace of the `FoxService` resource, the Secrets are resolved in
+pub async fn check_image_pull_secrets(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<Vec<String>, Error> {
+    let secrets: Api<Secret> = Api::namespaced(client, namespace);
+    let mut problems = Vec::new();
+    for (index, name) in fs.image_pull_secrets.iter().flatten().enumerate() {
+        let field = format!("imagePullSecrets[{}]", index);
+        match secrets.get(name).await {
+            Ok(secret) if secret.type_.as_deref() != Some(DOCKER_CONFIG_JSON) => {
+                problems.push(format!(
+                    "{}: Secret {} is of type {}, expected {}",
+                    field,
+                    name,
+                    secret.type_.as_deref().unwrap_or("Opaque"),
+                    DOCKER_CONFIG_JSON
+                ));
+            }
+            Ok(_) => {}
+            Err(kube::Error::Api(response)) if response.code == 404 => {
+                problems.push(format!(
+                    "{}: Secret {} does not exist in namespace {}",
+                    field, name, namespace
+                ));
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(problems)
+}
+
+/// Checks that the Secrets holding the TLS certificates of the ingress exist. A missing Secret
+/// doesn't prevent the deployment, the ingress controller serves a default certificate instead, so
+/// the problems are returned for the caller to report rather than as an error. Secrets issued by
+/// cert-manager are not checked, as they are only created once the ingress exists.
+///
+/// # Arguments
+/// - `client` - Kubernetes client to look up the Secrets with
+/// - `fs` - Fox service specification to check
+/// - `namespace` - Namespace of the `FoxService` resource, the Secrets are resolved in
+pub async fn check_tls_secrets(
+    client: Client,
+    fs: &FoxServiceSpec,
+    namespace: &str,
+) -> Result<Vec<String>, Error> {
+    let secrets: Api<Secret> = Api::namespaced(client, namespace);
+    let mut problems = Vec::new();
+    let ingress = fs
+        .ingress
+        .iter()
+        .filter(|ingress| ingress.cluster_issuer.is_none());
+    for (index, tls) in ingress
+        .flat_map(|ingress| ingress.tls.iter().flatten())
+        .enumerate()
+    {
+        if !exists(secrets.get(&tls.secret_name).await)? {
+            problems.push(format!(
+                "ingress.tls[{}].secretName: Secret {} does not exist in namespace {}",
+                index, tls.secret_name, namespace
+            ));
+        }
+    }
+    Ok(problems)
+}
+
+/// Returns the init containers, the main containers and the pre-deploy hooks of a specification,
+/// each with the path of its field for error messages.
+fn containers(fs: &FoxServiceSpec) -> impl Iterator<Item = (String, &FoxServiceContainer)> {
+    let init_containers = fs.init_containers.iter().flatten().enumerate();
+    let init_containers =
+        init_containers.map(|(index, container)| (format!("initContainers[{}]", index), container));
+    let containers = fs.containers.iter().enumerate();
+    let containers =
+        containers.map(|(index, container)| (format!("containers[{}]", index), container));
+    let hooks = hook::pre_deploy_hooks(fs).iter().enumerate();
+    let hooks = hooks.map(|(index, container)| (format!("hooks.preDeploy[{}]", index), container));
+    init_containers.chain(containers).chain(hooks)
+}
+
+/// Checks that the PriorityClass of the pods exists. Pods referencing a missing PriorityClass are
+/// rejected by the API server, which only surfaces in the events of the ReplicaSet. The problem is
+/// returned for the caller to report, as the PriorityClass may still be created.
+///
+/// # Arguments
+/// - `client` - Kubernetes client to look up the PriorityClass with
+/// - `fs` - Fox service specification to check
+pub async fn check_priority_class(
+    client: Client,
+    fs: &FoxServiceSpec,
+) -> Result<Option<String>, Error> {
+    let name = match &fs.priority_class_name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let priority_classes: Api<PriorityClass> = Api::all(client);
+    if exists(priority_classes.get(name).await)? {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "priorityClassName: PriorityClass {} does not exist",
+            name
+        )))
+    }
+}
+
+/// Turns the result of a GET request into the object, if it exists.
+fn fetch<K>(result: Result<K, kube::Error>) -> Result<Option<K>, Error> {
+    match result {
+        Ok(object) => Ok(Some(object)),
+        Err(kube::Error::Api(response)) if response.code == 404 => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Turns the result of a GET request into whether the object exists.
+fn exists<K>(result: Result<K, kube::Error>) -> Result<bool, Error> {
+    fetch(result).map(|object| object.is_some())
+}
+
+/// Checks that a label selector is made of equality-based requirements separated by commas, as
+/// accepted by the Kubernetes API: `key=value`, `key==value`, `key!=value`, `key` or `!key`, e.g.,
+/// `track=canary,tier!=batch`.
+pub fn validate_selector(selector: &str) -> Result<(), Error> {
+    for requirement in selector.split(',').map(str::trim) {
+        let (key, value) = match requirement.split_once("!=") {
+            Some((key, value)) => (key, Some(value)),
+            None => match requirement.split_once('=') {
+                Some((key, value)) => (key, Some(value.strip_prefix('=').unwrap_or(value))),
+                None => (requirement.strip_prefix('!').unwrap_or(requirement), None),
+            },
+        };
+        if !is_label_key(key.trim()) || !value.is_none_or(|value| is_label_value(value.trim())) {
+            return Err(Error::UserInputError(format!(
+                "selector: {:?} is not a label requirement, e.g., track=canary",
+                requirement
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether a key is a label key: a name, optionally prefixed by a DNS subdomain and `/`.
+fn is_label_key(key: &str) -> bool {
+    let (prefix, name) = match key.split_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+    let prefixed =
+        prefix.is_none_or(|prefix| prefix.len() <= 253 && prefix.split('.').all(is_dns_label));
+    prefixed && !name.is_empty() && is_label_value(name)
+}
+
+/// Checks whether a value is a label value: at most 63 alphanumeric characters, dashes,
+/// underscores or dots, starting and ending with an alphanumeric character, or empty.
+fn is_label_value(value: &str) -> bool {
+    let alphanumeric_ends = value.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && value.ends_with(|c: char| c.is_ascii_alphanumeric());
+    value.is_empty()
+        || (value.len() <= 63
+            && alphanumeric_ends
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fox_k8s_crds::fox_service::builder::FoxServiceSpecBuilder;
+
+    /// A daemonset `FoxService` specification running a single container.
+    fn daemon_set() -> FoxServiceSpecBuilder {
+        FoxServiceSpec::builder()
+            .name("agent")
+            .workload(WorkloadKind::DaemonSet)
+            .container(|container| container.image("fluentd:1.13"))
+    }
+
+    fn reasons(fs: &FoxServiceSpec) -> Vec<&'static str> {
+        warnings(fs).into_iter().map(|(reason, _)| reason).collect()
+    }
+
+    #[test]
+    fn default_replicas_of_a_daemon_set_are_not_reported() {
+        let fs = daemon_set().build().unwrap();
+
+        assert_eq!(fs.replicas, Some(1));
+        assert!(!reasons(&fs).contains(&"IgnoredReplicas"));
+    }
+
+    #[test]
+    fn replicas_set_on_a_daemon_set_are_reported() {
+        let fs = daemon_set().replicas(3).build().unwrap();
+
+        assert!(reasons(&fs).contains(&"IgnoredReplicas"));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::fox_service;
+use fox_k8s_crds::fox_service::{v1beta1, FoxService, CONVERSION_WEBHOOK_PATH};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use kube::api::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview};
+use kube::api::DynamicObject;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::TryInto;
+use tokio::net::TcpListener;
+use tokio_native_tls::native_tls::{self, Identity};
+use tokio_native_tls::TlsAcceptor;
+
+/// Environment variable holding the path of the PKCS #12 archive with the certificate and key
+/// served by the webhook server. The server is only started if it is set.
+const TLS_PKCS12_VAR: &str = "FOX_WEBHOOK_TLS_PKCS12";
+
+/// Environment variable holding the password of the PKCS #12 archive, empty if not set.
+const TLS_PASSWORD_VAR: &str = "FOX_WEBHOOK_TLS_PASSWORD";
+
+/// Environment variable holding the port the webhook server listens on.
+const PORT_VAR: &str = "FOX_WEBHOOK_PORT";
+
+/// Port the webhook server listens on, unless `FOX_WEBHOOK_PORT` is set.
+const DEFAULT_PORT: u16 = 8443;
+
+/// Path of the mutating admission endpoint, defaulting `FoxService` resources. Conversions are
+/// served at `CONVERSION_WEBHOOK_PATH`.
+const MUTATE_PATH: &str = "/mutate";
+
+/// Settings of the admission webhook server, read from the environment.
+pub struct Config {
+    /// Certificate and key served to the API server
+    identity: Identity,
+    /// Port to listen on
+    port: u16,
+}
+
+impl Config {
+    /// Reads the settings of the webhook server from the environment.
+    ///
+    /// Returns `None` if `FOX_WEBHOOK_TLS_PKCS12` is not set, the webhook server is not run then.
+    pub fn from_env() -> Option<Result<Config, String>> {
+        let path = std::env::var(TLS_PKCS12_VAR).ok()?;
+        Some(Config::read(&path))
+    }
+
+    fn read(path: &str) -> Result<Config, String> {
+        let archive =
+            std::fs::read(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        let password = std::env::var(TLS_PASSWORD_VAR).unwrap_or_default();
+        let identity = Identity::from_pkcs12(&archive, &password)
+            .map_err(|error| format!("Could not load {}: {}", path, error))?;
+        let port = match std::env::var(PORT_VAR) {
+            Ok(port) => port
+                .parse()
+                .map_err(|_| format!("{} is not a port: {}", PORT_VAR, port))?,
+            Err(_) => DEFAULT_PORT,
+        };
+        Ok(Config { identity, port })
+    }
+}
+
+/// Serves the admission webhooks of the operator over HTTPS until an error occurs accepting
+/// connections. Failures of single connections are only logged.
+///
+/// # Arguments
+/// - `config` - Settings of the webhook server
+pub async fn serve(config: Config) -> Result<(), String> {
+    let Config { identity, port } = config;
+    let acceptor = native_tls::TlsAcceptor::new(identity)
+        .map_err(|error| format!("Could not set up TLS: {}", error))?;
+    let acceptor = TlsAcceptor::from(acceptor);
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .map_err(|error| format!("Could not listen on port {}: {}", port, error))?;
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|error| format!("Could not accept a connection: {}", error))?;
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let served = match acceptor.accept(stream).await {
+                Ok(stream) => Http::new()
+                    .serve_connection(stream, service_fn(handle))
+                    .await
+                    .map_err(|error| error.to_string()),
+                Err(error) => Err(error.to_string()),
+            };
+            if let Err(error) = served {
+                tracing::warn!(%error, "Webhook connection error");
+            }
+        });
+    }
+}
+
+/// Answers a request to the webhook server. Admission reviews posted to `/mutate` and conversion
+/// reviews posted to `/convert` are handled.
+async fn handle(request: Request<Body>) -> Result<Response<Body>, hyper::Error> {
+    let path = request.uri().path().to_owned();
+    if request.method() != Method::POST {
+        return Ok(not_found());
+    }
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    let review = match path.as_str() {
+        MUTATE_PATH => admission_review(&body),
+        CONVERSION_WEBHOOK_PATH => conversion_review(&body),
+        _ => return Ok(not_found()),
+    };
+    let body = serde_json::to_vec(&review).unwrap_or_default();
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    Ok(response)
+}
+
+/// Response to requests for anything but the webhooks.
+fn not_found() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
+
+/// Answers an admission review of a `FoxService` resource, see `mutate`.
+fn admission_review(body: &[u8]) -> Value {
+    let review: Result<AdmissionReview<DynamicObject>, _> = serde_json::from_slice(body);
+    let response = match review.map(TryInto::try_into) {
+        Ok(Ok(request)) => mutate(&request),
+        Ok(Err(error)) => AdmissionResponse::invalid(error),
+        Err(error) => AdmissionResponse::invalid(error),
+    };
+    let review: AdmissionReview<DynamicObject> = response.into_review();
+    let mut review = json!(review);
+    // The API server expects the patch encoded in base64, it is serialized as a list of bytes.
+    if let Some(patch) = review["response"].get_mut("patch") {
+        let bytes: Vec<u8> = serde_json::from_value(patch.take()).unwrap_or_default();
+        *patch = Value::String(base64::encode(bytes));
+    }
+    review
+}
+
+/// Answers a conversion review, converting `FoxService` objects between the served versions with
+/// `v1beta1::convert`. The conversion fails as a whole if any object can't be converted.
+fn conversion_review(body: &[u8]) -> Value {
+    let request = serde_json::from_slice::<ConversionReview>(body)
+        .map_err(|error| error.to_string())
+        .and_then(|review| review.request.ok_or_else(|| "request missing".to_string()));
+    let request = match request {
+        Ok(request) => request,
+        Err(error) => return json!(ConversionReview::response(String::new(), Err(error))),
+    };
+    let api_version = &request.desired_api_version;
+    let converted: Result<Vec<Value>, String> = request
+        .objects
+        .into_iter()
+        .map(|object| v1beta1::convert(object, api_version))
+        .collect();
+    json!(ConversionReview::response(request.uid, converted))
+}
+
+/// Request or response of the conversion webhook, an `apiextensions.k8s.io/v1` `ConversionReview`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversionReview {
+    api_version: String,
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<ConversionRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<ConversionResponse>,
+}
+
+/// Objects the API server asks the conversion webhook to convert.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversionRequest {
+    /// Identifies the request, copied to the response
+    uid: String,
+    /// API version to convert the objects to, e.g., `cbopt.com/v1`
+    #[serde(rename = "desiredAPIVersion")]
+    desired_api_version: String,
+    /// The objects to convert
+    objects: Vec<Value>,
+}
+
+/// Outcome of a conversion, with the converted objects in the order of the request.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversionResponse {
+    uid: String,
+    converted_objects: Vec<Value>,
+    result: Status,
+}
+
+impl ConversionReview {
+    /// Constructs the review answering a conversion request.
+    ///
+    /// # Arguments
+    /// - `uid` - Identifier of the request
+    /// - `converted` - The converted objects, or why the objects couldn't be converted
+    fn response(uid: String, converted: Result<Vec<Value>, String>) -> Self {
+        let (converted_objects, result) = match converted {
+            Ok(objects) => (
+                objects,
+                Status {
+                    status: Some("Success".to_string()),
+                    ..Status::default()
+                },
+            ),
+            Err(message) => (
+                Vec::new(),
+                Status {
+                    status: Some("Failure".to_string()),
+                    message: Some(message),
+                    ..Status::default()
+                },
+            ),
+        };
+        ConversionReview {
+            api_version: "apiextensions.k8s.io/v1".to_string(),
+            kind: "ConversionReview".to_string(),
+            request: None,
+            response: Some(ConversionResponse {
+                uid,
+                converted_objects,
+                result,
+            }),
+        }
+    }
+}
+
+/// Defaults a `FoxService` resource before it is persisted, so the stored specification shows the
+/// effective settings: the defaults of `FoxServiceSpec::apply_defaults` and of its deserialization,
+/// and the standard labels of `fox_service::standard_labels`. Fields already set are kept.
+///
+/// # Arguments
+/// - `request` - Admission request to create or update a `FoxService` resource
+///
+/// Returns the response admitting the resource, along with a JSON patch applying the defaults.
+fn mutate(request: &AdmissionRequest<DynamicObject>) -> AdmissionResponse {
+    let response = AdmissionResponse::from(request);
+    let object = match &request.object {
+        Some(object) => json!(object),
+        None => return response,
+    };
+    let mut fox_svc: FoxService = match serde_json::from_value(object.clone()) {
+        Ok(fox_svc) => fox_svc,
+        Err(error) => return response.deny(format!("Invalid FoxService: {}", error)),
+    };
+    fox_svc.spec.apply_defaults();
+    let labels = fox_service::standard_labels(&fox_svc);
+    fox_svc.metadata.labels = Some(labels);
+
+    let mut defaulted = object.clone();
+    fill_missing(&mut defaulted, &json!(fox_svc));
+    let patch = json_patch::diff(&object, &defaulted);
+    if patch.0.is_empty() {
+        return response;
+    }
+    match response.with_patch(patch) {
+        Ok(response) => response,
+        Err(error) => AdmissionResponse::from(request).deny(error),
+    }
+}
+
+/// Copies the fields of `defaults` missing or `null` in `target` over, descending into objects and
+/// into lists of the same length. Values set in `target` are never replaced, so fields stored in
+/// a former shape are left as they are.
+fn fill_missing(target: &mut Value, defaults: &Value) {
+    match (target, defaults) {
+        (Value::Object(target), Value::Object(defaults)) => {
+            for (key, default) in defaults.iter().filter(|(_, default)| !default.is_null()) {
+                match target.get_mut(key) {
+                    Some(value) if !value.is_null() => fill_missing(value, default),
+                    _ => {
+                        target.insert(key.to_owned(), default.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(defaults)) if target.len() == defaults.len() => {
+            for (value, default) in target.iter_mut().zip(defaults) {
+                fill_missing(value, default);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,10 +1,48 @@
 use fox_k8s_crds::fox_service::FoxServiceSpec;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use serde_json::Value;
 
+/// Renders the CRD as YAML and JSON, so a build fails early if it can't be serialized or misses
+/// fields the API server requires. The files are written to `FOX_CRD_OUT` if set, to `OUT_DIR`
+/// otherwise. The manifest applied to clusters is printed by `fox-operator crd`.
 fn main() {
-    let pwd = std::env::var("PWD").expect("Could not get PWD from environment");
-    let fox_service_crd = FoxServiceSpec::kubernetes_crd();
-    let schema_string =
-        serde_yaml::to_string(&fox_service_crd).expect("Could not get schema from RootSchema");
-    std::fs::write(format!("{}/foxservices.cbopt.com.yaml", pwd), schema_string)
+    println!("cargo:rerun-if-env-changed=FOX_CRD_OUT");
+    let out_dir = std::env::var("FOX_CRD_OUT")
+        .or_else(|_| std::env::var("OUT_DIR"))
+        .expect("Could not get FOX_CRD_OUT or OUT_DIR from environment");
+    let fox_service_crd = FoxServiceSpec::kubernetes_crd_manifest();
+    if let Err(problem) = check(&fox_service_crd) {
+        eprintln!("The foxservices.cbopt.com CRD is invalid: {}", problem);
+        std::process::exit(1);
+    }
+
+    let yaml = serde_yaml::to_string(&fox_service_crd).expect("Could not render the CRD as YAML");
+    let json =
+        serde_json::to_string_pretty(&fox_service_crd).expect("Could not render the CRD as JSON");
+    std::fs::create_dir_all(&out_dir).expect("Could not create the output directory");
+    std::fs::write(format!("{}/foxservices.cbopt.com.yaml", out_dir), yaml)
+        .expect("Could not write the YAML file");
+    std::fs::write(format!("{}/foxservices.cbopt.com.json", out_dir), json)
         .expect("Could not write the JSON file");
 }
+
+/// Checks the CRD has the fields the API server requires: it reads back as a
+/// `CustomResourceDefinition`, exactly one version is stored and every version has a schema.
+fn check(manifest: &Value) -> Result<(), String> {
+    let crd: CustomResourceDefinition =
+        serde_json::from_value(manifest.clone()).map_err(|error| error.to_string())?;
+    let versions = &crd.spec.versions;
+    if versions.iter().filter(|version| version.storage).count() != 1 {
+        return Err("exactly one version has to be stored".to_string());
+    }
+    let missing = versions.iter().find(|version| {
+        let schema = version.schema.as_ref();
+        schema
+            .and_then(|schema| schema.open_api_v3_schema.as_ref())
+            .is_none()
+    });
+    match missing {
+        Some(version) => Err(format!("version {} has no schema", version.name)),
+        None => Ok(()),
+    }
+}
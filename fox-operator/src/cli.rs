@@ -0,0 +1,450 @@
+use crate::logging;
+use fox_k8s_crds::fox_service::{v1beta1, FoxService, FoxServiceSpec};
+use fox_operator_lib::{rbac, validation, Scope};
+use kube::Resource;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::Duration;
+use tracing::Level;
+
+/// Usage of the operator itself, printed on invalid arguments.
+const OPERATOR_USAGE: &str = "usage: fox-operator [--namespaces <namespace>,...] \
+[--selector <key>=<value>,...] [--drain-timeout <seconds>] [--requeue-interval <seconds>] \
+[--log-level trace|debug|info|warn|error] [--log-format json|text]
+       fox-operator crd|rbac|validate ...";
+
+/// Usage of the `crd` subcommand, printed on invalid arguments.
+const CRD_USAGE: &str =
+    "usage: fox-operator crd [--conversion-webhook <namespace>/<service>] [--output <file>|-]";
+
+/// Usage of the `rbac` subcommand, printed on invalid arguments.
+const RBAC_USAGE: &str = "usage: fox-operator rbac [--namespace <namespace>] \
+[--namespaced|--watch-namespaces <namespace>,...] [--output <file>|-]";
+
+/// Usage of the `validate` subcommand, printed on invalid arguments.
+const VALIDATE_USAGE: &str =
+    "usage: fox-operator validate -f <file>|- [-f <file>...] [--format text|json] [--strict]";
+
+/// Time in seconds the reconciliations in progress are given to finish on shutdown, unless
+/// `--drain-timeout` is given.
+const DEFAULT_DRAIN_TIMEOUT_SECONDS: u64 = 25;
+
+/// Namespace the operator is assumed to run in, unless `--namespace` is given.
+const DEFAULT_NAMESPACE: &str = "default";
+
+/// Runs the subcommand named by the first command line argument, if any.
+///
+/// # Arguments
+/// - `args` - Command line arguments, without the name of the program.
+///
+/// Returns the outcome of the subcommand, or `None` if no subcommand is given and the operator is
+/// to be run.
+pub fn run(args: &[String]) -> Option<Result<(), String>> {
+    match args.split_first() {
+        Some((subcommand, args)) if subcommand == "crd" => Some(crd(args)),
+        Some((subcommand, args)) if subcommand == "rbac" => Some(rbac(args)),
+        Some((subcommand, args)) if subcommand == "validate" => Some(validate(args)),
+        _ => None,
+    }
+}
+
+/// Settings of the operator itself, given on the command line.
+pub struct Settings {
+    /// Level and format of the logs
+    pub logging: logging::Config,
+    /// Namespaces to watch `FoxService` resources in
+    pub scope: Scope,
+    /// Label selector of the `FoxService` resources to reconcile, all are if not given
+    pub selector: Option<String>,
+    /// Time the reconciliations in progress are given to finish on shutdown
+    pub drain_timeout: Duration,
+    /// Delay before checking again on a successfully reconciled `FoxService`
+    pub requeue_interval: Duration,
+}
+
+/// Parses the command line arguments of the operator itself, given when no subcommand is.
+///
+/// # Arguments
+/// - `args` - Command line arguments, without the name of the program.
+///   `--namespaces <namespace>,...` only watches `FoxService` resources in the listed namespaces,
+///   `--namespace <namespace>` in a single one. Both may be given repeatedly. `WATCH_NAMESPACE` is
+///   used if neither is given.
+///   `--selector <key>=<value>,...` only reconciles `FoxService` resources matching the label
+///   selector, e.g., to split them between a stable and a canary operator. `--drain-timeout
+///   <seconds>` sets the time reconciliations in progress are given to finish on shutdown, 25
+///   seconds if omitted, within the default termination grace period of pods.
+///   `--requeue-interval <seconds>` sets the delay before checking again on a successfully
+///   reconciled `FoxService`, 10 seconds if omitted. It must be positive.
+///   `--log-level <level>` sets the most verbose level logged, `info` if omitted. `--log-format
+///   json|text` sets how log lines are written, text if the standard output is a terminal and
+///   JSON otherwise if omitted.
+///
+/// Returns the settings, or the usage on invalid arguments.
+pub fn operator(args: &[String]) -> Result<Settings, String> {
+    let options = parse(
+        args,
+        &[
+            "--namespace",
+            "--namespaces",
+            "--selector",
+            "--drain-timeout",
+            "--requeue-interval",
+            "--log-level",
+            "--log-format",
+        ],
+        &[],
+        OPERATOR_USAGE,
+    )?;
+    let level = match options.get("--log-level").map(String::as_str) {
+        None => Level::INFO,
+        Some(level @ ("trace" | "debug" | "info" | "warn" | "error")) => {
+            level.parse().map_err(|_| OPERATOR_USAGE.to_string())?
+        }
+        Some(_) => return Err(OPERATOR_USAGE.to_string()),
+    };
+    let format = match options.get("--log-format").map(String::as_str) {
+        None => logging::Format::detect(),
+        Some("json") => logging::Format::Json,
+        Some("text") => logging::Format::Text,
+        Some(_) => return Err(OPERATOR_USAGE.to_string()),
+    };
+    let mut namespaces = options.all("--namespace").to_vec();
+    namespaces.extend_from_slice(options.all("--namespaces"));
+    let scope = if namespaces.is_empty() {
+        Scope::from_env()
+    } else {
+        match Scope::parse(&namespaces.join(",")) {
+            Scope::Cluster => return Err(OPERATOR_USAGE.to_string()),
+            scope => scope,
+        }
+    };
+    let selector = options.get("--selector").cloned();
+    if let Some(selector) = &selector {
+        let message = match validation::validate_selector(selector) {
+            Ok(()) => None,
+            Err(fox_operator_lib::Error::UserInputError(message)) => Some(message),
+            Err(error) => Some(error.to_string()),
+        };
+        if let Some(message) = message {
+            return Err(format!("{}\n{}", message, OPERATOR_USAGE));
+        }
+    }
+    let drain_timeout = match options.get("--drain-timeout") {
+        Some(seconds) => seconds.parse().map_err(|_| OPERATOR_USAGE.to_string())?,
+        None => DEFAULT_DRAIN_TIMEOUT_SECONDS,
+    };
+    let requeue_interval = match options.get("--requeue-interval") {
+        Some(seconds) => match seconds.parse() {
+            Ok(seconds) if seconds > 0 => Duration::from_secs(seconds),
+            _ => {
+                return Err(format!(
+                    "--requeue-interval must be a positive number of seconds, not {}\n{}",
+                    seconds, OPERATOR_USAGE
+                ))
+            }
+        },
+        None => fox_operator_lib::DEFAULT_REQUEUE_INTERVAL,
+    };
+    Ok(Settings {
+        logging: logging::Config { level, format },
+        scope,
+        selector,
+        drain_timeout: Duration::from_secs(drain_timeout),
+        requeue_interval,
+    })
+}
+
+/// Runs the `crd` subcommand: writes the `foxservices.cbopt.com` CRD as applied to clusters in
+/// YAML, e.g., for `fox-operator crd | kubectl apply -f -`.
+///
+/// # Arguments
+/// - `args` - Command line arguments following `crd`. `--conversion-webhook <namespace>/<service>`
+///   converts objects between the versions through the webhook of the operator behind the
+///   Service. `--output <file>` writes the CRD to the file, it is written to the standard output
+///   if omitted or `-`.
+fn crd(args: &[String]) -> Result<(), String> {
+    let options = parse(args, &["--conversion-webhook", "--output"], &[], CRD_USAGE)?;
+    let manifest = match options.get("--conversion-webhook") {
+        Some(service) => match service.split_once('/') {
+            Some((namespace, name)) => {
+                FoxServiceSpec::kubernetes_crd_manifest_with_conversion(namespace, name)
+            }
+            None => return Err(CRD_USAGE.to_string()),
+        },
+        None => FoxServiceSpec::kubernetes_crd_manifest(),
+    };
+    let manifest = serde_yaml::to_string(&manifest)
+        .map_err(|error| format!("Could not serialize the CRD: {}", error))?;
+    write(options.get("--output"), &manifest)
+}
+
+/// Runs the `rbac` subcommand: writes the service account of the operator and the roles granting
+/// it its permissions in YAML, e.g., for `fox-operator rbac | kubectl apply -f -`.
+///
+/// # Arguments
+/// - `args` - Command line arguments following `rbac`. `--namespace <namespace>` sets the
+///   namespace the operator runs in, `--namespaced` grants permissions in that namespace only.
+///   `--watch-namespaces <namespace>,...` grants permissions in the namespaces watched by the
+///   operator only, `WATCH_NAMESPACE` is used if neither is given. `--output <file>` writes the
+///   manifests to the file, they are written to the standard output if omitted or `-`.
+fn rbac(args: &[String]) -> Result<(), String> {
+    let options = parse(
+        args,
+        &["--namespace", "--watch-namespaces", "--output"],
+        &["--namespaced"],
+        RBAC_USAGE,
+    )?;
+    let namespace = options
+        .get("--namespace")
+        .map_or(DEFAULT_NAMESPACE, String::as_str);
+    let scope = match (
+        options.get("--watch-namespaces"),
+        options.contains("--namespaced"),
+    ) {
+        (Some(_), true) => return Err(RBAC_USAGE.to_string()),
+        (Some(watched), false) => match Scope::parse(watched) {
+            Scope::Cluster => return Err(RBAC_USAGE.to_string()),
+            scope => scope,
+        },
+        (None, true) => Scope::parse(namespace),
+        (None, false) => Scope::from_env(),
+    };
+    let mut manifests = String::new();
+    for manifest in rbac::manifests(namespace, &scope) {
+        let manifest = serde_yaml::to_string(&manifest)
+            .map_err(|error| format!("Could not serialize the manifests: {}", error))?;
+        manifests.push_str(&manifest);
+    }
+    write(options.get("--output"), &manifests)
+}
+
+/// Runs the `validate` subcommand: checks `FoxService` manifests the way the operator does before
+/// applying them, without a cluster, e.g., in CI pipelines. Documents of other kinds are skipped.
+///
+/// # Arguments
+/// - `args` - Command line arguments following `validate`. `-f <file>` reads the manifests from
+///   the file, which may hold several YAML documents, or from the standard input if `-`. It may be
+///   given repeatedly. `--format json` prints the problems found as JSON rather than text.
+///   `--strict` reports fields of the specification not recognized as errors rather than warnings.
+///
+/// Fails if any error is found.
+fn validate(args: &[String]) -> Result<(), String> {
+    let options = parse(args, &["-f", "--format"], &["--strict"], VALIDATE_USAGE)?;
+    let json = match options.get("--format").map(String::as_str) {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => return Err(VALIDATE_USAGE.to_string()),
+    };
+    if options.all("-f").is_empty() {
+        return Err(VALIDATE_USAGE.to_string());
+    }
+
+    let mut findings = Vec::new();
+    for file in options.all("-f") {
+        let content = if file == "-" {
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .map(|_| content)
+        } else {
+            std::fs::read_to_string(file)
+        };
+        let content = content.map_err(|error| format!("Could not read {}: {}", file, error))?;
+        for (index, document) in serde_yaml::Deserializer::from_str(&content).enumerate() {
+            let mut finding = |name: Option<String>, severity, field: String, message| {
+                findings.push(Finding {
+                    file: file.to_owned(),
+                    document: index + 1,
+                    name,
+                    severity,
+                    field,
+                    message,
+                })
+            };
+            let document = match Value::deserialize(document) {
+                Ok(document) => document,
+                Err(error) => {
+                    // The rest of the file can't be read either.
+                    finding(None, ERROR, String::new(), error.to_string());
+                    break;
+                }
+            };
+            let metadata = &document["metadata"];
+            let name = match (metadata["namespace"].as_str(), metadata["name"].as_str()) {
+                (Some(namespace), Some(name)) => Some(format!("{}/{}", namespace, name)),
+                (None, name) => name.map(str::to_owned),
+                (Some(_), None) => None,
+            };
+            for (severity, field, message) in check_manifest(document, options.contains("--strict"))
+            {
+                finding(name.clone(), severity, field, message);
+            }
+        }
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == ERROR)
+        .count();
+    let output = if json {
+        serde_json::to_string_pretty(&findings).unwrap_or_default() + "\n"
+    } else {
+        findings
+            .iter()
+            .map(|finding| format!("{}\n", finding))
+            .collect()
+    };
+    write(None, &output)?;
+    match errors {
+        0 => Ok(()),
+        errors => Err(format!("{} error(s) found", errors)),
+    }
+}
+
+/// Severity of problems the operator refuses to apply a manifest for.
+const ERROR: &str = "error";
+
+/// Severity of problems the operator merely warns about.
+const WARNING: &str = "warning";
+
+/// A problem found by the `validate` subcommand in a `FoxService` manifest.
+#[derive(Serialize)]
+struct Finding {
+    /// File the manifest was read from, `-` for the standard input
+    file: String,
+    /// Position of the YAML document in the file, starting at 1
+    document: usize,
+    /// Name of the `FoxService`, if known
+    name: Option<String>,
+    /// `error` or `warning`
+    severity: &'static str,
+    /// Path of the offending field, empty if the problem isn't tied to one
+    field: String,
+    /// What is wrong
+    message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.file, self.document)?;
+        if let Some(name) = &self.name {
+            write!(f, " {}", name)?;
+        }
+        write!(f, ": {}: ", self.severity)?;
+        if !self.field.is_empty() {
+            write!(f, "{}: ", self.field)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Checks a manifest with the checks the operator runs before applying a `FoxService`.
+///
+/// # Arguments
+/// - `document` - The manifest, of any kind and version
+/// - `strict` - Whether fields of the specification not recognized are errors
+///
+/// Returns the problems found as triples of severity, field and message. Manifests of other kinds
+/// have none.
+fn check_manifest(document: Value, strict: bool) -> Vec<(&'static str, String, String)> {
+    if document.is_null() || document["kind"] != "FoxService" {
+        return Vec::new();
+    }
+    let fox_svc: FoxService = match v1beta1::convert(document, &FoxService::api_version(&()))
+        .and_then(|document| serde_json::from_value(document).map_err(|error| error.to_string()))
+    {
+        Ok(fox_svc) => fox_svc,
+        Err(error) => return vec![(ERROR, String::new(), error)],
+    };
+
+    let spec = &fox_svc.spec;
+    let mut findings: Vec<_> = spec
+        .validate()
+        .into_iter()
+        .map(|error| (ERROR, format!("spec.{}", error.field), error.message))
+        .collect();
+    let (unknown, message) = if strict {
+        (ERROR, "not recognized")
+    } else {
+        (WARNING, "not recognized, ignored")
+    };
+    for field in spec.unknown_field_paths() {
+        findings.push((unknown, format!("spec.{}", field), message.to_string()));
+    }
+    for (_, message) in validation::warnings(spec) {
+        findings.push((WARNING, String::new(), message));
+    }
+    findings
+}
+
+/// Flags given to a subcommand, with the values given for each of them.
+struct Options(BTreeMap<String, Vec<String>>);
+
+impl Options {
+    /// The value of a flag, the last one if given repeatedly.
+    fn get(&self, flag: &str) -> Option<&String> {
+        self.0.get(flag).and_then(|values| values.last())
+    }
+
+    /// All values of a flag, in the order given.
+    fn all(&self, flag: &str) -> &[String] {
+        self.0.get(flag).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether a flag is given.
+    fn contains(&self, flag: &str) -> bool {
+        self.0.contains_key(flag)
+    }
+}
+
+/// Parses the arguments of a subcommand, given as `--flag value`, `--flag=value` or `--switch`.
+///
+/// # Arguments
+/// - `args` - Command line arguments following the subcommand.
+/// - `flags` - Flags taking a value, they may be given repeatedly.
+/// - `switches` - Flags taking no value, these map to an empty value.
+/// - `usage` - Usage of the subcommand, returned as the error on unknown or incomplete flags.
+///
+/// Returns the values of each flag given.
+fn parse(
+    args: &[String],
+    flags: &[&str],
+    switches: &[&str],
+    usage: &str,
+) -> Result<Options, String> {
+    let mut options: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let value = if switches.contains(&flag) && value.is_none() {
+            String::new()
+        } else if flags.contains(&flag) {
+            match value.or_else(|| args.next().cloned()) {
+                Some(value) => value,
+                None => return Err(usage.to_string()),
+            }
+        } else {
+            return Err(usage.to_string());
+        };
+        options.entry(flag.to_string()).or_default().push(value);
+    }
+    Ok(Options(options))
+}
+
+/// Writes the output of a subcommand to a file, or to the standard output if `output` is omitted
+/// or `-`.
+fn write(output: Option<&String>, content: &str) -> Result<(), String> {
+    match output.filter(|output| *output != "-") {
+        Some(path) => std::fs::write(path, content)
+            .map_err(|error| format!("Could not write to {}: {}", path, error)),
+        None => std::io::stdout()
+            .write_all(content.as_bytes())
+            .map_err(|error| format!("Could not write to the standard output: {}", error)),
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::IsTerminal;
+use tracing::Level;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+
+/// How log lines are written to `stdout`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// One JSON object per line, for log pipelines
+    Json,
+    /// Human-readable lines with colors
+    Text,
+}
+
+impl Format {
+    /// Text when a person is likely watching the logs, i.e., `stdout` is a terminal, JSON
+    /// otherwise.
+    pub fn detect() -> Self {
+        if std::io::stdout().is_terminal() {
+            Format::Text
+        } else {
+            Format::Json
+        }
+    }
+}
+
+/// Logging settings of the operator, given on the command line.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Most verbose level logged, unless `RUST_LOG` sets one
+    pub level: Level,
+    /// How log lines are written
+    pub format: Format,
+}
+
+/// Installs the global subscriber writing the logs of the operator to `stdout`, including the
+/// fields of the spans they were logged in, e.g., the `FoxService` being reconciled. The settings
+/// are logged once installed.
+///
+/// # Arguments
+/// - `config` - Level and format of the logs. Directives in the `RUST_LOG` environment variable
+///   are applied on top of the level, e.g., `fox_operator_lib=debug` logs the reconciler in
+///   detail and `kube=debug` the requests made to the Kubernetes API. A level given without a target in `RUST_LOG` replaces the level.
+///
+/// Fails if `RUST_LOG` holds invalid directives.
+pub fn init(config: Config) -> Result<(), String> {
+    let directives = std::env::var("RUST_LOG").unwrap_or_default();
+    let mut filter = EnvFilter::builder()
+        .parse(&directives)
+        .map_err(|error| format!("Invalid RUST_LOG directives {}: {}", directives, error))?;
+    let replaces_level = directives
+        .split(',')
+        .map(str::trim)
+        .any(|directive| !directive.is_empty() && directive.parse::<LevelFilter>().is_ok());
+    if !replaces_level {
+        filter = filter.add_directive(LevelFilter::from_level(config.level).into());
+    }
+    let filter_description = filter.to_string();
+    // The settings are logged regardless of the level, e.g., to tell why logs are missing.
+    let directive = format!("{}=info", module_path!())
+        .parse()
+        .map_err(|error| format!("Invalid logging directive: {}", error))?;
+    let filter = filter.add_directive(directive);
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.format {
+        Format::Json => builder.json().with_current_span(true).init(),
+        Format::Text => builder.init(),
+    }
+    tracing::info!(
+        level = %config.level,
+        format = ?config.format,
+        filter = %filter_description,
+        "Logging configured"
+    );
+    Ok(())
+}